@@ -0,0 +1,77 @@
+## Notifications of the breaks
+
+take-a-pause = Mach eine Pause!
+take-a-long-pause = Mach eine lange Pause!
+work = Arbeit
+short-break = Kurze Pause
+short-break-counter = Kurze Pause ({ $current }/{ $total })
+long-break = Lange Pause
+skip-break = Pause überspringen
+done-with = Erledigt: { $task }
+today-progress = { $completed }/{ $goal } heute
+streak = { $streak } Pomodoros in Folge
+break-debt =
+    { $skipped } Pausen in Folge übersprungen, diese ist { $extension } länger
+break-snoozed = Pause verschoben { $count }/{ $limit }
+
+## Notifications and dialog of the work intervals
+
+back-to-work = Zurück an die Arbeit
+next-break-at = Nächste Pause um { $time }
+task = Aufgabe: { $task }
+break-over = Die Pause ist vorbei
+start-when-back = Starte das Arbeitsintervall, wenn du zurück bist
+start = Starten
+start-working = Mit der Arbeit beginnen
+until-next-break = { $time } bis zur nächsten Pause
+away = { $away } abwesend
+away-counted-as-break = { $away } abwesend, als Pause gezählt
+away-started-over = { $away } abwesend, das Arbeitsintervall beginnt von vorn
+
+## Goals and focus mode
+
+daily-goal-reached = Tagesziel erreicht
+pomodoros-completed-today =
+    { $completed ->
+        [one] 1 Pomodoro heute abgeschlossen, gut gemacht!
+       *[other] { $completed } Pomodoros heute abgeschlossen, gut gemacht!
+    }
+focus-over = Fokusmodus beendet
+focus-skipped =
+    { $skipped ->
+        [0] Keine Pause wurde übersprungen
+        [one] 1 Pause wurde übersprungen, mach die nächste
+       *[other] { $skipped } Pausen wurden übersprungen, mach die nächste
+    }
+
+## Survey and quiet hours
+
+feeling-refreshed = Erholt?
+how-was-the-break = Wie war die Pause?
+during-quiet-hours = Während der Ruhezeit
+
+## Weekly report
+
+last-week = Letzte Woche
+report-week = Woche vom { $monday } bis { $sunday }
+report-pomodoros =
+    { $completed ->
+        [one] 1 Pomodoro abgeschlossen, { $abandoned } abgebrochen
+       *[other] { $completed } Pomodoros abgeschlossen, { $abandoned } abgebrochen
+    }
+report-focus = { $hours }h { $minutes }m Fokus
+report-best-streak = höchstens { $streak } Pomodoros in Folge
+report-breaks = { $percent }% der Pausen gemacht ({ $taken } von { $breaks })
+
+## Tray icon
+
+tray-left = { $phase }: noch { $time }
+tray-elapsed = { $phase }: seit { $time }
+tray-paused = Angehalten
+tray-day-off = Freier Tag
+tray-focusing = Fokus seit { $time }
+
+## Crash
+
+crashed = passata ist abgestürzt
+crash-status = Der letzte Status liegt in { $path }
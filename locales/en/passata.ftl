@@ -0,0 +1,77 @@
+## Notifications of the breaks
+
+take-a-pause = Take a pause!
+take-a-long-pause = Take a long pause!
+work = Work
+short-break = Short break
+short-break-counter = Short break ({ $current }/{ $total })
+long-break = Long break
+skip-break = Skip break
+done-with = Done with: { $task }
+today-progress = { $completed }/{ $goal } today
+streak = { $streak } pomodoros in a row
+break-debt =
+    { $skipped } breaks skipped in a row, this one is { $extension } longer
+break-snoozed = Break snoozed { $count }/{ $limit }
+
+## Notifications and dialog of the work intervals
+
+back-to-work = Back to work
+next-break-at = Next break at { $time }
+task = Task: { $task }
+break-over = The break is over
+start-when-back = Start the work interval when you are back
+start = Start
+start-working = Start working
+until-next-break = { $time } until next break
+away = Away for { $away }
+away-counted-as-break = Away for { $away }, counted as the break
+away-started-over = Away for { $away }, the work interval started over
+
+## Goals and focus mode
+
+daily-goal-reached = Daily goal reached
+pomodoros-completed-today =
+    { $completed ->
+        [one] 1 pomodoro completed today, well done!
+       *[other] { $completed } pomodoros completed today, well done!
+    }
+focus-over = Focus is over
+focus-skipped =
+    { $skipped ->
+        [0] No breaks have been skipped
+        [one] 1 break has been skipped, take the next one
+       *[other] { $skipped } breaks have been skipped, take the next one
+    }
+
+## Survey and quiet hours
+
+feeling-refreshed = Feeling refreshed?
+how-was-the-break = How was the break?
+during-quiet-hours = During the quiet hours
+
+## Weekly report
+
+last-week = Last week
+report-week = Week from { $monday } to { $sunday }
+report-pomodoros =
+    { $completed ->
+        [one] 1 pomodoro completed, { $abandoned } abandoned
+       *[other] { $completed } pomodoros completed, { $abandoned } abandoned
+    }
+report-focus = { $hours }h { $minutes }m of focus
+report-best-streak = { $streak } pomodoros in a row at best
+report-breaks = { $percent }% of the breaks taken ({ $taken } of { $breaks })
+
+## Tray icon
+
+tray-left = { $phase }: { $time } left
+tray-elapsed = { $phase }: { $time } elapsed
+tray-paused = Paused
+tray-day-off = Day off
+tray-focusing = Focusing for { $time }
+
+## Crash
+
+crashed = passata crashed
+crash-status = The last status is in { $path }
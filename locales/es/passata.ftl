@@ -0,0 +1,77 @@
+## Notifications of the breaks
+
+take-a-pause = ¡Tómate un descanso!
+take-a-long-pause = ¡Tómate un descanso largo!
+work = Trabajo
+short-break = Descanso corto
+short-break-counter = Descanso corto ({ $current }/{ $total })
+long-break = Descanso largo
+skip-break = Saltar el descanso
+done-with = Terminado: { $task }
+today-progress = { $completed }/{ $goal } hoy
+streak = { $streak } pomodoros seguidos
+break-debt =
+    { $skipped } descansos saltados seguidos, este dura { $extension } más
+break-snoozed = Descanso pospuesto { $count }/{ $limit }
+
+## Notifications and dialog of the work intervals
+
+back-to-work = De vuelta al trabajo
+next-break-at = Próximo descanso a las { $time }
+task = Tarea: { $task }
+break-over = El descanso ha terminado
+start-when-back = Empieza el intervalo de trabajo cuando vuelvas
+start = Empezar
+start-working = Empezar a trabajar
+until-next-break = { $time } hasta el próximo descanso
+away = Ausente durante { $away }
+away-counted-as-break = Ausente durante { $away }, contado como descanso
+away-started-over = Ausente durante { $away }, el intervalo de trabajo ha vuelto a empezar
+
+## Goals and focus mode
+
+daily-goal-reached = Objetivo diario alcanzado
+pomodoros-completed-today =
+    { $completed ->
+        [one] 1 pomodoro completado hoy, ¡bien hecho!
+       *[other] { $completed } pomodoros completados hoy, ¡bien hecho!
+    }
+focus-over = Modo concentración terminado
+focus-skipped =
+    { $skipped ->
+        [0] No se ha saltado ningún descanso
+        [one] Se ha saltado 1 descanso, tómate el siguiente
+       *[other] Se han saltado { $skipped } descansos, tómate el siguiente
+    }
+
+## Survey and quiet hours
+
+feeling-refreshed = ¿Te sientes descansado?
+how-was-the-break = ¿Qué tal el descanso?
+during-quiet-hours = Durante las horas de silencio
+
+## Weekly report
+
+last-week = La semana pasada
+report-week = Semana del { $monday } al { $sunday }
+report-pomodoros =
+    { $completed ->
+        [one] 1 pomodoro completado, { $abandoned } abandonados
+       *[other] { $completed } pomodoros completados, { $abandoned } abandonados
+    }
+report-focus = { $hours }h { $minutes }m de concentración
+report-best-streak = { $streak } pomodoros seguidos como máximo
+report-breaks = { $percent } % de los descansos tomados ({ $taken } de { $breaks })
+
+## Tray icon
+
+tray-left = { $phase }: quedan { $time }
+tray-elapsed = { $phase }: { $time } transcurridos
+tray-paused = En pausa
+tray-day-off = Día libre
+tray-focusing = Concentrado desde hace { $time }
+
+## Crash
+
+crashed = passata se ha bloqueado
+crash-status = El último estado está en { $path }
@@ -0,0 +1,77 @@
+## Notifications of the breaks
+
+take-a-pause = Faites une pause !
+take-a-long-pause = Faites une longue pause !
+work = Travail
+short-break = Courte pause
+short-break-counter = Courte pause ({ $current }/{ $total })
+long-break = Longue pause
+skip-break = Passer la pause
+done-with = Terminé : { $task }
+today-progress = { $completed }/{ $goal } aujourd'hui
+streak = { $streak } pomodoros d'affilée
+break-debt =
+    { $skipped } pauses sautées d'affilée, celle-ci dure { $extension } de plus
+break-snoozed = Pause repoussée { $count }/{ $limit }
+
+## Notifications and dialog of the work intervals
+
+back-to-work = Au travail
+next-break-at = Prochaine pause à { $time }
+task = Tâche : { $task }
+break-over = La pause est terminée
+start-when-back = Lancez l'intervalle de travail à votre retour
+start = Démarrer
+start-working = Se remettre au travail
+until-next-break = { $time } avant la prochaine pause
+away = Absent pendant { $away }
+away-counted-as-break = Absent pendant { $away }, compté comme pause
+away-started-over = Absent pendant { $away }, l'intervalle de travail recommence
+
+## Goals and focus mode
+
+daily-goal-reached = Objectif du jour atteint
+pomodoros-completed-today =
+    { $completed ->
+        [one] { $completed } pomodoro terminé aujourd'hui, bravo !
+       *[other] { $completed } pomodoros terminés aujourd'hui, bravo !
+    }
+focus-over = Fin du mode concentration
+focus-skipped =
+    { $skipped ->
+        [0] Aucune pause n'a été sautée
+        [one] 1 pause a été sautée, prenez la suivante
+       *[other] { $skipped } pauses ont été sautées, prenez la suivante
+    }
+
+## Survey and quiet hours
+
+feeling-refreshed = Reposé ?
+how-was-the-break = Comment était la pause ?
+during-quiet-hours = Pendant les heures calmes
+
+## Weekly report
+
+last-week = La semaine dernière
+report-week = Semaine du { $monday } au { $sunday }
+report-pomodoros =
+    { $completed ->
+        [one] { $completed } pomodoro terminé, { $abandoned } abandonné(s)
+       *[other] { $completed } pomodoros terminés, { $abandoned } abandonné(s)
+    }
+report-focus = { $hours }h { $minutes }m de concentration
+report-best-streak = { $streak } pomodoros d'affilée au mieux
+report-breaks = { $percent } % des pauses prises ({ $taken } sur { $breaks })
+
+## Tray icon
+
+tray-left = { $phase } : { $time } restantes
+tray-elapsed = { $phase } : { $time } écoulées
+tray-paused = En pause
+tray-day-off = Jour de repos
+tray-focusing = Concentration depuis { $time }
+
+## Crash
+
+crashed = passata a planté
+crash-status = Le dernier état est dans { $path }
@@ -0,0 +1,77 @@
+## Notifications of the breaks
+
+take-a-pause = Fai una pausa!
+take-a-long-pause = Fai una pausa lunga!
+work = Lavoro
+short-break = Pausa breve
+short-break-counter = Pausa breve ({ $current }/{ $total })
+long-break = Pausa lunga
+skip-break = Salta la pausa
+done-with = Completato: { $task }
+today-progress = { $completed }/{ $goal } oggi
+streak = { $streak } pomodori di fila
+break-debt =
+    { $skipped } pause saltate di fila, questa dura { $extension } in più
+break-snoozed = Pausa rimandata { $count }/{ $limit }
+
+## Notifications and dialog of the work intervals
+
+back-to-work = Al lavoro
+next-break-at = Prossima pausa alle { $time }
+task = Attività: { $task }
+break-over = La pausa è finita
+start-when-back = Avvia l'intervallo di lavoro quando torni
+start = Avvia
+start-working = Inizia a lavorare
+until-next-break = { $time } alla prossima pausa
+away = Assente per { $away }
+away-counted-as-break = Assente per { $away }, contato come pausa
+away-started-over = Assente per { $away }, l'intervallo di lavoro è ricominciato
+
+## Goals and focus mode
+
+daily-goal-reached = Obiettivo giornaliero raggiunto
+pomodoros-completed-today =
+    { $completed ->
+        [one] 1 pomodoro completato oggi, ben fatto!
+       *[other] { $completed } pomodori completati oggi, ben fatto!
+    }
+focus-over = Modalità concentrazione terminata
+focus-skipped =
+    { $skipped ->
+        [0] Nessuna pausa saltata
+        [one] 1 pausa saltata, fai la prossima
+       *[other] { $skipped } pause saltate, fai la prossima
+    }
+
+## Survey and quiet hours
+
+feeling-refreshed = Ti senti riposato?
+how-was-the-break = Com'è andata la pausa?
+during-quiet-hours = Durante le ore di silenzio
+
+## Weekly report
+
+last-week = La settimana scorsa
+report-week = Settimana dal { $monday } al { $sunday }
+report-pomodoros =
+    { $completed ->
+        [one] 1 pomodoro completato, { $abandoned } abbandonati
+       *[other] { $completed } pomodori completati, { $abandoned } abbandonati
+    }
+report-focus = { $hours }h { $minutes }m di concentrazione
+report-best-streak = { $streak } pomodori di fila al massimo
+report-breaks = { $percent }% delle pause fatte ({ $taken } su { $breaks })
+
+## Tray icon
+
+tray-left = { $phase }: mancano { $time }
+tray-elapsed = { $phase }: trascorsi { $time }
+tray-paused = In pausa
+tray-day-off = Giorno libero
+tray-focusing = Concentrazione da { $time }
+
+## Crash
+
+crashed = passata si è bloccato
+crash-status = L'ultimo stato è in { $path }
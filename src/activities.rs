@@ -0,0 +1,66 @@
+//! Suggestions of what to do during a break, shown in the body of the break notification.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{timer::Phase, Passata};
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ActivitiesConfig {
+    pub short_break: Vec<String>,
+    pub long_break: Vec<String>,
+    pub order: ActivityOrder,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActivityOrder {
+    /// Go through the activities in the order they are listed
+    #[default]
+    Rotate,
+    Random,
+}
+
+/// Position of the next activity in each list, when rotating them
+#[derive(Default)]
+pub struct ActivityRotation {
+    short_break: usize,
+    long_break: usize,
+}
+
+impl Passata {
+    /// Choose the activity suggested for the break that just started
+    pub fn pick_activity(&mut self) {
+        let config = &self.config.activities;
+        let (activities, position) = match self.phase {
+            Phase::Work => {
+                self.activity = None;
+                return;
+            }
+            Phase::ShortBreak => (&config.short_break, &mut self.activity_rotation.short_break),
+            Phase::LongBreak => (&config.long_break, &mut self.activity_rotation.long_break),
+        };
+        if activities.is_empty() {
+            self.activity = None;
+            return;
+        }
+        let index = match config.order {
+            ActivityOrder::Rotate => {
+                let index = *position % activities.len();
+                *position = index + 1;
+                index
+            }
+            // a fresh clock reading is random enough to pick a suggestion
+            ActivityOrder::Random => {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .subsec_nanos() as usize
+                    % activities.len()
+            }
+        };
+        self.activity = Some(activities[index].clone());
+    }
+}
@@ -0,0 +1,143 @@
+//! Timer algorithms deciding the length of the work intervals and of the breaks: the classic
+//! pomodoro from the configured timings, the fixed 52/17 rhythm, Flowtime, where the break is
+//! proportional to the focus time before it, and the adaptive one, where the break grows with
+//! the input intensity of the work interval.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{profile::Timings, timer::Phase, Passata};
+
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Algorithm {
+    /// Work intervals and short breaks, with a long break every few of them, as configured
+    #[default]
+    Pomodoro,
+    /// 52 minutes of work followed by 17 minutes of break
+    #[serde(rename = "52-17")]
+    FiftyTwoSeventeen,
+    /// Work until skipping to the break, at most for the work interval; the break lasts a
+    /// fraction of the time worked
+    Flowtime,
+    /// The configured timings, with the short break scaled by the intensity of the work
+    /// interval before it
+    Adaptive,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct FlowtimeConfig {
+    /// The break lasts the time worked divided by this
+    pub ratio: u32,
+    /// Shortest break, after a short focus
+    #[serde(with = "humantime_serde")]
+    pub min_break: Duration,
+}
+
+impl Default for FlowtimeConfig {
+    fn default() -> Self {
+        Self {
+            ratio: 5,
+            min_break: Duration::from_secs(2 * 60),
+        }
+    }
+}
+
+/// How the intensity of a work interval is scored, from 0 to 1
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IntensityScore {
+    /// Share of the work interval spent typing or moving the mouse
+    #[default]
+    Activity,
+    /// Longest stretch of input without an idle gap, as a share of the work interval
+    LongestStretch,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct AdaptiveConfig {
+    pub score: IntensityScore,
+    /// Break after a work interval without any input
+    #[serde(with = "humantime_serde")]
+    pub min_break: Duration,
+    /// Break after a work interval of constant input
+    #[serde(with = "humantime_serde")]
+    pub max_break: Duration,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            score: IntensityScore::default(),
+            min_break: Duration::from_secs(3 * 60),
+            max_break: Duration::from_secs(8 * 60),
+        }
+    }
+}
+
+impl Passata {
+    /// The timings of the algorithm, from the `configured` ones
+    pub fn algorithm_timings(&self, configured: Timings) -> Timings {
+        match self.config.algorithm {
+            Algorithm::Pomodoro => configured,
+            // the breaks are projected as if the intensity stays the one so far
+            Algorithm::Adaptive => Timings {
+                short_break: self.adaptive_break(self.worked_so_far()),
+                ..configured
+            },
+            Algorithm::FiftyTwoSeventeen => Timings {
+                work_interval: Duration::from_secs(52 * 60),
+                short_break: Duration::from_secs(17 * 60),
+                long_break: None,
+                short_breaks_before_long_break: None,
+            },
+            // the breaks are projected as if the whole work interval is worked
+            Algorithm::Flowtime => Timings {
+                short_break: self.flowtime_break(configured.work_interval),
+                long_break: None,
+                short_breaks_before_long_break: None,
+                ..configured
+            },
+        }
+    }
+
+    /// Length of the short break after `worked` of work
+    pub fn short_break_after(&self, worked: Duration) -> Duration {
+        if self.stopwatch {
+            return self.flowtime_break(worked);
+        }
+        match self.config.algorithm {
+            Algorithm::Flowtime => self.flowtime_break(worked),
+            Algorithm::Adaptive => self.adaptive_break(worked),
+            Algorithm::Pomodoro | Algorithm::FiftyTwoSeventeen => self.short_break(),
+        }
+    }
+
+    fn flowtime_break(&self, worked: Duration) -> Duration {
+        let flowtime = &self.config.flowtime;
+        (worked / flowtime.ratio).max(flowtime.min_break)
+    }
+
+    /// Work of the current work interval, or of the one before the break
+    fn worked_so_far(&self) -> Duration {
+        match self.phase {
+            Phase::Work => self.phase_length.saturating_sub(self.remaining()),
+            Phase::ShortBreak | Phase::LongBreak => self.worked_before_break,
+        }
+    }
+
+    /// Break between min-break and max-break, by the intensity score of `worked`; the
+    /// shortest one when the intensity is unknown
+    fn adaptive_break(&self, worked: Duration) -> Duration {
+        let adaptive = &self.config.adaptive;
+        let score = match adaptive.score {
+            IntensityScore::Activity => self.intensity(worked),
+            IntensityScore::LongestStretch => self.longest_stretch_share(worked),
+        };
+        let extra = adaptive.max_break.saturating_sub(adaptive.min_break);
+        adaptive.min_break + extra.mul_f32(score.unwrap_or_default())
+    }
+}
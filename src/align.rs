@@ -0,0 +1,117 @@
+//! Alignment of the work intervals to the wall clock: with `align-work = "30m"` and the default
+//! timings, the breaks always start at :25 and :55. The periods are counted from the local
+//! midnight, and the time left is computed whenever a work interval starts; a resumed work
+//! interval keeps the time left, with whatever the user extended, shortened or snoozed.
+
+use std::time::Duration;
+
+use chrono::{Local, NaiveTime, Timelike};
+
+use crate::{timer::Phase, Passata};
+
+/// Time from `now` to the first break aligned to `period` at least `min_left` away, the breaks
+/// being `work` past the start of each period
+fn until_aligned_break(
+    now: NaiveTime,
+    period: Duration,
+    work: Duration,
+    min_left: Duration,
+) -> Duration {
+    let period = period.as_secs().max(1);
+    let offset = work.as_secs() % period;
+    let since_midnight = u64::from(now.num_seconds_from_midnight());
+    let earliest = since_midnight + min_left.as_secs();
+    let break_at = (earliest + period - 1 - offset) / period * period + offset;
+    Duration::from_secs(break_at - since_midnight)
+        .saturating_sub(Duration::from_nanos(now.nanosecond().into()))
+}
+
+impl Passata {
+    /// Stretch or shrink `phase_length` so that the work interval ends aligned to the wall
+    /// clock, returning the time left; `remaining` when the work intervals are not aligned.
+    /// The work interval lasts at least half of its length
+    pub fn align_phase_length(&mut self, remaining: Duration) -> Duration {
+        let Some(period) = self.config.align_work else {
+            return remaining;
+        };
+        if self.phase != Phase::Work || self.stopwatch {
+            return remaining;
+        }
+        let work = self.work_interval();
+        let worked = self.phase_length.saturating_sub(remaining);
+        let aligned = until_aligned_break(
+            Local::now().time(),
+            period,
+            work,
+            (work / 2).saturating_sub(worked),
+        );
+        self.phase_length = worked + aligned;
+        aligned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn at(hour: u32, min: u32, sec: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, min, sec).unwrap()
+    }
+
+    #[test]
+    fn next_break_of_the_period() {
+        let left = until_aligned_break(at(10, 0, 0), 30 * MINUTE, 25 * MINUTE, Duration::ZERO);
+        assert_eq!(left, 25 * MINUTE);
+        let left = until_aligned_break(at(10, 40, 0), 30 * MINUTE, 25 * MINUTE, Duration::ZERO);
+        assert_eq!(left, 15 * MINUTE);
+    }
+
+    #[test]
+    fn break_too_close_moves_to_the_next_period() {
+        let left = until_aligned_break(at(10, 20, 0), 30 * MINUTE, 25 * MINUTE, 12 * MINUTE);
+        assert_eq!(left, 35 * MINUTE);
+        // a break due right now is only taken without any minimum
+        let left = until_aligned_break(at(10, 25, 0), 30 * MINUTE, 25 * MINUTE, Duration::ZERO);
+        assert_eq!(left, Duration::ZERO);
+        let left = until_aligned_break(
+            at(10, 25, 0),
+            30 * MINUTE,
+            25 * MINUTE,
+            Duration::from_secs(1),
+        );
+        assert_eq!(left, 30 * MINUTE);
+    }
+
+    #[test]
+    fn work_longer_than_the_period_wraps() {
+        // 50m of work in periods of 30m: the breaks are at :20 and :50
+        let left = until_aligned_break(at(10, 0, 0), 30 * MINUTE, 50 * MINUTE, Duration::ZERO);
+        assert_eq!(left, 20 * MINUTE);
+        // work as long as the period: the breaks are at the start of each period
+        let left = until_aligned_break(at(10, 10, 0), 30 * MINUTE, 30 * MINUTE, Duration::ZERO);
+        assert_eq!(left, 20 * MINUTE);
+    }
+
+    #[test]
+    fn sub_second_part_is_subtracted() {
+        let now = NaiveTime::from_hms_milli_opt(10, 0, 0, 500).unwrap();
+        let left = until_aligned_break(now, 30 * MINUTE, 25 * MINUTE, Duration::ZERO);
+        assert_eq!(left, 25 * MINUTE - Duration::from_millis(500));
+    }
+
+    #[test]
+    fn periods_around_midnight() {
+        let left = until_aligned_break(at(23, 50, 0), 30 * MINUTE, 25 * MINUTE, Duration::ZERO);
+        assert_eq!(left, 5 * MINUTE);
+        // the first break of the next day
+        let left = until_aligned_break(at(23, 50, 0), 30 * MINUTE, 25 * MINUTE, 12 * MINUTE);
+        assert_eq!(left, 35 * MINUTE);
+        let now = NaiveTime::from_hms_milli_opt(23, 59, 59, 500).unwrap();
+        let left = until_aligned_break(now, 30 * MINUTE, 25 * MINUTE, Duration::ZERO);
+        assert_eq!(left, 25 * MINUTE + Duration::from_millis(500));
+        let left = until_aligned_break(at(0, 0, 0), 30 * MINUTE, 25 * MINUTE, Duration::ZERO);
+        assert_eq!(left, 25 * MINUTE);
+    }
+}
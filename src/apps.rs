@@ -0,0 +1,211 @@
+//! Work applications: the work time only runs while one of them is focused, following the
+//! focused toplevel through wlr-foreign-toplevel-management, and pauses when drifting to
+//! anything else. The same toplevels tell when a meeting window is focused.
+
+use color_eyre::{eyre::WrapErr, Result};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::{
+    client::{event_created_child, globals::GlobalList, Connection, Dispatch, QueueHandle},
+    protocols_wlr::foreign_toplevel::v1::client::{
+        zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+        zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+    },
+};
+
+use crate::{timer::Phase, Passata};
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct WorkAppsConfig {
+    /// App ids of the applications counting as work, e.g. "org.gnome.Terminal"; the focus is
+    /// not followed when empty
+    pub apps: Vec<String>,
+}
+
+struct Toplevel {
+    handle: ZwlrForeignToplevelHandleV1,
+    app_id: Option<String>,
+    title: Option<String>,
+    activated: bool,
+}
+
+/// Toplevels of the session, as announced by the compositor
+pub struct WorkApps {
+    manager: ZwlrForeignToplevelManagerV1,
+    toplevels: Vec<Toplevel>,
+}
+
+impl WorkApps {
+    pub fn new(globals: &GlobalList, qh: &QueueHandle<Passata>) -> Result<Self> {
+        Ok(Self {
+            manager: globals
+                .bind(qh, 1..=3, ())
+                .wrap_err("wlr-foreign-toplevel-management is not available")?,
+            toplevels: Vec::new(),
+        })
+    }
+
+    /// App id of the focused toplevel, if it is known
+    fn focused(&self) -> Option<&str> {
+        self.toplevels
+            .iter()
+            .find(|toplevel| toplevel.activated)
+            .and_then(|toplevel| toplevel.app_id.as_deref())
+    }
+
+    /// App id and title of the focused toplevel, if there is one
+    pub fn focused_window(&self) -> Option<(&str, &str)> {
+        self.toplevels
+            .iter()
+            .find(|toplevel| toplevel.activated)
+            .map(|toplevel| {
+                (
+                    toplevel.app_id.as_deref().unwrap_or_default(),
+                    toplevel.title.as_deref().unwrap_or_default(),
+                )
+            })
+    }
+}
+
+impl Drop for WorkApps {
+    fn drop(&mut self) {
+        for toplevel in &self.toplevels {
+            toplevel.handle.destroy();
+        }
+        self.manager.stop();
+    }
+}
+
+impl Passata {
+    /// Whether the focused app is one of the enforcement exceptions, sparing the user the
+    /// overlay and the lock
+    pub fn enforcement_exempt(&self) -> bool {
+        let Some((app_id, _)) = self.work_apps.as_ref().and_then(WorkApps::focused_window) else {
+            return false;
+        };
+        let exempt = self
+            .config
+            .enforcement_exceptions
+            .iter()
+            .any(|exception| exception == app_id);
+        if exempt {
+            debug!("{app_id} is focused, not enforcing the break");
+        }
+        exempt
+    }
+
+    fn focused_app_changed(&mut self) {
+        if self.config.work_apps.apps.is_empty() {
+            return;
+        }
+        // without a focused toplevel, e.g. while switching, the last verdict holds
+        let Some(app_id) = self
+            .work_apps
+            .as_ref()
+            .and_then(WorkApps::focused)
+            .map(str::to_owned)
+        else {
+            return;
+        };
+        let off_work_app = !self
+            .config
+            .work_apps
+            .apps
+            .iter()
+            .any(|app| app.eq_ignore_ascii_case(&app_id));
+        if off_work_app == self.off_work_app {
+            return;
+        }
+        debug!("focused {app_id}, a work app: {}", !off_work_app);
+        self.off_work_app = off_work_app;
+        // breaks are not interrupted, only the work time is held
+        if self.phase != Phase::Work {
+            return;
+        }
+        if off_work_app {
+            info!("{app_id} is not a work app, pausing the work time");
+            self.pause();
+        } else if self.can_resume() {
+            if let Err(err) = self.resume() {
+                error!("resuming the timer: {err:?}");
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for Passata {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(work_apps) = &mut state.work_apps else {
+            return;
+        };
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            work_apps.toplevels.push(Toplevel {
+                handle: toplevel,
+                app_id: None,
+                title: None,
+                activated: false,
+            });
+        }
+    }
+
+    event_created_child!(Passata, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for Passata {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(work_apps) = &mut state.work_apps else {
+            return;
+        };
+        let Some(toplevel) = work_apps
+            .toplevels
+            .iter_mut()
+            .find(|toplevel| &toplevel.handle == handle)
+        else {
+            return;
+        };
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                toplevel.app_id = Some(app_id);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
+                toplevel.title = Some(title);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: states } => {
+                let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+                toplevel.activated = states
+                    .chunks_exact(4)
+                    .any(|state| u32::from_ne_bytes(state.try_into().unwrap()) == activated);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                state.focused_app_changed();
+                state.meeting_window_changed();
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                handle.destroy();
+                work_apps
+                    .toplevels
+                    .retain(|toplevel| &toplevel.handle != handle);
+                state.focused_app_changed();
+                state.meeting_window_changed();
+            }
+            _ => {}
+        }
+    }
+}
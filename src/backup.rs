@@ -0,0 +1,100 @@
+//! Snapshot of the statistics, the history and the daily aggregates together in a single
+//! versioned JSON document, to move them to another machine or to recover them.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    time::SystemTime,
+};
+
+use color_eyre::{
+    eyre::{ensure, WrapErr},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ipc,
+    stats::{self, DailyAggregate, Record},
+};
+
+/// Version of the backups written, raised whenever an older passata could not read them
+const BACKUP_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Backup {
+    version: u32,
+    #[serde(with = "humantime_serde")]
+    created: SystemTime,
+    records: Vec<Record>,
+    aggregates: Vec<DailyAggregate>,
+}
+
+/// Write the backup of the history to `path`, or to the standard output; the lines of the
+/// history that cannot be parsed are left out and reported
+pub fn backup(path: Option<&Path>) -> Result<()> {
+    let history = stats::history_path()?;
+    let (records, mut skipped) = stats::salvage(&history)?;
+    let (aggregates, skipped_aggregates) = stats::salvage_aggregates(&history)?;
+    skipped.extend(skipped_aggregates);
+    for line in &skipped {
+        eprintln!("left out {line}");
+    }
+    let backup = Backup {
+        version: BACKUP_VERSION,
+        created: SystemTime::now(),
+        records,
+        aggregates,
+    };
+    let json = serde_json::to_string_pretty(&backup)?;
+    match path {
+        Some(path) => {
+            fs::write(path, json).wrap_err_with(|| format!("writing {path:?}"))?;
+            eprintln!(
+                "saved {} records and {} days to {path:?}",
+                backup.records.len(),
+                backup.aggregates.len()
+            );
+        }
+        None => writeln!(io::stdout(), "{json}")?,
+    }
+    Ok(())
+}
+
+/// Replace the history with the backup at `path`; a history with records is only replaced
+/// with `force`. The daemon listening on `socket` must be stopped first, it keeps the progress,
+/// the streak and the debt of the old history in memory
+pub fn restore(path: &Path, force: bool, socket: &Path) -> Result<()> {
+    ensure!(
+        !ipc::is_running(socket),
+        "passata is running, stop it before restoring the history"
+    );
+    let content = fs::read_to_string(path).wrap_err_with(|| format!("reading {path:?}"))?;
+    let backup: Backup =
+        serde_json::from_str(&content).wrap_err_with(|| format!("parsing {path:?}"))?;
+    ensure!(
+        backup.version <= BACKUP_VERSION,
+        "the backup has version {}, this passata reads up to {BACKUP_VERSION}",
+        backup.version
+    );
+    let history = stats::history_path()?;
+    // a corrupted history cannot be read, it is only replaced with force
+    let has_history = stats::read(&history).map_or(true, |records| !records.is_empty())
+        || stats::read_aggregates(&history).map_or(true, |aggregates| !aggregates.is_empty());
+    ensure!(
+        force || !has_history,
+        "{history:?} already has a history, restore with --force to replace it"
+    );
+    if let Some(dir) = history.parent() {
+        fs::create_dir_all(dir).wrap_err_with(|| format!("creating {dir:?}"))?;
+    }
+    stats::replace(&history, &backup.records, &backup.aggregates)?;
+    eprintln!(
+        "restored {} records and {} days to {history:?}",
+        backup.records.len(),
+        backup.aggregates.len()
+    );
+    Ok(())
+}
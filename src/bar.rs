@@ -0,0 +1,110 @@
+//! Status command for swaybar and i3bar: writes the time left as a block of their JSON protocol
+//! every second, and turns the clicks on it into requests to the daemon (left click pauses or
+//! resumes, right click skips the phase).
+
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    ipc::{self, Request, Response},
+    status::Status,
+    timer::Phase,
+};
+
+/// How often the block is written, the countdown runs locally in between the requests
+const TICK: Duration = Duration::from_secs(1);
+/// How often the status is asked again to the daemon
+const REFRESH: Duration = Duration::from_secs(5);
+
+const BREAK_COLOR: &str = "#a3be8c";
+const PAUSED_COLOR: &str = "#ebcb8b";
+
+/// Click on a block, as sent by the bar on stdin
+#[derive(Deserialize)]
+struct ClickEvent {
+    button: u32,
+}
+
+pub fn run(socket: &Path) -> Result<()> {
+    let (sender, clicks) = mpsc::channel();
+    thread::spawn(move || {
+        // the events form an endless JSON array, one per line after the opening bracket
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            let line = line.trim_start_matches(['[', ',']).trim();
+            if let Ok(click) = serde_json::from_str::<ClickEvent>(line) {
+                if sender.send(click).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", json!({ "version": 1, "click_events": true }))?;
+    writeln!(stdout, "[")?;
+    let mut status = None;
+    let mut refreshed: Option<Instant> = None;
+    loop {
+        if !refreshed.is_some_and(|refreshed| refreshed.elapsed() < REFRESH) {
+            status = fetch_status(socket, &Request::Status);
+            refreshed = Some(Instant::now());
+        }
+        writeln!(stdout, "{},", json!([block(status.as_ref())]))?;
+        stdout.flush()?;
+        match clicks.recv_timeout(TICK) {
+            Ok(click) => {
+                let request = match click.button {
+                    1 => Request::TogglePause,
+                    3 => Request::Skip,
+                    _ => continue,
+                };
+                status = fetch_status(socket, &request);
+                refreshed = Some(Instant::now());
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // stdin is closed, the bar does not send clicks
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(TICK),
+        }
+    }
+}
+
+/// Send a request answered with the status, none if the daemon cannot be reached
+fn fetch_status(socket: &Path, request: &Request) -> Option<Status> {
+    match ipc::send(socket, request) {
+        Ok(Response::Status(status)) => Some(status),
+        _ => None,
+    }
+}
+
+fn block(status: Option<&Status>) -> serde_json::Value {
+    let Some(status) = status else {
+        return json!({ "name": "passata", "full_text": "passata is not running" });
+    };
+    let clock = status.clock().as_secs();
+    let name = status.break_type.as_deref().unwrap_or(status.phase.name());
+    let mut block = json!({
+        "name": "passata",
+        "full_text": format!("{name} {:02}:{:02}", clock / 60, clock % 60),
+        "short_text": format!("{:02}:{:02}", clock / 60, clock % 60),
+        // the custom keys of the protocol start with an underscore, the bars ignore them
+        "_percent": status.progress(),
+    });
+    if status.paused {
+        block["color"] = json!(PAUSED_COLOR);
+    } else if status.phase != Phase::Work {
+        block["color"] = json!(BREAK_COLOR);
+    }
+    block
+}
@@ -0,0 +1,105 @@
+//! Relax the schedule while the laptop runs on battery, as the sessions on the couch are rarely
+//! formal work: the work intervals get longer, or the work time is held until the power is
+//! plugged in. The power source comes from the OnBattery property of UPower.
+
+use std::thread;
+
+use color_eyre::Result;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+use zbus::{blocking::Connection, proxy};
+
+use crate::{profile::Timings, timer::Phase, Passata};
+
+/// What changes while running on battery
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BatteryPolicy {
+    /// Nothing, the schedule stays as configured
+    #[default]
+    Ignore,
+    /// The work intervals are half again as long
+    LongerIntervals,
+    /// The work time is held until the power is plugged in
+    Pause,
+}
+
+#[proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower",
+    gen_async = false
+)]
+trait UPower {
+    #[zbus(property)]
+    fn on_battery(&self) -> zbus::Result<bool>;
+}
+
+/// Start following the power source
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let connection = Connection::system()?;
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg(on_battery) => state.power_changed(on_battery),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = follow_on_battery(&connection, sender) {
+            warn!("stopped following the battery: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+fn follow_on_battery(connection: &Connection, sender: Sender<bool>) -> Result<()> {
+    let upower = UPowerProxy::new(connection)?;
+    sender.send(upower.on_battery()?)?;
+    for change in upower.receive_on_battery_changed() {
+        sender.send(change.get()?)?;
+    }
+    Ok(())
+}
+
+impl Passata {
+    /// The `timings` stretched while on battery, with longer-intervals
+    pub fn battery_timings(&self, timings: Timings) -> Timings {
+        if !self.on_battery || self.config.on_battery != BatteryPolicy::LongerIntervals {
+            return timings;
+        }
+        Timings {
+            work_interval: timings.work_interval * 3 / 2,
+            ..timings
+        }
+    }
+
+    /// Whether the work time is held until the power is plugged in
+    pub fn battery_held(&self) -> bool {
+        self.on_battery && self.config.on_battery == BatteryPolicy::Pause
+    }
+
+    fn power_changed(&mut self, on_battery: bool) {
+        if on_battery == self.on_battery {
+            return;
+        }
+        debug!("running on battery: {on_battery}");
+        self.on_battery = on_battery;
+        // the longer intervals start with the next work interval, and the breaks go on
+        if self.config.on_battery != BatteryPolicy::Pause || self.phase != Phase::Work {
+            return;
+        }
+        if on_battery {
+            info!("running on battery, pausing the work time");
+            self.pause();
+        } else if self.can_resume() {
+            if let Err(err) = self.resume() {
+                error!("resuming the timer: {err:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Drive the state machine through many phases without waiting for the timers, to catch the
+//! subsystems hooked to the transitions getting slower or allocating more.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use color_eyre::Result;
+
+use crate::Passata;
+
+/// How many phases are run by the benchmark
+const TRANSITIONS: u32 = 10_000;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting the allocations for the benchmark
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+pub fn run(state: &mut Passata) -> Result<()> {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..TRANSITIONS {
+        state.next_phase()?;
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes;
+
+    println!("{TRANSITIONS} transitions in {elapsed:?}");
+    println!(
+        "{:.0} transitions/s",
+        f64::from(TRANSITIONS) / elapsed.as_secs_f64()
+    );
+    println!(
+        "{} allocations ({} bytes) per transition",
+        allocations / TRANSITIONS as usize,
+        allocated_bytes / TRANSITIONS as usize
+    );
+    Ok(())
+}
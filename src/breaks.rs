@@ -0,0 +1,118 @@
+//! Named break types, each with its own length and styling, scheduled after the work intervals
+//! in the order given by the configured sequence, or every few work intervals.
+
+use std::time::Duration;
+
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+
+use crate::{command::BreakCommandEnd, notify, overlay::OverlayConfig, timer::Phase, Passata};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BreakType {
+    #[serde(with = "humantime_serde")]
+    pub duration: Duration,
+    /// Run the break as a long break, escalating it with the long break ladder
+    #[serde(default)]
+    pub long: bool,
+    /// Take the break after every n-th work interval when there is no sequence; the break
+    /// with the largest `every` is taken when several are due, the short or long break when
+    /// none is
+    pub every: Option<u32>,
+    /// Summary of the notification, the name of the break by default
+    pub summary: Option<String>,
+    pub body: Option<String>,
+    /// Icon of the notification, as an icon name or a path
+    pub icon: Option<String>,
+    /// Sound played with the notification, as a name from the XDG sound theme or a path
+    pub sound: Option<String>,
+    /// Overlay shown when the break escalates, replacing the main one
+    pub overlay: Option<OverlayConfig>,
+    /// Command run for the break, replacing break-command
+    pub command: Option<String>,
+    /// What ends the break running the command, replacing break-command-end
+    pub command_end: Option<BreakCommandEnd>,
+}
+
+impl Passata {
+    /// The named break currently running, if any
+    pub fn break_type(&self) -> Option<&BreakType> {
+        self.break_type
+            .as_ref()
+            .and_then(|name| self.config.breaks.get(name))
+    }
+
+    /// Notification announcing the current break
+    pub fn break_notification(&self) -> Notification {
+        let mut notification = Notification::new();
+        match (&self.break_type, self.break_type()) {
+            (Some(name), Some(break_type)) => {
+                notification
+                    .summary(break_type.summary.as_deref().unwrap_or(name))
+                    .body(
+                        break_type
+                            .body
+                            .as_deref()
+                            .or(self.activity.as_deref())
+                            .unwrap_or(&self.tr("take-a-pause")),
+                    );
+                if let Some(icon) = &break_type.icon {
+                    notification.icon(icon);
+                }
+                if let Some(sound) = &break_type.sound {
+                    notify::set_sound(&mut notification, sound);
+                }
+            }
+            _ if self.phase == Phase::LongBreak => {
+                notification.summary(&self.tr("long-break")).body(
+                    self.activity
+                        .as_deref()
+                        .unwrap_or(&self.tr("take-a-long-pause")),
+                );
+            }
+            _ => {
+                notification
+                    .summary(&self.tr("short-break"))
+                    .body(self.activity.as_deref().unwrap_or(&self.tr("take-a-pause")));
+            }
+        }
+        let named_sound = self
+            .break_type()
+            .and_then(|break_type| break_type.sound.as_ref());
+        if let (None, Some(sound)) = (named_sound, &self.config.sound_break_start) {
+            notify::set_sound(&mut notification, sound);
+        }
+        if let Some(task) = &self.task {
+            let done = self.tr_args("done-with", &[("task", task.as_str().into())]);
+            notification.body = format!("{}\n{done}", notification.body);
+        }
+        if let Some(progress) = self.goal_progress() {
+            let today = self.tr_args(
+                "today-progress",
+                &[
+                    ("completed", progress.completed.into()),
+                    ("goal", progress.goal.into()),
+                ],
+            );
+            notification.body = format!("{}\n{today}", notification.body);
+        }
+        let streak = self.streak.current();
+        if streak > 1 {
+            let streak = self.tr_args("streak", &[("streak", streak.into())]);
+            notification.body = format!("{}\n{streak}", notification.body);
+        }
+        if let Some(notice) = self.break_debt_notice() {
+            notification.body = format!("{}\n{notice}", notification.body);
+        }
+        notification.action("skip", &self.tr("skip-break"));
+        notification
+    }
+
+    /// Overlay settings of the current break
+    pub fn overlay_config(&self) -> &OverlayConfig {
+        self.break_type()
+            .and_then(|break_type| break_type.overlay.as_ref())
+            .unwrap_or(&self.config.overlay)
+    }
+}
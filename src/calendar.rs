@@ -0,0 +1,761 @@
+//! Enter the focus mode during the busy events of the calendar, so that no break starts in the
+//! middle of a meeting. The events are read from ICS files, or from directories of them such as
+//! the vdirs that vdirsyncer keeps in sync with CalDAV for khal.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{
+    DateTime, Datelike, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use color_eyre::Result;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
+
+use crate::Passata;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CalendarConfig {
+    /// ICS files, or directories of ICS files
+    pub sources: Vec<PathBuf>,
+    /// How often the calendar is read again
+    #[serde(with = "humantime_serde")]
+    pub refresh: Duration,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            refresh: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Time range of an event during which the user is busy
+#[derive(Debug, PartialEq)]
+struct BusyEvent {
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+}
+
+/// Check the calendar now and then after every `refresh`
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>, refresh: Duration) -> Result<()> {
+    loop_handle
+        .insert_source(Timer::immediate(), move |_, _, state: &mut Passata| {
+            state.check_calendar();
+            TimeoutAction::ToDuration(refresh)
+        })
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Busy events of `day` in all the sources; the unreadable ones are skipped
+fn busy_events(sources: &[PathBuf], day: NaiveDate) -> Vec<BusyEvent> {
+    let mut events = Vec::new();
+    for source in sources {
+        if !source.is_dir() {
+            events.extend(read_ics(source, day));
+            continue;
+        }
+        let entries = match fs::read_dir(source) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("opening the calendar {source:?}: {err}");
+                continue;
+            }
+        };
+        for entry in entries {
+            match entry {
+                Ok(entry) if entry.path().extension().is_some_and(|ext| ext == "ics") => {
+                    events.extend(read_ics(&entry.path(), day));
+                }
+                Ok(_) => {}
+                Err(err) => warn!("listing the calendar {source:?}: {err}"),
+            }
+        }
+    }
+    events
+}
+
+fn read_ics(path: &Path, day: NaiveDate) -> Vec<BusyEvent> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse_ics(&content, day),
+        Err(err) => {
+            warn!("reading the calendar {path:?}: {err}");
+            Vec::new()
+        }
+    }
+}
+
+/// Time zone of a DATE-TIME value
+#[derive(Clone, Copy)]
+enum Zone {
+    Utc,
+    /// No time zone, the local time
+    Floating,
+    Named(Tz),
+}
+
+impl Zone {
+    fn resolve(self, naive: NaiveDateTime) -> Option<DateTime<Local>> {
+        match self {
+            Zone::Utc => Some(Utc.from_utc_datetime(&naive).with_timezone(&Local)),
+            Zone::Floating => Local.from_local_datetime(&naive).earliest(),
+            Zone::Named(tz) => tz
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|time| time.with_timezone(&Local)),
+        }
+    }
+}
+
+/// A DATE-TIME value, kept on the wall clock of its time zone for the recurrences to follow it
+#[derive(Clone, Copy)]
+struct Time {
+    naive: NaiveDateTime,
+    zone: Zone,
+}
+
+impl Time {
+    fn resolve(self) -> Option<DateTime<Local>> {
+        self.zone.resolve(self.naive)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// The parts of a RRULE in common use; BYSETPOS and the parts below a day are not supported
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<DateTime<Local>>,
+    /// Weekdays, with their position in the month for the monthly and yearly rules
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+}
+
+impl Rule {
+    fn parse(value: &str) -> Option<Self> {
+        let mut frequency = None;
+        let mut rule = Rule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        };
+        for part in value.split(';') {
+            let (name, value) = part.split_once('=')?;
+            match name {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return None,
+                    });
+                }
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|n| *n > 0)?,
+                "COUNT" => rule.count = Some(value.parse().ok()?),
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    rule.by_day = value.split(',').map(parse_weekday).collect::<Option<_>>()?;
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = value
+                        .split(',')
+                        .map(|day| day.parse().ok())
+                        .collect::<Option<_>>()?;
+                }
+                "BYMONTH" => {
+                    rule.by_month = value
+                        .split(',')
+                        .map(|month| month.parse().ok())
+                        .collect::<Option<_>>()?;
+                }
+                _ => {}
+            }
+        }
+        rule.frequency = frequency?;
+        Some(rule)
+    }
+
+    /// Whether the rule of an event starting on `first` repeats it on `date`, ignoring COUNT
+    /// and UNTIL
+    fn repeats_on(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        if date < first {
+            return false;
+        }
+        let interval = i64::from(self.interval);
+        let in_step = match self.frequency {
+            Frequency::Daily => (date - first).num_days() % interval == 0,
+            Frequency::Weekly => (week_start(date) - week_start(first)).num_weeks() % interval == 0,
+            Frequency::Monthly => {
+                let months =
+                    (date.year() - first.year()) * 12 + date.month() as i32 - first.month() as i32;
+                i64::from(months) % interval == 0
+            }
+            Frequency::Yearly => i64::from(date.year() - first.year()) % interval == 0,
+        };
+        if !in_step {
+            return false;
+        }
+        if !self.by_month.is_empty() && !self.by_month.contains(&date.month()) {
+            return false;
+        }
+        if !self.by_month_day.is_empty()
+            && !self
+                .by_month_day
+                .iter()
+                .any(|day| month_day_matches(date, *day))
+        {
+            return false;
+        }
+        if !self.by_day.is_empty() {
+            // the positions only count within the month, and not at all for the shorter rules
+            let by_position = matches!(self.frequency, Frequency::Monthly | Frequency::Yearly);
+            return self.by_day.iter().any(|(position, weekday)| {
+                date.weekday() == *weekday
+                    && position
+                        .filter(|_| by_position)
+                        .is_none_or(|position| weekday_position_matches(date, position))
+            });
+        }
+        // without the parts picking the days, the events repeat on the day they started
+        match self.frequency {
+            Frequency::Daily => true,
+            Frequency::Weekly => date.weekday() == first.weekday(),
+            Frequency::Monthly => !self.by_month_day.is_empty() || date.day() == first.day(),
+            Frequency::Yearly => {
+                (!self.by_month.is_empty() || date.month() == first.month())
+                    && (!self.by_month_day.is_empty() || date.day() == first.day())
+            }
+        }
+    }
+
+    /// Whether the event starting on `first` has an occurrence on `date` within COUNT
+    fn within_count(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        let Some(count) = self.count else {
+            return true;
+        };
+        first
+            .iter_days()
+            .take_while(|day| *day <= date)
+            .filter(|day| self.repeats_on(first, *day))
+            .take(count as usize)
+            .last()
+            .is_some_and(|last| last == date)
+    }
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday().into())
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    let (year, month) = match date.month() {
+        12 => (date.year() + 1, 1),
+        month => (date.year(), month + 1),
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|next| next.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+/// Whether `date` is the day of the month `day`, counted from the end when negative
+fn month_day_matches(date: NaiveDate, day: i32) -> bool {
+    if day < 0 {
+        days_in_month(date) as i32 + day + 1 == date.day() as i32
+    } else {
+        date.day() as i32 == day
+    }
+}
+
+/// Whether `date` is the weekday at `position` in its month, counted from the end when negative
+fn weekday_position_matches(date: NaiveDate, position: i32) -> bool {
+    if position < 0 {
+        ((days_in_month(date) - date.day()) / 7 + 1) as i32 == -position
+    } else {
+        ((date.day() - 1) / 7 + 1) as i32 == position
+    }
+}
+
+/// Parse a BYDAY weekday such as `TU`, `2MO` or `-1FR`
+fn parse_weekday(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let split = value.len().checked_sub(2)?;
+    let (position, weekday) = value.split_at(split);
+    let weekday = match weekday {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let position = match position {
+        "" => None,
+        position => Some(position.trim_start_matches('+').parse().ok()?),
+    };
+    Some((position, weekday))
+}
+
+/// Parse the UNTIL of a RRULE, a DATE being until the end of that day
+fn parse_until(value: &str) -> Option<DateTime<Local>> {
+    if let Some(time) = parse_time(value, "") {
+        return time.resolve();
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    Local
+        .from_local_datetime(&date.succ_opt()?.and_time(NaiveTime::MIN))
+        .earliest()
+}
+
+/// Parse a DURATION value such as `PT1H30M` or `P1D`
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.trim_start_matches('+')),
+    };
+    let mut duration = TimeDelta::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.strip_prefix('P')?.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            'T' => {
+                in_time = true;
+                continue;
+            }
+            'W' if !in_time => TimeDelta::try_weeks,
+            'D' if !in_time => TimeDelta::try_days,
+            'H' if in_time => TimeDelta::try_hours,
+            'M' if in_time => TimeDelta::try_minutes,
+            'S' if in_time => TimeDelta::try_seconds,
+            _ => return None,
+        };
+        duration = duration.checked_add(&unit(number.parse().ok()?)?)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(if negative { -duration } else { duration })
+}
+
+#[derive(Default)]
+struct VEvent {
+    uid: Option<String>,
+    start: Option<Time>,
+    end: Option<Time>,
+    duration: Option<TimeDelta>,
+    rule: Option<Rule>,
+    excluded: Vec<DateTime<Local>>,
+    /// Occurrence of a recurring event that this one replaces
+    recurrence_id: Option<DateTime<Local>>,
+    free: bool,
+    cancelled: bool,
+}
+
+impl VEvent {
+    /// Occurrences overlapping `day`, skipping those replaced by the events in `replaced`
+    fn busy_on(&self, day: NaiveDate, replaced: &[DateTime<Local>]) -> Vec<BusyEvent> {
+        let Some(start) = self.start else {
+            return Vec::new();
+        };
+        let length = match (self.end, self.duration) {
+            (Some(end), _) => end
+                .resolve()
+                .zip(start.resolve())
+                .map(|(end, start)| end - start),
+            (None, duration) => duration,
+        };
+        let Some(length) = length.filter(|length| *length > TimeDelta::zero()) else {
+            return Vec::new();
+        };
+        let (Some(day_start), Some(day_end)) = (
+            Local
+                .from_local_datetime(&day.and_time(NaiveTime::MIN))
+                .earliest(),
+            day.succ_opt().and_then(|next| {
+                Local
+                    .from_local_datetime(&next.and_time(NaiveTime::MIN))
+                    .earliest()
+            }),
+        ) else {
+            return Vec::new();
+        };
+        let overlapping = |start: DateTime<Local>| {
+            let end = start + length;
+            (start < day_end && day_start < end).then_some(BusyEvent { start, end })
+        };
+        let Some(rule) = &self.rule else {
+            return start.resolve().and_then(overlapping).into_iter().collect();
+        };
+        // the occurrences that can overlap the day, with a day more on both sides for the time
+        // zones ahead and behind
+        let first = start.naive.date();
+        let (Some(from), Some(to)) = (
+            day.checked_sub_days(Days::new(length.num_days().unsigned_abs() + 1)),
+            day.succ_opt(),
+        ) else {
+            return Vec::new();
+        };
+        from.iter_days()
+            .take_while(|date| *date <= to)
+            .filter(|date| rule.repeats_on(first, *date) && rule.within_count(first, *date))
+            .filter_map(|date| start.zone.resolve(date.and_time(start.naive.time())))
+            .filter(|start| rule.until.is_none_or(|until| *start <= until))
+            .filter(|start| !self.excluded.contains(start) && !replaced.contains(start))
+            .filter_map(overlapping)
+            .collect()
+    }
+}
+
+/// Busy events of an ICS calendar overlapping `day`, with the recurring events expanded. The
+/// times with an unknown TZID, such as the Windows names, are taken as local times
+fn parse_ics(content: &str, day: NaiveDate) -> Vec<BusyEvent> {
+    // long lines are folded by starting the continuation with a space or a tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<VEvent> = None;
+    for line in &lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match name {
+            "BEGIN" if value == "VEVENT" => current = Some(VEvent::default()),
+            "END" if value == "VEVENT" => events.extend(current.take()),
+            _ => {
+                // the properties outside of the events, e.g. of the time zones, are not interesting
+                let Some(event) = &mut current else {
+                    continue;
+                };
+                match name {
+                    "UID" => event.uid = Some(value.to_owned()),
+                    "DTSTART" => event.start = parse_time(value, params),
+                    "DTEND" => event.end = parse_time(value, params),
+                    "DURATION" => event.duration = parse_duration(value),
+                    "RRULE" => event.rule = Rule::parse(value),
+                    "EXDATE" => event.excluded.extend(
+                        value
+                            .split(',')
+                            .filter_map(|value| parse_time(value, params)?.resolve()),
+                    ),
+                    "RECURRENCE-ID" => {
+                        event.recurrence_id = parse_time(value, params).and_then(Time::resolve);
+                    }
+                    "TRANSP" => event.free = value == "TRANSPARENT",
+                    "STATUS" => event.cancelled = value == "CANCELLED",
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    events
+        .iter()
+        .filter(|event| !event.free && !event.cancelled)
+        .flat_map(|event| {
+            // the occurrences moved or cancelled are replaced by their own events
+            let replaced: Vec<_> = events
+                .iter()
+                .filter(|other| {
+                    event.uid.is_some() && event.recurrence_id.is_none() && other.uid == event.uid
+                })
+                .filter_map(|other| other.recurrence_id)
+                .collect();
+            event.busy_on(day, &replaced)
+        })
+        .collect()
+}
+
+/// Parse a DATE-TIME value, in UTC, in the time zone of its TZID or floating; the all-day DATE
+/// values are not busy time
+fn parse_time(value: &str, params: &str) -> Option<Time> {
+    let mut tzid = None;
+    for param in params.split(';') {
+        match param.split_once('=') {
+            Some(("VALUE", "DATE")) => return None,
+            Some(("TZID", name)) => tzid = Some(name.trim_matches('"')),
+            _ => {}
+        }
+    }
+    let (value, zone) = match value.strip_suffix('Z') {
+        Some(utc) => (utc, Zone::Utc),
+        None => (
+            value,
+            // the names may come with the prefix of the database they are from
+            tzid.and_then(|name| name.trim_start_matches('/').parse().ok())
+                .map_or(Zone::Floating, Zone::Named),
+        ),
+    };
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    Some(Time { naive, zone })
+}
+
+impl Passata {
+    fn check_calendar(&mut self) {
+        let now = Local::now();
+        let events = busy_events(&self.config.calendar.sources, now.date_naive());
+        let Some(end) = events
+            .iter()
+            .filter(|event| event.start <= now && now < event.end)
+            .map(|event| event.end)
+            .max()
+        else {
+            return;
+        };
+        let busy_for = (end - now).to_std().unwrap_or_default();
+        // a longer focus mode is already covering the event
+        if self
+            .focus
+            .as_ref()
+            .is_some_and(|focus| focus.remaining() >= busy_for)
+        {
+            return;
+        }
+        info!("busy in the calendar until {}", end.format("%H:%M"));
+        if let Err(err) = self.set_focus(busy_for) {
+            error!("entering the focus mode: {err:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(events: &[&str]) -> String {
+        let events: String = events
+            .iter()
+            .map(|event| format!("BEGIN:VEVENT\r\n{event}END:VEVENT\r\n"))
+            .collect();
+        format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{events}END:VCALENDAR\r\n")
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, day).unwrap()
+    }
+
+    fn local(value: &str) -> DateTime<Local> {
+        let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").unwrap();
+        Utc.from_utc_datetime(&naive)
+    }
+
+    fn busy(start: &str, end: &str) -> BusyEvent {
+        BusyEvent {
+            start: local(start),
+            end: local(end),
+        }
+    }
+
+    #[test]
+    fn single_event() {
+        let content = calendar(&["DTSTART:20260114T100000\r\nDTEND:20260114T110000\r\n"]);
+        assert_eq!(
+            parse_ics(&content, date(14)),
+            [busy("20260114T100000", "20260114T110000")]
+        );
+        assert_eq!(parse_ics(&content, date(15)), []);
+    }
+
+    #[test]
+    fn folded_lines() {
+        let content = calendar(&["DTSTART:20260114T1\r\n 00000\r\nDTEND:20260114T110000\r\n"]);
+        assert_eq!(
+            parse_ics(&content, date(14)),
+            [busy("20260114T100000", "20260114T110000")]
+        );
+    }
+
+    #[test]
+    fn not_busy() {
+        let content = calendar(&[
+            "DTSTART:20260114T100000\r\nDTEND:20260114T110000\r\nTRANSP:TRANSPARENT\r\n",
+            "DTSTART:20260114T100000\r\nDTEND:20260114T110000\r\nSTATUS:CANCELLED\r\n",
+            "DTSTART;VALUE=DATE:20260114\r\nDTEND;VALUE=DATE:20260115\r\n",
+        ]);
+        assert_eq!(parse_ics(&content, date(14)), []);
+    }
+
+    #[test]
+    fn time_zones() {
+        let content = calendar(&[
+            "DTSTART;TZID=Europe/Rome:20260114T100000\r\n\
+             DTEND;TZID=\"Europe/Rome\":20260114T110000\r\n",
+            "DTSTART:20260114T120000Z\r\nDTEND:20260114T130000Z\r\n",
+        ]);
+        let events: Vec<_> = parse_ics(&content, date(14))
+            .into_iter()
+            .map(|event| {
+                (
+                    event.start.with_timezone(&Utc),
+                    event.end.with_timezone(&Utc),
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (utc("20260114T090000"), utc("20260114T100000")),
+                (utc("20260114T120000"), utc("20260114T130000")),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_time_zone_is_local() {
+        let content = calendar(&["DTSTART;TZID=W. Europe Standard Time:20260114T100000\r\n\
+             DTEND;TZID=W. Europe Standard Time:20260114T110000\r\n"]);
+        assert_eq!(
+            parse_ics(&content, date(14)),
+            [busy("20260114T100000", "20260114T110000")]
+        );
+    }
+
+    #[test]
+    fn duration_instead_of_end() {
+        let content = calendar(&["DTSTART:20260114T100000\r\nDURATION:PT1H30M\r\n"]);
+        assert_eq!(
+            parse_ics(&content, date(14)),
+            [busy("20260114T100000", "20260114T113000")]
+        );
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("PT15M"), TimeDelta::try_minutes(15));
+        assert_eq!(parse_duration("P1DT2H"), TimeDelta::try_hours(26));
+        assert_eq!(parse_duration("P1W"), TimeDelta::try_days(7));
+        assert_eq!(parse_duration("-PT30S"), TimeDelta::try_seconds(-30));
+        assert_eq!(parse_duration("PT1H30"), None);
+        assert_eq!(parse_duration("1H"), None);
+    }
+
+    #[test]
+    fn weekly_recurrence() {
+        let content = calendar(&["DTSTART:20260105T100000\r\nDTEND:20260105T103000\r\n\
+             RRULE:FREQ=WEEKLY;BYDAY=MO,WE\r\nEXDATE:20260119T100000\r\n"]);
+        assert_eq!(
+            parse_ics(&content, date(14)),
+            [busy("20260114T100000", "20260114T103000")]
+        );
+        assert_eq!(parse_ics(&content, date(15)), []);
+        // excluded
+        assert_eq!(parse_ics(&content, date(19)), []);
+        // before the first occurrence
+        let content = calendar(&[
+            "DTSTART:20260107T100000\r\nDTEND:20260107T103000\r\nRRULE:FREQ=WEEKLY\r\n",
+        ]);
+        assert_eq!(parse_ics(&content, date(7)).len(), 1);
+        assert_eq!(parse_ics(&content, date(14)).len(), 1);
+        assert_eq!(parse_ics(&content, date(5)), []);
+    }
+
+    #[test]
+    fn every_other_week_until() {
+        let content = calendar(&["DTSTART:20260105T100000\r\nDTEND:20260105T103000\r\n\
+             RRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL=20260120T000000Z\r\n"]);
+        assert_eq!(parse_ics(&content, date(12)), []);
+        assert_eq!(parse_ics(&content, date(19)).len(), 1);
+        // past the end of the rule
+        let february = NaiveDate::from_ymd_opt(2026, 2, 2).unwrap();
+        assert_eq!(parse_ics(&content, february), []);
+    }
+
+    #[test]
+    fn daily_count() {
+        let content = calendar(&[
+            "DTSTART:20260105T090000\r\nDTEND:20260105T091500\r\nRRULE:FREQ=DAILY;COUNT=3\r\n",
+        ]);
+        assert_eq!(
+            parse_ics(&content, date(7)),
+            [busy("20260107T090000", "20260107T091500")]
+        );
+        assert_eq!(parse_ics(&content, date(8)), []);
+    }
+
+    #[test]
+    fn monthly_by_position() {
+        // the last friday of the month
+        let content = calendar(&["DTSTART:20251226T160000\r\nDTEND:20251226T170000\r\n\
+             RRULE:FREQ=MONTHLY;BYDAY=-1FR\r\n"]);
+        assert_eq!(parse_ics(&content, date(23)), []);
+        assert_eq!(
+            parse_ics(&content, date(30)),
+            [busy("20260130T160000", "20260130T170000")]
+        );
+    }
+
+    #[test]
+    fn moved_occurrence() {
+        let content = calendar(&[
+            "UID:standup\r\nDTSTART:20260105T100000\r\nDTEND:20260105T101500\r\n\
+             RRULE:FREQ=DAILY\r\n",
+            "UID:standup\r\nRECURRENCE-ID:20260114T100000\r\n\
+             DTSTART:20260114T140000\r\nDTEND:20260114T141500\r\n",
+            "UID:standup\r\nRECURRENCE-ID:20260115T100000\r\n\
+             DTSTART:20260115T100000\r\nDTEND:20260115T101500\r\nSTATUS:CANCELLED\r\n",
+        ]);
+        assert_eq!(
+            parse_ics(&content, date(13)),
+            [busy("20260113T100000", "20260113T101500")]
+        );
+        assert_eq!(
+            parse_ics(&content, date(14)),
+            [busy("20260114T140000", "20260114T141500")]
+        );
+        assert_eq!(parse_ics(&content, date(15)), []);
+    }
+
+    #[test]
+    fn event_across_midnight() {
+        let content = calendar(&[
+            "DTSTART:20260113T230000\r\nDTEND:20260114T010000\r\nRRULE:FREQ=WEEKLY\r\n",
+        ]);
+        assert_eq!(
+            parse_ics(&content, date(14)),
+            [busy("20260113T230000", "20260114T010000")]
+        );
+    }
+}
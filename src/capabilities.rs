@@ -0,0 +1,226 @@
+//! What the session offers, probed at startup: the Wayland protocols of the compositor, the
+//! notification server, pipewire and the D-Bus buses. The defaults that are not set in the
+//! config are chosen from them, and every probe can be forced from the config when it guesses
+//! wrong. A notification server missing at startup is looked for again until it shows up.
+
+use std::{env, path::PathBuf, time::Duration};
+
+use color_eyre::Result;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::{
+    calloop::{
+        timer::{TimeoutAction, Timer},
+        LoopHandle,
+    },
+    client::globals::GlobalList,
+};
+use zbus::{
+    blocking::{fdo::DBusProxy, Connection},
+    names::BusName,
+};
+
+use crate::{idle::IdleBackend, notify::NotifierKind, Passata};
+
+/// How often a missing notification server is looked for again
+const NOTIFICATION_SERVER_PROBE: Duration = Duration::from_secs(60);
+
+/// Forced results of the probes, detected when not set
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CapabilitiesConfig {
+    /// zwlr_layer_shell_v1, needed by the overlay
+    pub layer_shell: Option<bool>,
+    /// ext_idle_notifier_v1, needed by the wayland idle backend
+    pub idle_notify: Option<bool>,
+    /// zwp_idle_inhibit_manager_v1, keeping the screen on during the overlay
+    pub idle_inhibit: Option<bool>,
+    /// zwlr_gamma_control_manager_v1, needed by the dimming
+    pub gamma_control: Option<bool>,
+    /// Notification server on the session bus, needed by the desktop notifier
+    pub notification_server: Option<bool>,
+    /// Pipewire daemon, needed by the sound notifier
+    pub pipewire: Option<bool>,
+    /// Session bus, needed by mpris, the tray and the session inhibitors
+    pub session_bus: Option<bool>,
+    /// System bus, needed by logind and NetworkManager
+    pub system_bus: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Desktop {
+    Gnome,
+    Kde,
+    /// sway, river, labwc and the other wlroots-based compositors
+    Wlroots,
+    Other,
+}
+
+impl Desktop {
+    /// Desktop of the session, from XDG_CURRENT_DESKTOP
+    fn detect() -> Self {
+        let current = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        for name in current.split(':') {
+            match name.to_ascii_lowercase().as_str() {
+                "gnome" => return Desktop::Gnome,
+                "kde" => return Desktop::Kde,
+                "sway" | "river" | "labwc" | "wayfire" | "dwl" => return Desktop::Wlroots,
+                _ => {}
+            }
+        }
+        Desktop::Other
+    }
+}
+
+#[derive(Debug)]
+pub struct Capabilities {
+    pub desktop: Desktop,
+    /// Whether there is a wayland compositor, instead of an X server
+    pub wayland: bool,
+    pub layer_shell: bool,
+    pub idle_notify: bool,
+    pub idle_inhibit: bool,
+    pub gamma_control: bool,
+    pub notification_server: bool,
+    pub pipewire: bool,
+    pub session_bus: bool,
+    pub system_bus: bool,
+}
+
+impl Capabilities {
+    /// Probe the session, keeping the results forced by `config`. The `globals` of the
+    /// compositor are none on X11
+    pub fn detect(globals: Option<&GlobalList>, config: &CapabilitiesConfig) -> Self {
+        let has_global = |interface: &str| {
+            globals.is_some_and(|globals| {
+                globals
+                    .contents()
+                    .with_list(|list| list.iter().any(|global| global.interface == interface))
+            })
+        };
+        let session_bus = match config.session_bus {
+            Some(false) => None,
+            _ => Connection::session().ok(),
+        };
+        let capabilities = Self {
+            desktop: Desktop::detect(),
+            wayland: globals.is_some(),
+            layer_shell: config
+                .layer_shell
+                .unwrap_or_else(|| has_global("zwlr_layer_shell_v1")),
+            idle_notify: config
+                .idle_notify
+                .unwrap_or_else(|| has_global("ext_idle_notifier_v1")),
+            idle_inhibit: config
+                .idle_inhibit
+                .unwrap_or_else(|| has_global("zwp_idle_inhibit_manager_v1")),
+            gamma_control: config
+                .gamma_control
+                .unwrap_or_else(|| has_global("zwlr_gamma_control_manager_v1")),
+            notification_server: config
+                .notification_server
+                .unwrap_or_else(|| session_bus.as_ref().is_some_and(has_notification_server)),
+            pipewire: config.pipewire.unwrap_or_else(has_pipewire),
+            session_bus: config.session_bus.unwrap_or(session_bus.is_some()),
+            system_bus: config
+                .system_bus
+                .unwrap_or_else(|| Connection::system().is_ok()),
+        };
+        info!("detected {capabilities:?}");
+        capabilities
+    }
+
+    /// Idle backend used when the config does not choose one
+    pub fn idle_backend(&self) -> IdleBackend {
+        #[cfg(feature = "x11")]
+        if !self.wayland {
+            return IdleBackend::X11;
+        }
+        if self.idle_notify || !self.system_bus {
+            IdleBackend::Wayland
+        } else {
+            IdleBackend::Logind
+        }
+    }
+
+    /// Whether the breaks are deferred under the session inhibitors when the config does not
+    /// say so, only GNOME and KDE track them
+    pub fn respect_inhibitors(&self) -> bool {
+        self.session_bus && matches!(self.desktop, Desktop::Gnome | Desktop::Kde)
+    }
+
+    /// Whether the notifier can work in this session
+    pub fn supports(&self, notifier: NotifierKind) -> bool {
+        match notifier {
+            NotifierKind::Desktop => self.notification_server,
+            NotifierKind::Terminal => true,
+            NotifierKind::Overlay => self.layer_shell,
+            NotifierKind::Sound => self.pipewire,
+        }
+    }
+
+    /// Drop the notifiers that cannot work in this session, keeping the terminal as a last
+    /// resort. The desktop notifier is kept, skipped until its notification server shows up
+    pub fn filter_notifiers(&self, notifiers: &mut Vec<NotifierKind>) {
+        notifiers.retain(|&notifier| {
+            let supported = self.supports(notifier);
+            if !supported {
+                debug!("the {notifier:?} notifier is not supported in this session");
+            }
+            supported || notifier == NotifierKind::Desktop
+        });
+        if !notifiers.iter().any(|&notifier| self.supports(notifier)) {
+            notifiers.push(NotifierKind::Terminal);
+        }
+    }
+
+    /// Whether the notification server is missing but can still show up, when it is not forced
+    /// from `config`
+    pub fn may_gain_notification_server(&self, config: &CapabilitiesConfig) -> bool {
+        !self.notification_server && self.session_bus && config.notification_server.is_none()
+    }
+}
+
+/// Look for the notification server every minute, until it shows up
+pub fn watch_notification_server(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    loop_handle
+        .insert_source(
+            Timer::from_duration(NOTIFICATION_SERVER_PROBE),
+            |_, _, state: &mut Passata| {
+                let found = Connection::session()
+                    .is_ok_and(|connection| has_notification_server(&connection));
+                if !found {
+                    return TimeoutAction::ToDuration(NOTIFICATION_SERVER_PROBE);
+                }
+                info!("the notification server is running now");
+                state.notification_server = true;
+                TimeoutAction::Drop
+            },
+        )
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Whether a notification server is running or can be activated
+fn has_notification_server(connection: &Connection) -> bool {
+    const NAME: &str = "org.freedesktop.Notifications";
+    let Ok(dbus) = DBusProxy::new(connection) else {
+        return false;
+    };
+    dbus.name_has_owner(BusName::try_from(NAME).unwrap())
+        .unwrap_or(false)
+        || dbus
+            .list_activatable_names()
+            .is_ok_and(|names| names.iter().any(|name| name.as_str() == NAME))
+}
+
+/// Whether the socket of the pipewire daemon exists
+fn has_pipewire() -> bool {
+    let remote = PathBuf::from(env::var_os("PIPEWIRE_REMOTE").unwrap_or("pipewire-0".into()));
+    if remote.is_absolute() {
+        return remote.exists();
+    }
+    env::var_os("PIPEWIRE_RUNTIME_DIR")
+        .or_else(|| env::var_os("XDG_RUNTIME_DIR"))
+        .is_some_and(|dir| PathBuf::from(dir).join(&remote).exists())
+}
@@ -0,0 +1,576 @@
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate};
+
+use color_eyre::{eyre::ContextCompat, Result};
+use lexopt::{
+    Arg::{Long, Short, Value},
+    Parser, ValueExt,
+};
+use log::LevelFilter;
+
+use crate::{
+    backup, bar,
+    config::Config,
+    error::IpcError,
+    i18n::Language,
+    idle, init,
+    ipc::{self, Request, Response},
+    journal::JournalEntry,
+    report,
+    schedule::NEXT_BREAKS,
+    stats::{self, ExportFilter, ExportFormat},
+    survey, team, tui, watch,
+};
+
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub daemon: bool,
+    /// Run the benchmark of the state machine instead of the daemon
+    pub bench: bool,
+    pub command: Option<Subcommand>,
+}
+
+/// Commands that run once instead of starting the daemon
+pub enum Subcommand {
+    /// Parse and validate the config file
+    CheckConfig,
+    /// Write the default config file
+    Init {
+        systemd: bool,
+        force: bool,
+    },
+    /// Write the config file asking the main settings
+    Setup,
+    Status {
+        json: bool,
+    },
+    /// Print the phases until the end of the day
+    Plan {
+        json: bool,
+    },
+    /// Print when the next breaks start
+    Next {
+        count: usize,
+        json: bool,
+    },
+    Extend {
+        duration: Duration,
+    },
+    Shorten {
+        duration: Duration,
+    },
+    /// Start the work interval waiting after a break
+    Start,
+    /// Show or hide the progress widget
+    Widget,
+    /// Show the dashboard in the terminal
+    Tui,
+    /// Write the status for swaybar and i3bar, as their status_command
+    Bar,
+    /// Show a live countdown on one line of the terminal
+    Watch,
+    /// Write the history of the pomodoros to stdout
+    StatsExport {
+        format: ExportFormat,
+        filter: ExportFilter,
+    },
+    /// Write the answers to the break survey and the suggested work interval
+    StatsSurvey,
+    /// Write the idle periods of each work interval, today by default
+    StatsIdle {
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    /// Write the summary of the previous week
+    Report,
+    /// Write a badge with the pomodoros completed today
+    StatsBadge {
+        path: PathBuf,
+    },
+    /// Skip the breaks for a while, or stop skipping them with "off"
+    Focus {
+        duration: Duration,
+    },
+    /// Count the work interval up until `passata stop`
+    Stopwatch,
+    /// Postpone the coming break
+    Snooze,
+    /// End the stopwatch session and start its break
+    Stop,
+    /// Label the work with a task, or remove the label
+    Task {
+        label: Option<String>,
+    },
+    /// Change a setting of the running daemon and of its config file
+    Set {
+        key: String,
+        value: String,
+    },
+    /// Forward the messages of the team mode between the peers
+    Relay {
+        address: SocketAddr,
+    },
+    /// Save the history to a file, or print it
+    Backup {
+        path: Option<PathBuf>,
+    },
+    /// Replace the history with a backup
+    Restore {
+        path: PathBuf,
+        force: bool,
+    },
+    /// Print the last events of the timer
+    Events {
+        follow: bool,
+        json: bool,
+    },
+    /// Print the log of the daemon as it is written
+    Log {
+        level: LevelFilter,
+        subsystem: Option<String>,
+    },
+}
+
+pub fn parse_args() -> Result<Args, lexopt::Error> {
+    let mut config: Option<PathBuf> = None;
+    let mut daemon = false;
+    let mut bench = false;
+    let mut command = None;
+    let mut parser = lexopt::Parser::from_env();
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short('c') | Long("config") => {
+                config = Some(PathBuf::from(parser.value()?));
+            }
+            Short('d') | Long("daemon") => {
+                daemon = true;
+            }
+            Long("bench") => {
+                bench = true;
+            }
+            Value(value) if command.is_none() => {
+                let name = value.string()?;
+                command = Some(match name.as_str() {
+                    "check-config" => Subcommand::CheckConfig,
+                    "init" => parse_init(&mut parser)?,
+                    "setup" => Subcommand::Setup,
+                    "log" => parse_log(&mut parser)?,
+                    "tui" => Subcommand::Tui,
+                    "bar" => Subcommand::Bar,
+                    "watch" => Subcommand::Watch,
+                    "start" => Subcommand::Start,
+                    "widget" => Subcommand::Widget,
+                    "stopwatch" => Subcommand::Stopwatch,
+                    "snooze" => Subcommand::Snooze,
+                    "stop" => Subcommand::Stop,
+                    "task" => parse_task(&mut parser)?,
+                    "set" => Subcommand::Set {
+                        key: parser.value()?.string()?,
+                        value: parser.value()?.string()?,
+                    },
+                    "focus" => parse_focus(&mut parser)?,
+                    "relay" => Subcommand::Relay {
+                        address: parser.value()?.parse()?,
+                    },
+                    "stats" => parse_stats(&mut parser)?,
+                    "status" => parse_status(&mut parser)?,
+                    "report" => Subcommand::Report,
+                    "plan" => Subcommand::Plan {
+                        json: parse_json_flag(&mut parser)?,
+                    },
+                    "next" => parse_next(&mut parser)?,
+                    "events" => parse_events(&mut parser)?,
+                    "backup" => parse_backup(&mut parser)?,
+                    "restore" => parse_restore(&mut parser)?,
+                    "extend" => Subcommand::Extend {
+                        duration: parse_duration(&mut parser)?,
+                    },
+                    "shorten" => Subcommand::Shorten {
+                        duration: parse_duration(&mut parser)?,
+                    },
+                    _ => return Err(format!("unknown command {name:?}").into()),
+                });
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    Ok(Args {
+        config,
+        daemon,
+        bench,
+        command,
+    })
+}
+
+fn parse_status(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    Ok(Subcommand::Status {
+        json: parse_json_flag(parser)?,
+    })
+}
+
+fn parse_next(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let mut count = NEXT_BREAKS;
+    let mut json = false;
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("json") => json = true,
+            Value(value) => count = value.parse()?,
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    Ok(Subcommand::Next { count, json })
+}
+
+fn parse_backup(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let mut path = None;
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Value(value) if path.is_none() => path = Some(PathBuf::from(value)),
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    Ok(Subcommand::Backup { path })
+}
+
+fn parse_restore(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let mut path = None;
+    let mut force = false;
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short('f') | Long("force") => force = true,
+            Value(value) if path.is_none() => path = Some(PathBuf::from(value)),
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    let path = path.ok_or("missing the backup to restore")?;
+    Ok(Subcommand::Restore { path, force })
+}
+
+fn parse_events(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let mut follow = false;
+    let mut json = false;
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short('f') | Long("follow") => follow = true,
+            Long("json") => json = true,
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    Ok(Subcommand::Events { follow, json })
+}
+
+fn parse_json_flag(parser: &mut Parser) -> Result<bool, lexopt::Error> {
+    let mut json = false;
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("json") => json = true,
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    Ok(json)
+}
+
+fn parse_init(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let mut systemd = false;
+    let mut force = false;
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("systemd") => systemd = true,
+            Short('f') | Long("force") => force = true,
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    Ok(Subcommand::Init { systemd, force })
+}
+
+fn parse_log(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let mut level = LevelFilter::Trace;
+    let mut subsystem = None;
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short('l') | Long("level") => level = parser.value()?.parse()?,
+            Short('s') | Long("subsystem") => subsystem = Some(parser.value()?.string()?),
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    Ok(Subcommand::Log { level, subsystem })
+}
+
+fn parse_task(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let mut label = None;
+    let mut clear = false;
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("clear") => clear = true,
+            Value(value) if label.is_none() => label = Some(value.string()?),
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    match (label, clear) {
+        (Some(_), true) => Err("a task label cannot be given together with --clear".into()),
+        (None, false) => Err("missing the task label, or --clear to remove it".into()),
+        (label, _) => Ok(Subcommand::Task { label }),
+    }
+}
+
+fn parse_stats(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    match parser.next()? {
+        Some(Value(value)) if value == "export" => parse_stats_export(parser),
+        Some(Value(value)) if value == "survey" => match parser.next()? {
+            Some(arg) => Err(arg.unexpected()),
+            None => Ok(Subcommand::StatsSurvey),
+        },
+        Some(Value(value)) if value == "idle" => parse_stats_idle(parser),
+        Some(Long("badge")) => {
+            let path = PathBuf::from(parser.value()?);
+            match parser.next()? {
+                Some(arg) => Err(arg.unexpected()),
+                None => Ok(Subcommand::StatsBadge { path }),
+            }
+        }
+        Some(arg) => Err(arg.unexpected()),
+        None => Err("missing the stats command, export, survey, idle or --badge".into()),
+    }
+}
+
+fn parse_stats_idle(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let (mut from, mut to) = (None, None);
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("from") => from = Some(parse_date(parser)?),
+            Long("to") => to = Some(parse_date(parser)?),
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    Ok(Subcommand::StatsIdle { from, to })
+}
+
+fn parse_stats_export(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let mut format = ExportFormat::Csv;
+    let mut filter = ExportFilter {
+        from: None,
+        to: None,
+        abandoned: false,
+    };
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short('f') | Long("format") => format = parser.value()?.parse()?,
+            Long("from") => filter.from = Some(parse_date(parser)?),
+            Long("to") => filter.to = Some(parse_date(parser)?),
+            Long("abandoned") => filter.abandoned = true,
+            _ => return Err(arg.unexpected()),
+        }
+    }
+    Ok(Subcommand::StatsExport { format, filter })
+}
+
+fn parse_date(parser: &mut Parser) -> Result<NaiveDate, lexopt::Error> {
+    parser
+        .value()?
+        .parse_with(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+}
+
+fn parse_focus(parser: &mut Parser) -> Result<Subcommand, lexopt::Error> {
+    let value = parser.value()?;
+    let duration = if value == "off" {
+        Duration::ZERO
+    } else {
+        value.parse_with(humantime::parse_duration)?
+    };
+    Ok(Subcommand::Focus { duration })
+}
+
+fn parse_duration(parser: &mut Parser) -> Result<Duration, lexopt::Error> {
+    parser.value()?.parse_with(humantime::parse_duration)
+}
+
+pub fn run(command: Subcommand, socket: &Path, config_file: &Path) -> Result<()> {
+    match command {
+        Subcommand::CheckConfig => {
+            Config::load(config_file)?;
+            println!("{config_file:?} is valid");
+        }
+        Subcommand::Init { systemd, force } => init::init(config_file, systemd, force)?,
+        Subcommand::Setup => init::setup(config_file)?,
+        Subcommand::Status { json } => {
+            let status = match ipc::send(socket, &Request::Status)? {
+                Response::Status(status) => status,
+                response => return unexpected(response),
+            };
+            if json {
+                println!("{}", serde_json::to_string(&status)?);
+            } else {
+                println!("{status}");
+                for upcoming in &status.upcoming {
+                    println!(
+                        "  {} at {}",
+                        upcoming.name(),
+                        DateTime::<Local>::from(upcoming.at).format("%H:%M")
+                    );
+                }
+                println!("Daemon: {}", status.health);
+            }
+        }
+        Subcommand::Plan { json } => {
+            let (current, phases) = match ipc::send(socket, &Request::Plan)? {
+                Response::Plan { current, phases } => (current, phases),
+                response => return unexpected(response),
+            };
+            if json {
+                println!("{}", serde_json::to_string(&phases)?);
+                return Ok(());
+            }
+            println!("Now: {current}");
+            for phase in &phases {
+                println!(
+                    "{}  {} ({})",
+                    DateTime::<Local>::from(phase.at).format("%H:%M"),
+                    phase.name(),
+                    humantime::format_duration(phase.length)
+                );
+            }
+            if let Some(last) = phases.last() {
+                println!(
+                    "{}  Last phase of the day over",
+                    DateTime::<Local>::from(last.at + last.length).format("%H:%M")
+                );
+            }
+        }
+        Subcommand::Next { count, json } => {
+            let phases = match ipc::send(socket, &Request::Next { count })? {
+                Response::Plan { phases, .. } => phases,
+                response => return unexpected(response),
+            };
+            if json {
+                println!("{}", serde_json::to_string(&phases)?);
+                return Ok(());
+            }
+            for phase in &phases {
+                println!(
+                    "{} at {}, for {}",
+                    phase.name(),
+                    DateTime::<Local>::from(phase.at).format("%H:%M"),
+                    humantime::format_duration(phase.length)
+                );
+            }
+        }
+        Subcommand::Extend { duration } => print_status(socket, &Request::Extend { duration })?,
+        Subcommand::Shorten { duration } => print_status(socket, &Request::Shorten { duration })?,
+        Subcommand::Tui => tui::run(socket)?,
+        Subcommand::Bar => bar::run(socket)?,
+        Subcommand::Watch => watch::run(socket)?,
+        Subcommand::Start => print_status(socket, &Request::Start)?,
+        Subcommand::Widget => print_status(socket, &Request::ToggleWidget)?,
+        Subcommand::Focus { duration } => print_status(socket, &Request::Focus { duration })?,
+        Subcommand::StatsBadge { path } => {
+            stats::write_badge(&path, stats::completed_today(&stats::history_path()?)?)?;
+        }
+        Subcommand::StatsExport { format, filter } => {
+            stats::export(
+                &stats::history_path()?,
+                format,
+                &filter,
+                &mut io::stdout().lock(),
+            )?;
+        }
+        Subcommand::StatsSurvey => {
+            survey::report(&stats::history_path()?, &mut io::stdout().lock())?;
+        }
+        Subcommand::StatsIdle { from, to } => {
+            let today = Local::now().date_naive();
+            idle::report(
+                &stats::history_path()?,
+                from.unwrap_or(today),
+                to.unwrap_or(today),
+                &mut io::stdout().lock(),
+            )?;
+        }
+        Subcommand::Report => {
+            let language = Config::load(config_file)?
+                .language
+                .unwrap_or_else(Language::detect);
+            report::print(&stats::history_path()?, language, &mut io::stdout().lock())?;
+        }
+        Subcommand::Stopwatch => print_status(socket, &Request::Stopwatch)?,
+        Subcommand::Snooze => print_status(socket, &Request::Snooze)?,
+        Subcommand::Stop => print_status(socket, &Request::Stop)?,
+        Subcommand::Task { label } => print_status(socket, &Request::Task { label })?,
+        Subcommand::Relay { address } => {
+            let config = Config::load(config_file)?;
+            config.ensure_network_allowed("relay")?;
+            let secret = config
+                .team
+                .secret
+                .filter(|secret| !secret.is_empty())
+                .wrap_err("team.secret must be set to relay the messages of the team")?;
+            team::relay(address, &secret)?;
+        }
+        Subcommand::Set { key, value } => print_status(socket, &Request::Set { key, value })?,
+        Subcommand::Backup { path } => backup::backup(path.as_deref())?,
+        Subcommand::Restore { path, force } => backup::restore(&path, force, socket)?,
+        Subcommand::Events { follow, json } => {
+            let print = |entry: &JournalEntry| -> Result<()> {
+                if json {
+                    println!("{}", serde_json::to_string(entry)?);
+                } else {
+                    println!("{entry}");
+                }
+                Ok(())
+            };
+            let mut responses = ipc::stream(socket, &Request::Events { follow })?;
+            match responses.next().transpose()? {
+                Some(Response::Events { events }) => events.iter().try_for_each(print)?,
+                Some(response) => return unexpected(response),
+                None => return Err(IpcError::NoResponse.into()),
+            }
+            for response in responses {
+                match response? {
+                    Response::Event(entry) => print(&entry)?,
+                    response => return unexpected(response),
+                }
+            }
+        }
+        Subcommand::Log { level, subsystem } => {
+            let request = Request::Log {
+                level: level.to_string(),
+                subsystem,
+            };
+            let mut responses = ipc::stream(socket, &request)?;
+            match responses.next().transpose()? {
+                Some(Response::Following) => {}
+                Some(response) => return unexpected(response),
+                None => return Err(IpcError::NoResponse.into()),
+            }
+            for response in responses {
+                match response? {
+                    Response::Log { line } => println!("{line}"),
+                    response => return unexpected(response),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Send a request answered with the updated status and print it
+fn print_status(socket: &Path, request: &Request) -> Result<()> {
+    match ipc::send(socket, request)? {
+        Response::Status(status) => println!("{status}"),
+        response => return unexpected(response),
+    }
+    Ok(())
+}
+
+fn unexpected(response: Response) -> Result<()> {
+    match response {
+        Response::Error { message } => Err(IpcError::Daemon(message).into()),
+        _ => Err(IpcError::UnexpectedResponse.into()),
+    }
+}
@@ -0,0 +1,129 @@
+//! Command taking over the breaks, e.g. a player of stretching videos. It is started with the
+//! break in its own process group and either stopped when the break is over, or the break is
+//! over as soon as it exits, with the length of the break as timeout. A screen locker does not
+//! fit: stopping it leaves the session locked, lock-on-long-break is there for that.
+
+use std::{
+    os::unix::process::CommandExt,
+    process::{Child, Command},
+    thread,
+};
+
+use color_eyre::{eyre::WrapErr, Result};
+use log::{debug, error, warn};
+use nix::{
+    sys::{
+        signal::{killpg, Signal},
+        wait::{waitid, Id, WaitPidFlag},
+    },
+    unistd::Pid,
+};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::channel::Sender;
+
+use crate::{events::NotificationAction, timer::Phase, Passata};
+
+/// What ends a break running a command
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BreakCommandEnd {
+    /// The break lasts its length, stopping the command if it is still running
+    #[default]
+    Duration,
+    /// The command exiting, or the length of the break at the latest
+    Exit,
+}
+
+/// Send `action` once `child` exits, leaving it to be reaped by the daemon so that its pid
+/// cannot be reused while the daemon still refers to it
+pub fn watch_exit(child: &Child, sender: Sender<NotificationAction>, action: NotificationAction) {
+    let pid = Pid::from_raw(child.id() as i32);
+    thread::spawn(move || {
+        if let Err(err) = waitid(Id::Pid(pid), WaitPidFlag::WEXITED | WaitPidFlag::WNOWAIT) {
+            warn!("waiting for the process {pid}: {err}");
+        }
+        // the daemon is gone if this fails
+        let _ = sender.send(action);
+    });
+}
+
+fn spawn_command(command: &str) -> Result<Child> {
+    Command::new("sh")
+        .args(["-c", command])
+        .process_group(0)
+        .spawn()
+        .wrap_err_with(|| format!("running {command:?}"))
+}
+
+impl Passata {
+    /// Command of the current break and what ends the break, if it runs one
+    fn break_command(&self) -> Option<(&str, BreakCommandEnd)> {
+        let break_type = self.break_type();
+        let command = break_type
+            .and_then(|break_type| break_type.command.as_deref())
+            .or(self.config.break_command.as_deref())?;
+        let end = break_type
+            .and_then(|break_type| break_type.command_end)
+            .unwrap_or(self.config.break_command_end);
+        Some((command, end))
+    }
+
+    /// Run the command of the break that just started, if any
+    pub fn start_break_command(&mut self) {
+        let Some((command, _)) = self.break_command() else {
+            return;
+        };
+        let child = match spawn_command(command) {
+            Ok(child) => child,
+            Err(err) => {
+                warn!("starting the break command: {err:?}");
+                return;
+            }
+        };
+        watch_exit(
+            &child,
+            self.notification_actions.clone(),
+            NotificationAction::BreakCommandExited { pid: child.id() },
+        );
+        self.break_command = Some(child);
+    }
+
+    /// Stop the command of the break, with every process it started, if it is still running
+    pub fn stop_break_command(&mut self) {
+        if let Some(child) = self.break_command.take() {
+            // the shell is not reaped yet, its group cannot have been reused
+            let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGTERM);
+            self.stopped_commands.push(child);
+        }
+    }
+
+    /// The command `pid` exited, ending its break if it is configured so
+    pub fn break_command_exited(&mut self, pid: u32) {
+        if let Some(index) = self
+            .stopped_commands
+            .iter()
+            .position(|child| child.id() == pid)
+        {
+            let mut child = self.stopped_commands.swap_remove(index);
+            if let Ok(status) = child.wait() {
+                debug!("the stopped break command exited with {status}");
+            }
+            return;
+        }
+        let Some(mut child) = self.break_command.take_if(|child| child.id() == pid) else {
+            return;
+        };
+        match child.wait() {
+            Ok(status) => debug!("the break command exited with {status}"),
+            Err(err) => warn!("waiting for the break command: {err}"),
+        }
+        if self.phase == Phase::Work
+            || self.break_command().map(|(_, end)| end) != Some(BreakCommandEnd::Exit)
+        {
+            return;
+        }
+        if let Err(err) = self.next_phase() {
+            error!("ending the break after its command: {err:?}");
+        }
+    }
+}
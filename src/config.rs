@@ -0,0 +1,634 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    mem,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::{
+    eyre::{ensure, ContextCompat, WrapErr},
+    Report, Result,
+};
+use figment::{
+    providers::{Env, Format, Toml},
+    Figment,
+};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, InlineTable, Item, Table, Value};
+
+use crate::{
+    activities::ActivitiesConfig,
+    algorithm::{AdaptiveConfig, Algorithm, FlowtimeConfig},
+    apps::WorkAppsConfig,
+    battery::BatteryPolicy,
+    breaks::BreakType,
+    calendar::CalendarConfig,
+    capabilities::CapabilitiesConfig,
+    command::BreakCommandEnd,
+    dayoff::DayOffConfig,
+    debt::BreakDebtConfig,
+    error::ConfigError,
+    escalation::EscalationConfig,
+    gamma::DimmingConfig,
+    i18n::Language,
+    idle::IdleBackend,
+    lock::LockPolicy,
+    logs::LogFormat,
+    meetings::MeetingsConfig,
+    mqtt::MqttConfig,
+    notify::{default_notifiers, NotificationMode, NotifierKind},
+    overlay::OverlayConfig,
+    profile::{Profile, TimeRangeProfile},
+    prompt::BreakPrompt,
+    quiet::QuietHoursConfig,
+    report::ReportConfig,
+    snooze::SnoozeConfig,
+    stats::HistoryConfig,
+    survey::SurveyConfig,
+    suspend::SuspendPolicy,
+    team::TeamConfig,
+    timeclock::TimeTrackingConfig,
+    widget::WidgetConfig,
+    workspaces::WorkspacesConfig,
+    Passata,
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(rename = "interval", with = "humantime_serde")]
+    pub work_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub short_break: Duration,
+    #[serde(with = "humantime_serde")]
+    pub long_break: Option<Duration>,
+    pub short_breaks_before_long_break: Option<u8>,
+    /// Align the work intervals to periods of this length from midnight, the breaks starting
+    /// at the same minutes of the wall clock
+    #[serde(default, with = "humantime_serde")]
+    pub align_work: Option<Duration>,
+    /// How the work intervals and the breaks are timed
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    #[serde(default)]
+    pub flowtime: FlowtimeConfig,
+    #[serde(default)]
+    pub snooze: SnoozeConfig,
+    #[serde(default)]
+    pub adaptive: AdaptiveConfig,
+    /// Pause the timer after being idle for this long
+    #[serde(alias = "idle-timeout", default, with = "humantime_serde")]
+    pub idle_pause: Option<Duration>,
+    /// Start the work interval over after being idle for this long
+    #[serde(default, with = "humantime_serde")]
+    pub idle_reset: Option<Duration>,
+    /// Count being idle during the work interval as its break once it lasted as long, starting
+    /// the work interval over instead of breaking right after coming back
+    #[serde(default)]
+    pub idle_break: bool,
+    /// End the break as soon as the user is back from it, starting the work interval
+    #[serde(default)]
+    pub end_break_on_activity: bool,
+    /// Time away from the input during the break needed for coming back to end it
+    #[serde(default = "default_end_break_absence", with = "humantime_serde")]
+    pub end_break_absence: Duration,
+    /// Name of the seat whose idle state is followed, the first one by default
+    pub seat: Option<String>,
+    /// Where the idle state comes from, the wayland protocol when the compositor supports it
+    /// and logind otherwise when not set
+    pub idle_backend: Option<IdleBackend>,
+    /// Treat the time under an idle inhibitor held through logind as media time, pausing the
+    /// work interval
+    #[serde(default)]
+    pub idle_inhibitors: bool,
+    /// What the time with the screen locked during a work interval counts as
+    #[serde(default)]
+    pub lock_policy: LockPolicy,
+    /// How the schedule is relaxed while the laptop runs on battery
+    #[serde(default)]
+    pub on_battery: BatteryPolicy,
+    /// What the time spent suspended during a work interval counts as
+    #[serde(default)]
+    pub suspend_policy: SuspendPolicy,
+    /// Lock the screen when a long break starts
+    #[serde(default)]
+    pub lock_on_long_break: bool,
+    /// App ids whose focus spares the user the overlay and the lock of the breaks, e.g. an
+    /// emergency terminal or a screen sharing tool
+    #[serde(default)]
+    pub enforcement_exceptions: Vec<String>,
+    /// Defer the breaks while an application inhibits the idle of the GNOME or KDE session,
+    /// by default on those desktops
+    pub respect_inhibitors: Option<bool>,
+    /// Defer the breaks while an application records from the microphone
+    #[serde(default)]
+    pub defer_on_mic: bool,
+    /// Start a deferred break anyway after this long, whatever holds it back
+    #[serde(default = "default_max_deferral", with = "humantime_serde")]
+    pub max_deferral: Duration,
+    /// Start the work interval as soon as the break is over, instead of waiting for the user
+    /// to start it from the notification or with `passata start`
+    #[serde(default = "default_auto_start_work")]
+    pub auto_start_work: bool,
+    /// How the end of the break is acknowledged; the dialog holds every work interval until
+    /// it is clicked, regardless of auto-start-work
+    #[serde(default)]
+    pub break_prompt: BreakPrompt,
+    /// Command run for the length of each break, e.g. a player of stretching videos
+    pub break_command: Option<String>,
+    /// What ends a break running a command
+    #[serde(default)]
+    pub break_command_end: BreakCommandEnd,
+    /// Master switch of every feature opening network sockets (HTTP, MQTT, webhooks, sync),
+    /// checked through `ensure_network_allowed`
+    #[serde(default)]
+    pub allow_network: bool,
+    /// Address of the HTTP server exposing the status and the controls, e.g. 127.0.0.1:7777;
+    /// needs allow-network
+    pub listen: Option<SocketAddr>,
+    /// Token the HTTP requests must carry as `Authorization: Bearer <token>`
+    pub listen_token: Option<String>,
+    /// Broker to publish the state to, with the Home Assistant discovery; needs allow-network
+    pub mqtt: Option<MqttConfig>,
+    /// Store with each pomodoro the share of it spent using the keyboard or the mouse
+    #[serde(default)]
+    pub input_intensity: bool,
+    /// Hold the break back for up to this long while the user is typing, until a micro-pause
+    #[serde(default, with = "humantime_serde")]
+    pub grace_typing: Option<Duration>,
+    /// Format of the log, "text" or "json"
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Ways of showing the notifications, each one tried when the previous one fails
+    #[serde(default = "default_notifiers")]
+    pub notifiers: Vec<NotifierKind>,
+    /// Whether the notification of a phase starting replaces the one of the previous phase
+    #[serde(default)]
+    pub notification_mode: NotificationMode,
+    /// Language of the messages shown to the user, the one of the locale by default
+    pub language: Option<Language>,
+    /// Sounds played by the notification daemon with the notifications, as names from the XDG
+    /// sound theme or paths: when the work starts again, when a break starts (unless the named
+    /// break has its own) and when the break is over and the work waits for the user
+    pub sound_work_start: Option<String>,
+    pub sound_break_start: Option<String>,
+    pub sound_break_end: Option<String>,
+    /// Expose the timer as a MPRIS media player
+    #[serde(default)]
+    pub mpris: bool,
+    /// Show the minutes left in a tray icon
+    #[serde(default)]
+    pub tray: bool,
+    #[serde(default)]
+    pub capabilities: CapabilitiesConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    #[serde(default)]
+    pub escalation: EscalationConfig,
+    #[serde(default)]
+    pub overlay: OverlayConfig,
+    #[serde(default)]
+    pub widget: WidgetConfig,
+    #[serde(default)]
+    pub dimming: DimmingConfig,
+    #[serde(default)]
+    pub workspaces: WorkspacesConfig,
+    #[serde(default)]
+    pub work_apps: WorkAppsConfig,
+    /// Windows deferring the breaks while focused
+    #[serde(default)]
+    pub meetings: MeetingsConfig,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    /// Timings overriding the ones above and the profile for the work intervals starting in
+    /// a range of the day
+    #[serde(default)]
+    pub intervals: HashMap<String, TimeRangeProfile>,
+    /// Profile to switch to when connected to each Wi-Fi network, by SSID
+    #[serde(default)]
+    pub network_profiles: HashMap<String, String>,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub calendar: CalendarConfig,
+    #[serde(default)]
+    pub day_off: DayOffConfig,
+    #[serde(default)]
+    pub quiet_hours: QuietHoursConfig,
+    #[serde(default)]
+    pub break_debt: BreakDebtConfig,
+    /// SVG badge with the pomodoros completed today, rewritten after each pomodoro
+    pub badge: Option<PathBuf>,
+    /// Pomodoros to complete each day
+    pub daily_goal: Option<u32>,
+    #[serde(default)]
+    pub activities: ActivitiesConfig,
+    #[serde(default)]
+    pub survey: SurveyConfig,
+    #[serde(default)]
+    pub report: ReportConfig,
+    #[serde(default)]
+    pub time_tracking: TimeTrackingConfig,
+    /// Breaks taken together with the peers; needs allow-network
+    #[serde(default)]
+    pub team: TeamConfig,
+    #[serde(default)]
+    pub breaks: HashMap<String, BreakType>,
+    /// Names of the breaks taken after each work interval, in order, replacing the
+    /// short/long break alternation
+    #[serde(default)]
+    pub sequence: Vec<String>,
+}
+
+fn default_auto_start_work() -> bool {
+    true
+}
+
+fn default_max_deferral() -> Duration {
+    Duration::from_secs(30 * 60)
+}
+
+fn default_end_break_absence() -> Duration {
+    Duration::from_secs(2 * 60)
+}
+
+/// Switches for the single behaviors triggered by the events
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct EventsConfig {
+    /// Show the time left before the next break when resuming from idle
+    pub resume_notification: bool,
+    /// Append the "(n/m)" counter to the short break summary
+    pub short_break_counter: bool,
+    /// Show a notification with the time of the next break when a work interval starts again
+    pub work_notification: bool,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            resume_notification: true,
+            short_break_counter: true,
+            work_notification: true,
+        }
+    }
+}
+
+/// Names accepted by `passata set` for the keys whose name in the file is less obvious
+const KEY_ALIASES: [(&str, &str); 2] = [
+    ("work-interval", "interval"),
+    ("idle-timeout", "idle-pause"),
+];
+
+impl Config {
+    /// Set `key`, a dotted path like "dimming.brightness", to `value` in the config file at
+    /// `path`, keeping its comments and layout, and return the resulting config. The key and
+    /// its tables are added when missing, and the file created. The file is only replaced when
+    /// the result is valid, following the symlinks. `value` is read as a TOML value, or as a
+    /// string when it is not one, so that durations do not need quotes
+    pub fn set(path: &Path, key: &str, value: &str) -> Result<Self> {
+        // replace the file the symlinks point to, not the symlinks
+        let path = match fs::canonicalize(path) {
+            Ok(path) => path,
+            Err(err) if err.kind() == ErrorKind::NotFound => path.to_path_buf(),
+            Err(err) => return Err(err).wrap_err_with(|| format!("resolving {path:?}")),
+        };
+        let path = path.as_path();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err).wrap_err_with(|| format!("reading {path:?}")),
+        };
+        let mut document = content
+            .parse::<DocumentMut>()
+            .wrap_err_with(|| format!("parsing {path:?}"))?;
+        let key = KEY_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == key)
+            .map_or(key, |(_, key)| key);
+        let (parents, name) = match key.rsplit_once('.') {
+            Some((parents, name)) => (Some(parents), name),
+            None => (None, key),
+        };
+        let mut table = document.as_item_mut();
+        for parent in parents.into_iter().flat_map(|parents| parents.split('.')) {
+            // the missing tables are written the way their parent is
+            let missing = if table.is_table() {
+                let mut missing = Table::new();
+                missing.set_implicit(true);
+                Item::Table(missing)
+            } else {
+                Item::Value(Value::InlineTable(InlineTable::new()))
+            };
+            table = table
+                .as_table_like_mut()
+                .with_context(|| format!("the parent of {parent} in {key} is not a table"))?
+                .entry(parent)
+                .or_insert(missing);
+        }
+        let item = table
+            .as_table_like_mut()
+            .with_context(|| format!("the parent of {name} in {key} is not a table"))?
+            .entry(name)
+            .or_insert(Item::None);
+        let mut value = value
+            .parse::<Value>()
+            .unwrap_or_else(|_| Value::from(value));
+        // keep the comment at the end of the line
+        if let Some(old) = item.as_value() {
+            *value.decor_mut() = old.decor().clone();
+        }
+        *item = toml_edit::value(value);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).wrap_err_with(|| format!("creating {dir:?}"))?;
+        }
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, document.to_string()).wrap_err_with(|| format!("writing {tmp:?}"))?;
+        let config = Config::load(&tmp).map_err(Report::from).and_then(|config| {
+            ensure!(config.has_key(key), "{key} is not a valid key");
+            Ok(config)
+        });
+        if config.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        let config = config?;
+        fs::rename(&tmp, path).wrap_err_with(|| format!("replacing {path:?}"))?;
+        Ok(config)
+    }
+
+    /// Whether the dotted path `key` is set in the config, which catches the misspelled keys
+    /// that are otherwise ignored
+    fn has_key(&self, key: &str) -> bool {
+        toml::Value::try_from(self).map_or(true, |config| {
+            key.split('.')
+                .try_fold(&config, |table, part| table.get(part))
+                .is_some()
+        })
+    }
+
+    /// Read the config from `path` and the `PASSATA_` environment variables, then validate it
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        if !path.exists() {
+            return Err(ConfigError::NotFound(path.to_path_buf()));
+        }
+        let invalid = |errors| ConfigError::Invalid {
+            path: path.to_path_buf(),
+            errors,
+        };
+        let config: Config = Figment::new()
+            .merge(Toml::file(path))
+            .merge(Env::prefixed("PASSATA"))
+            .extract()
+            .map_err(|err| invalid(err.into_iter().map(|err| err.to_string()).collect()))?;
+        let errors = config.validate();
+        if !errors.is_empty() {
+            return Err(invalid(errors));
+        }
+        Ok(config)
+    }
+
+    /// Fail unless the user allowed network access; every feature that opens a network socket
+    /// must go through here before doing it
+    pub fn ensure_network_allowed(&self, feature: &str) -> Result<(), ConfigError> {
+        if !self.allow_network {
+            return Err(ConfigError::NetworkDisabled {
+                feature: feature.to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check the values that can be parsed but make no sense, returning one message for each
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        check_timings(
+            &mut errors,
+            "",
+            Some(self.work_interval),
+            Some(self.short_break),
+            self.long_break,
+            self.short_breaks_before_long_break,
+            self.long_break,
+        );
+        for (key, duration) in [
+            ("idle-pause", self.idle_pause),
+            ("idle-reset", self.idle_reset),
+            ("grace-typing", self.grace_typing),
+        ] {
+            if duration == Some(Duration::ZERO) {
+                errors.push(format!("{key} must be greater than zero"));
+            }
+        }
+        if let (Some(idle_pause), Some(idle_reset)) = (self.idle_pause, self.idle_reset) {
+            if idle_reset <= idle_pause {
+                errors.push(format!(
+                    "idle-reset ({}) must be longer than idle-pause ({})",
+                    humantime::format_duration(idle_reset),
+                    humantime::format_duration(idle_pause)
+                ));
+            }
+        }
+        for (name, profile) in &self.profiles {
+            check_timings(
+                &mut errors,
+                &format!("profiles.{name}."),
+                profile.work_interval,
+                profile.short_break,
+                profile.long_break,
+                profile.short_breaks_before_long_break,
+                profile.long_break.or(self.long_break),
+            );
+        }
+        for (name, range) in &self.intervals {
+            let timings = &range.timings;
+            check_timings(
+                &mut errors,
+                &format!("intervals.{name}."),
+                timings.work_interval,
+                timings.short_break,
+                timings.long_break,
+                timings.short_breaks_before_long_break,
+                timings.long_break.or(self.long_break),
+            );
+            if range.from == range.to {
+                errors.push(format!("intervals.{name}.from and to must be different"));
+            }
+        }
+        if self
+            .history
+            .raw_retention
+            .is_some_and(|retention| retention < Duration::from_secs(24 * 60 * 60))
+        {
+            errors.push("history.raw-retention must be at least one day".to_owned());
+        }
+        if !(0.0..=1.0).contains(&self.survey.fraction) {
+            errors.push("survey.fraction must be between 0 and 1".to_owned());
+        }
+        if !(0.0..=1.0).contains(&self.dimming.brightness) {
+            errors.push("dimming.brightness must be between 0 and 1".to_owned());
+        }
+        if self.day_off.after.is_some() && self.idle_pause.is_none() {
+            errors.push(
+                "day-off.after needs idle-pause to tell whether the computer is used".to_owned(),
+            );
+        }
+        match (self.quiet_hours.start, self.quiet_hours.end) {
+            (Some(start), Some(end)) if start == end => {
+                errors.push("quiet-hours.start and quiet-hours.end must differ".to_owned());
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                errors.push("quiet-hours needs both start and end".to_owned());
+            }
+            _ => {}
+        }
+        if let Err(err) = self.meetings.regex_set() {
+            errors.push(format!("invalid meetings.windows: {err}"));
+        }
+        if self.team.listen.is_some() && self.team.secret.as_deref().is_none_or(str::is_empty) {
+            errors.push("team.secret must be set to sign the messages of the team".to_owned());
+        }
+        if let Some(period) = self.align_work {
+            if period.as_secs() == 0 || period.subsec_nanos() != 0 || 86400 % period.as_secs() != 0
+            {
+                errors.push(format!(
+                    "align-work ({}) must divide a day in whole seconds",
+                    humantime::format_duration(period)
+                ));
+            }
+            if period < self.work_interval {
+                errors.push(format!(
+                    "align-work ({}) must not be shorter than interval ({})",
+                    humantime::format_duration(period),
+                    humantime::format_duration(self.work_interval)
+                ));
+            }
+            if self.algorithm == Algorithm::Flowtime {
+                errors.push("align-work needs a fixed interval, not flowtime".to_owned());
+            }
+        }
+        if self.widget.thickness == 0 || self.widget.thickness * 2 > self.widget.size {
+            errors.push(
+                "widget.thickness must be greater than zero and at most half of widget.size"
+                    .to_owned(),
+            );
+        }
+        if self.snooze.step.is_zero() {
+            errors.push("snooze.step must be greater than zero".to_owned());
+        }
+        if self.flowtime.ratio == 0 {
+            errors.push("flowtime.ratio must be greater than zero".to_owned());
+        }
+        if self.adaptive.min_break > self.adaptive.max_break {
+            errors.push("adaptive.min-break must not be longer than adaptive.max-break".to_owned());
+        }
+        if self.algorithm == Algorithm::Adaptive && !self.input_intensity {
+            errors.push("algorithm = \"adaptive\" needs input-intensity".to_owned());
+        }
+        if self.daily_goal == Some(0) {
+            errors.push("daily-goal must be greater than zero".to_owned());
+        }
+        if self.break_debt.after == Some(0) {
+            errors.push("break-debt.after must be greater than zero".to_owned());
+        }
+        if self.break_debt.after.is_some() && self.idle_pause.is_none() {
+            errors.push(
+                "break-debt.after needs idle-pause to tell whether a break is taken".to_owned(),
+            );
+        }
+        if self.calendar.refresh.is_zero() {
+            errors.push("calendar.refresh must be greater than zero".to_owned());
+        }
+        if self.overlay.font_size <= 0.0 {
+            errors.push("overlay.font-size must be greater than zero".to_owned());
+        }
+        for (name, break_type) in &self.breaks {
+            if break_type.duration.is_zero() {
+                errors.push(format!("breaks.{name}.duration must be greater than zero"));
+            }
+            if break_type
+                .overlay
+                .as_ref()
+                .is_some_and(|overlay| overlay.font_size <= 0.0)
+            {
+                errors.push(format!(
+                    "breaks.{name}.overlay.font-size must be greater than zero"
+                ));
+            }
+            if break_type.every == Some(0) {
+                errors.push(format!("breaks.{name}.every must be greater than zero"));
+            }
+        }
+        for name in &self.sequence {
+            if !self.breaks.contains_key(name) {
+                errors.push(format!(
+                    "sequence refers to the break {name:?}, which is not defined in [breaks]"
+                ));
+            }
+        }
+        for (ssid, profile) in &self.network_profiles {
+            if !self.profiles.contains_key(profile) {
+                errors.push(format!(
+                    "network-profiles.{ssid} refers to the profile {profile:?}, \
+                     which is not defined in [profiles]"
+                ));
+            }
+        }
+        errors
+    }
+}
+
+impl Passata {
+    /// Set `key` to `value` in the running daemon and in its config file. The timings apply
+    /// from the next phase, the settings read at startup (watchers, sockets) after a restart
+    pub fn set_config(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut config = Config::set(&self.config_path, key, value)?;
+        // the notifiers unsupported in this session have been dropped at startup
+        if key != "notifiers" {
+            config.notifiers = mem::take(&mut self.config.notifiers);
+        }
+        self.config = config;
+        // the named break chosen for later may have been removed from the file meanwhile
+        if self
+            .next_break_type
+            .take_if(|name| !self.config.breaks.contains_key(name))
+            .is_some()
+        {
+            debug!("the next named break does not exist anymore, taking a plain one instead");
+        }
+        info!("{key} set to {value}");
+        self.publish_status();
+        Ok(())
+    }
+}
+
+/// Check a set of timings, `prefix` being the path of the table they are defined in and
+/// `effective_long_break` the long break that will be used with them
+fn check_timings(
+    errors: &mut Vec<String>,
+    prefix: &str,
+    work_interval: Option<Duration>,
+    short_break: Option<Duration>,
+    long_break: Option<Duration>,
+    short_breaks_before_long_break: Option<u8>,
+    effective_long_break: Option<Duration>,
+) {
+    for (key, duration) in [
+        ("interval", work_interval),
+        ("short-break", short_break),
+        ("long-break", long_break),
+    ] {
+        if duration == Some(Duration::ZERO) {
+            errors.push(format!("{prefix}{key} must be greater than zero"));
+        }
+    }
+    if short_breaks_before_long_break.is_some() && effective_long_break.is_none() {
+        errors.push(format!(
+            "{prefix}short-breaks-before-long-break is set, but there is no long-break to take; \
+             add long-break = \"15m\" or remove it"
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+//! Panic handler of the daemon: a panic of the main thread is logged, announced with a
+//! notification and leaves the last status on disk, then the whole process aborts instead of
+//! dying silently. A background thread that panics is only logged: it ends, and the loop sees
+//! its channel closed like any other watcher that stopped.
+
+use std::{fs, panic, path::PathBuf, process, sync::Mutex, thread};
+
+use log::error;
+use notify_rust::{Notification, Timeout, Urgency};
+
+use crate::{i18n::Language, status::Status};
+
+/// Last status published, written out by the panic hook
+static LAST_STATUS: Mutex<Option<Status>> = Mutex::new(None);
+
+/// Keep `status` around, in case the daemon crashes
+pub fn remember(status: &Status) {
+    // a poisoned lock means the hook is already running
+    if let Ok(mut last) = LAST_STATUS.lock() {
+        *last = Some(status.clone());
+    }
+}
+
+/// Install the panic hook, writing the last status to `status_file` and notifying in `language`
+pub fn install_hook(status_file: PathBuf, language: Language) {
+    panic::set_hook(Box::new(move |info| {
+        let current = thread::current();
+        if current.name() != Some("main") {
+            error!(
+                "the thread {} panicked: {info}",
+                current.name().unwrap_or("without a name")
+            );
+            return;
+        }
+        error!("passata crashed: {info}");
+        // the panic may have happened while holding the lock
+        let status = LAST_STATUS.try_lock().ok().and_then(|last| last.clone());
+        let saved = match status.map(|status| serde_json::to_string_pretty(&status)) {
+            Some(Ok(json)) => match fs::write(&status_file, json) {
+                Ok(()) => true,
+                Err(err) => {
+                    error!("writing the status to {}: {err}", status_file.display());
+                    false
+                }
+            },
+            Some(Err(err)) => {
+                error!("serializing the status: {err}");
+                false
+            }
+            None => false,
+        };
+        let mut body = info.to_string();
+        if saved {
+            body.push('\n');
+            body.push_str(&language.format(
+                "crash-status",
+                &[("path", status_file.display().to_string().into())],
+            ));
+        }
+        if let Err(err) = Notification::new()
+            .summary(&language.format("crashed", &[]))
+            .body(&body)
+            .urgency(Urgency::Critical)
+            .timeout(Timeout::Never)
+            .show()
+        {
+            error!("showing the crash notification: {err}");
+        }
+        log::logger().flush();
+        process::abort();
+    }));
+}
@@ -0,0 +1,126 @@
+//! Day off detection: when nobody used the computer on a workday by a configured time, the
+//! machine has just been left on, and the daemon stays dormant instead of piling up break
+//! notifications. It wakes up with `passata start` or after a while of sustained activity.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local, Weekday};
+use color_eyre::Result;
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
+
+use crate::{events::Event, profile::TimeOfDay, timer::Phase, Passata};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct DayOffConfig {
+    /// Time of a workday by which the computer must have been used, or the day is assumed to
+    /// be off; never when not set. Needs idle-pause to tell whether it has been used
+    pub after: Option<TimeOfDay>,
+    pub workdays: Vec<Weekday>,
+    /// Activity needed to wake up the daemon on a day off
+    #[serde(with = "humantime_serde")]
+    pub wake_after: Duration,
+}
+
+impl Default for DayOffConfig {
+    fn default() -> Self {
+        Self {
+            after: None,
+            workdays: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            wake_after: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Check for the day off every day at `after`
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>, after: TimeOfDay) -> Result<()> {
+    loop_handle
+        .insert_source(
+            Timer::from_duration(after.until_next(|_| true)),
+            move |_, _, state: &mut Passata| {
+                if let Err(err) = state.check_day_off() {
+                    error!("entering the day off: {err:?}");
+                }
+                TimeoutAction::ToDuration(after.until_next(|_| true))
+            },
+        )
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+impl Passata {
+    fn check_day_off(&mut self) -> Result<()> {
+        let now = Local::now();
+        if self.day_off || !self.config.day_off.workdays.contains(&now.weekday()) {
+            return Ok(());
+        }
+        let last_active = DateTime::<Local>::from(self.last_active);
+        if !self.idle || last_active.date_naive() == now.date_naive() {
+            debug!("the computer has been used today, no day off");
+            return Ok(());
+        }
+        self.day_off = true;
+        self.emit(Event::DayOffStarted);
+        // a break started while idle keeps the cycle going, the work interval waits instead
+        if self.phase != Phase::Work {
+            self.next_phase()?;
+        }
+        self.user_paused = true;
+        self.pause();
+        self.publish_status();
+        Ok(())
+    }
+
+    /// Follow the activity during the day off, waking up after `wake-after` of it
+    pub fn day_off_activity(&mut self, idled: bool) {
+        if let Some(token) = self.day_off_wake_token.take() {
+            self.loop_handle.remove(token);
+        }
+        if idled {
+            return;
+        }
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(self.config.day_off.wake_after),
+            |_, _, state: &mut Passata| {
+                state.day_off_wake_token = None;
+                debug!("sustained activity, waking up");
+                state.user_paused = false;
+                state.end_day_off();
+                if let Some(hold) = state.resume_hold() {
+                    debug!("awake, but {hold}");
+                    state.publish_status();
+                } else if let Err(err) = state.resume() {
+                    error!("resuming the timer: {err:?}");
+                }
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(token) => self.day_off_wake_token = Some(token),
+            Err(err) => warn!("arming the end of the day off: {}", err.error),
+        }
+    }
+
+    /// The timer runs again, the daemon is not dormant anymore
+    pub fn end_day_off(&mut self) {
+        if !self.day_off {
+            return;
+        }
+        self.day_off = false;
+        if let Some(token) = self.day_off_wake_token.take() {
+            self.loop_handle.remove(token);
+        }
+        self.emit(Event::DayOffEnded);
+    }
+}
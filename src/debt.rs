@@ -0,0 +1,110 @@
+//! Break debt: after several breaks skipped in a row, the next ones are announced as critical
+//! and last longer, until one of them is actually taken.
+
+use std::{path::Path, time::Duration};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    stats::{self, Outcome},
+    Passata,
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct BreakDebtConfig {
+    /// Breaks skipped in a row after which the next ones are in debt; never when not set.
+    /// Needs idle-pause to tell whether a break has been taken
+    pub after: Option<u32>,
+    /// Added to a break in debt for each break skipped in a row
+    #[serde(with = "humantime_serde")]
+    pub extension: Duration,
+    /// Longest that a break in debt is extended
+    #[serde(with = "humantime_serde")]
+    pub max_extension: Duration,
+}
+
+impl Default for BreakDebtConfig {
+    fn default() -> Self {
+        Self {
+            after: None,
+            extension: Duration::from_secs(60),
+            max_extension: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Breaks ignored in a row at the end of the history at `path`, to carry the debt over restarts;
+/// the breaks skipped by focus mode are not recorded, and do not count in memory either
+pub fn skipped_in_row(path: &Path) -> u32 {
+    stats::read(path).map_or_else(
+        |err| {
+            warn!("counting the breaks skipped in a row: {err:?}");
+            0
+        },
+        |records| {
+            records
+                .iter()
+                .rev()
+                .filter(|record| {
+                    matches!(record.outcome, Outcome::BreakTaken | Outcome::BreakIgnored)
+                })
+                .take_while(|record| record.outcome == Outcome::BreakIgnored)
+                .count() as u32
+        },
+    )
+}
+
+impl Passata {
+    /// Whether enough breaks have been skipped in a row for the next one to be in debt
+    pub fn in_break_debt(&self) -> bool {
+        self.config
+            .break_debt
+            .after
+            .is_some_and(|after| self.breaks_skipped >= after)
+    }
+
+    /// Time added to the break starting now
+    pub fn break_debt_extension(&self) -> Duration {
+        if !self.in_break_debt() {
+            return Duration::ZERO;
+        }
+        let debt = &self.config.break_debt;
+        debt.extension
+            .saturating_mul(self.breaks_skipped)
+            .min(debt.max_extension)
+    }
+
+    /// Line of the break notification explaining why it is longer
+    pub fn break_debt_notice(&self) -> Option<String> {
+        let extension = self.break_debt_extension();
+        self.in_break_debt().then(|| {
+            self.tr_args(
+                "break-debt",
+                &[
+                    ("skipped", self.breaks_skipped.into()),
+                    (
+                        "extension",
+                        humantime::format_duration(extension).to_string().into(),
+                    ),
+                ],
+            )
+        })
+    }
+
+    /// Count the last break as taken, paying off the debt, or as skipped
+    pub fn settle_break(&mut self, taken: bool) {
+        if !taken {
+            self.breaks_skipped += 1;
+            return;
+        }
+        if self.breaks_skipped > 0 {
+            debug!(
+                "a break has been taken after {} skipped",
+                self.breaks_skipped
+            );
+        }
+        self.breaks_skipped = 0;
+    }
+}
@@ -0,0 +1,133 @@
+//! Classes of failure that callers need to tell apart. They travel inside the eyre reports like
+//! any other error and are recovered with `downcast_ref`, e.g. to pick the exit status or the
+//! hint shown with the report.
+
+use std::{io, path::PathBuf};
+
+use color_eyre::{config::HookBuilder, Report, Section};
+use smithay_client_toolkit::reexports::client::{
+    globals::{BindError, GlobalError},
+    ConnectError,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not find config file {0:?}, run `passata init` to create it")]
+    NotFound(PathBuf),
+    #[error("invalid config file {path:?}:\n{}", bullet_list(.errors))]
+    Invalid { path: PathBuf, errors: Vec<String> },
+    #[error("{feature} needs network access, set allow-network = true to enable it")]
+    NetworkDisabled { feature: String },
+}
+
+#[derive(Debug, Error)]
+pub enum WaylandError {
+    #[error("connecting to the wayland compositor")]
+    Connect(#[from] ConnectError),
+    #[error("listing the globals of the compositor")]
+    Globals(#[from] GlobalError),
+    #[error("binding the {protocol} protocol of the compositor")]
+    Bind {
+        protocol: &'static str,
+        source: BindError,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("the notification daemon refused the notification")]
+    Desktop(#[from] notify_rust::error::Error),
+    #[error("writing the notification")]
+    Io(#[from] io::Error),
+    /// The notifier cannot deliver this notification, but another one might
+    #[error("{0}")]
+    Unavailable(&'static str),
+}
+
+#[derive(Debug, Error)]
+pub enum IpcError {
+    #[error("connecting to the daemon at {path:?}, is passata running?")]
+    NotRunning { path: PathBuf, source: io::Error },
+    #[error("the daemon closed the connection without responding")]
+    NoResponse,
+    #[error("the daemon returned an error: {0}")]
+    Daemon(String),
+    #[error("unexpected response from the daemon")]
+    UnexpectedResponse,
+}
+
+fn bullet_list(errors: &[String]) -> String {
+    errors
+        .iter()
+        .map(|err| format!("  - {err}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Install the report handler, before any report is created so that all of them carry the hints
+pub fn install_handler() -> color_eyre::Result<()> {
+    HookBuilder::default().display_env_section(false).install()
+}
+
+impl NotifyError {
+    /// What the user can do about the failure
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::Desktop(_) => Some(
+                "is a notification daemon running? Start one, e.g. mako or dunst, or set \
+                notifiers = [\"terminal\"] in the config",
+            ),
+            Self::Io(_) | Self::Unavailable(_) => None,
+        }
+    }
+}
+
+impl WaylandError {
+    /// What the user can do about the failure
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::Connect(_) => Some(
+                "passata needs a wayland session: check that the compositor is running and \
+                WAYLAND_DISPLAY is set",
+            ),
+            Self::Bind {
+                protocol: "ext-idle-notify-v1",
+                ..
+            } => Some(
+                "the compositor does not support the idle notifications, set \
+                idle-backend = \"logind\" in the config",
+            ),
+            Self::Globals(_) | Self::Bind { .. } => None,
+        }
+    }
+}
+
+/// Attach to `report` the hint for the common failures, e.g. no notification daemon or no
+/// wayland compositor
+pub fn with_hint(report: Report) -> Report {
+    let hint = if let Some(err) = report.downcast_ref::<WaylandError>() {
+        err.hint()
+    } else if let Some(err) = report.downcast_ref::<NotifyError>() {
+        err.hint()
+    } else {
+        None
+    };
+    match hint {
+        Some(hint) => report.suggestion(hint),
+        None => report,
+    }
+}
+
+/// Exit status for the class of `report`, so that scripts can react to it
+pub fn exit_code(report: &Report) -> i32 {
+    if report.downcast_ref::<ConfigError>().is_some() {
+        2
+    } else if report.downcast_ref::<IpcError>().is_some() {
+        3
+    } else if report.downcast_ref::<WaylandError>().is_some() {
+        4
+    } else {
+        1
+    }
+}
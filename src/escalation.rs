@@ -0,0 +1,117 @@
+//! Escalation ladder run during a break: each step is taken after its offset from the start of
+//! the break, unless the user has stopped using the computer in the meantime.
+
+use std::{thread, time::Duration};
+
+use log::{debug, warn};
+use notify_rust::{Timeout, Urgency};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::timer::{TimeoutAction, Timer};
+
+use crate::{events::Event, lock, pipewire, timer::Phase, Passata};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EscalationAction {
+    /// Show the break notification again
+    Notification,
+    /// Play a tone
+    Sound,
+    /// Show a critical notification that stays until dismissed
+    #[serde(alias = "urgent-notification")]
+    Banner,
+    /// Cover the outputs with the fullscreen overlay
+    Overlay,
+    /// Dim the outputs through their gamma tables
+    Dim,
+    /// Lock the session
+    Lock,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EscalationStep {
+    pub action: EscalationAction,
+    /// Offset from the start of the break
+    #[serde(with = "humantime_serde", default)]
+    pub after: Duration,
+}
+
+/// Steps taken when the break notification is ignored, for each break type
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct EscalationConfig {
+    pub short_break: Vec<EscalationStep>,
+    pub long_break: Vec<EscalationStep>,
+}
+
+pub const TONE_DURATION: Duration = Duration::from_secs(1);
+
+impl Passata {
+    /// Arm the escalation steps configured for the break that just started
+    pub fn start_escalation(&mut self) {
+        self.stop_escalation();
+        let steps = match self.phase {
+            Phase::Work => return,
+            Phase::ShortBreak => &self.config.escalation.short_break,
+            Phase::LongBreak => &self.config.escalation.long_break,
+        };
+        for step in steps {
+            let action = step.action;
+            let res = self.loop_handle.insert_source(
+                Timer::from_duration(step.after),
+                move |_, _, state: &mut Passata| {
+                    state.escalate(action);
+                    TimeoutAction::Drop
+                },
+            );
+            match res {
+                Ok(token) => self.escalation_tokens.push(token),
+                Err(err) => warn!("arming the escalation step {action:?}: {}", err.error),
+            }
+        }
+    }
+
+    /// Cancel the pending escalation steps
+    pub fn stop_escalation(&mut self) {
+        for token in self.escalation_tokens.drain(..) {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    fn escalate(&mut self, action: EscalationAction) {
+        // the user is away from the computer, the break is being taken
+        if self.idle {
+            debug!("user idle, skipping the escalation step {action:?}");
+            self.stop_escalation();
+            return;
+        }
+        self.emit(Event::Escalation { action });
+    }
+
+    pub fn run_escalation_action(&mut self, action: EscalationAction) {
+        match action {
+            EscalationAction::Notification => {
+                self.notify_phase(self.break_notification());
+            }
+            EscalationAction::Banner => {
+                let mut banner = self.break_notification();
+                banner.urgency(Urgency::Critical).timeout(Timeout::Never);
+                self.notify_phase(banner);
+            }
+            EscalationAction::Sound => {
+                thread::spawn(|| {
+                    if let Err(err) = pipewire::play_tone(TONE_DURATION) {
+                        warn!("playing the escalation tone: {err:?}");
+                    }
+                });
+            }
+            EscalationAction::Overlay => self.show_overlay(),
+            EscalationAction::Dim => self.dim_outputs(),
+            EscalationAction::Lock => {
+                if !self.enforcement_exempt() {
+                    lock::lock_session();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use log::{debug, error, info};
+use notify_rust::{Notification, Urgency};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+
+use crate::{
+    escalation::EscalationAction, notify, prompt::BreakPrompt, stats::Outcome, timer::Phase,
+    Passata,
+};
+
+/// Everything that happens in the timer state machine goes through here, so that every
+/// consumer can react to it (or be disabled) in a single place
+#[derive(Debug)]
+pub enum Event {
+    WorkStarted,
+    /// The break is over, the work interval waits for the user to start it
+    WorkPending,
+    /// The work interval is over, right before its break starts
+    PomodoroCompleted {
+        worked: Duration,
+    },
+    /// The user has been idle for longer than idle-reset and the work interval starts over
+    PomodoroAbandoned {
+        worked: Duration,
+    },
+    ShortBreakStarted {
+        /// Current short break and total number of short breaks in the cycle
+        counter: Option<(u8, u8)>,
+    },
+    LongBreakStarted,
+    /// One of the named breaks of the sequence started
+    NamedBreakStarted {
+        name: String,
+    },
+    BreakDeferred {
+        reason: String,
+    },
+    /// The user postponed the break by `by`, for the `count`th time out of `limit`
+    BreakSnoozed {
+        by: Duration,
+        count: u32,
+        limit: u32,
+    },
+    /// The break is over, `taken` if the user went idle during it
+    BreakEnded {
+        taken: bool,
+        length: Duration,
+    },
+    /// The work interval is over, and its break is skipped for the focus mode
+    BreakSkipped,
+    /// Nobody used the computer today by day-off.after, the daemon stays dormant
+    DayOffStarted,
+    /// The daemon woke up from the day off
+    DayOffEnded,
+    /// The pomodoros completed today reached daily-goal
+    DailyGoalReached {
+        completed: u32,
+    },
+    /// The focus mode is over and the breaks are taken again
+    FocusEnded {
+        skipped: u32,
+    },
+    /// The screen stayed locked during the work interval for as long as its break, which
+    /// counts as taken
+    BreakTakenWhileLocked {
+        locked_for: Duration,
+    },
+    /// The user came back after being idle for `idle_for` during the work interval, `reset`
+    /// if it has been started over for it
+    IdleEnded {
+        idle_for: Duration,
+        reset: bool,
+    },
+    /// The user was idle during the work interval for as long as its break, which counts as
+    /// taken
+    BreakTakenWhileIdle {
+        idle_for: Duration,
+    },
+    /// The system stayed suspended during the work interval for as long as its break, which
+    /// counts as taken
+    BreakTakenWhileSuspended {
+        suspended_for: Duration,
+    },
+    Escalation {
+        action: EscalationAction,
+    },
+    RemainingChanged {
+        remaining: Duration,
+    },
+    Paused,
+    Resumed {
+        time_left: Duration,
+    },
+}
+
+/// Actions clicked in the notifications, sent back by the threads waiting for them
+pub enum NotificationAction {
+    StartWork,
+    /// End the break now
+    SkipBreak,
+    /// Answer to the survey after a break
+    Survey {
+        refreshed: bool,
+    },
+    /// The notification `id` has been acted on or closed, nothing waits for it anymore
+    Released {
+        id: u32,
+    },
+    /// The dialog closing the break, running as `pid`, exited
+    PromptExited {
+        pid: u32,
+    },
+    /// The command of the break, running as `pid`, exited
+    BreakCommandExited {
+        pid: u32,
+    },
+}
+
+impl NotificationAction {
+    /// Action of the notifications identified by `id`
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "start" => Some(NotificationAction::StartWork),
+            "skip" => Some(NotificationAction::SkipBreak),
+            "refreshed" => Some(NotificationAction::Survey { refreshed: true }),
+            "tired" => Some(NotificationAction::Survey { refreshed: false }),
+            _ => None,
+        }
+    }
+}
+
+pub fn listen_notification_actions(
+    loop_handle: &LoopHandle<'static, Passata>,
+) -> Result<Sender<NotificationAction>> {
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg(NotificationAction::StartWork) => {
+                if let Err(err) = state.start_work() {
+                    error!("starting the work interval: {err:?}");
+                }
+            }
+            channel::Event::Msg(NotificationAction::SkipBreak) => {
+                // the notification can outlive its break
+                if state.phase == Phase::Work {
+                    return;
+                }
+                if let Err(err) = state.next_phase() {
+                    error!("skipping the break: {err:?}");
+                }
+            }
+            channel::Event::Msg(NotificationAction::Survey { refreshed }) => {
+                state.answer_survey(refreshed);
+            }
+            channel::Event::Msg(NotificationAction::Released { id }) => {
+                state.action_waiters.remove(&id);
+            }
+            channel::Event::Msg(NotificationAction::PromptExited { pid }) => {
+                state.break_prompt_exited(pid);
+            }
+            channel::Event::Msg(NotificationAction::BreakCommandExited { pid }) => {
+                state.break_command_exited(pid);
+            }
+            channel::Event::Closed => {}
+        })
+        .map_err(|e| e.error)?;
+    Ok(sender)
+}
+
+impl Passata {
+    pub fn emit(&mut self, event: Event) {
+        self.journal_event(&event);
+        let events = &self.config.events;
+        // quiet breaks must not disturb, they will be escalated later
+        let urgency = if self.break_quiet {
+            Urgency::Low
+        } else if self.in_break_debt() {
+            Urgency::Critical
+        } else {
+            Urgency::Normal
+        };
+        // only the history is kept when benchmarking, without notifications or escalations
+        if self.bench
+            && !matches!(
+                event,
+                Event::PomodoroCompleted { .. }
+                    | Event::PomodoroAbandoned { .. }
+                    | Event::BreakEnded { .. }
+            )
+        {
+            return;
+        }
+        // nobody is there to start working on a day off
+        if self.day_off && matches!(event, Event::WorkStarted | Event::WorkPending) {
+            return;
+        }
+        match event {
+            Event::WorkStarted => {
+                debug!(event = "work-started"; "work again!");
+                // a pending work interval is announced by its own notification
+                if events.work_notification && !self.user_paused {
+                    let mut body = match self.phase_ends_at() {
+                        Some(at) => self.tr_args(
+                            "next-break-at",
+                            &[(
+                                "time",
+                                DateTime::<Local>::from(at)
+                                    .format("%H:%M")
+                                    .to_string()
+                                    .into(),
+                            )],
+                        ),
+                        None => self.tr("break-over"),
+                    };
+                    if let Some(task) = &self.task {
+                        body.push('\n');
+                        body.push_str(&self.tr_args("task", &[("task", task.as_str().into())]));
+                    }
+                    let mut notification = Notification::new();
+                    notification.summary(&self.tr("back-to-work")).body(&body);
+                    if let Some(sound) = &self.config.sound_work_start {
+                        notify::set_sound(&mut notification, sound);
+                    }
+                    self.notify_phase(notification);
+                }
+            }
+            Event::WorkPending => {
+                debug!(event = "work-pending"; "waiting for the user to start working");
+                // the notification stands in for a dialog that cannot be shown
+                if self.config.break_prompt == BreakPrompt::Dialog && self.show_break_prompt() {
+                    return;
+                }
+                let mut notification = Notification::new();
+                notification
+                    .summary(&self.tr("break-over"))
+                    .body(&self.tr("start-when-back"))
+                    .action("start", &self.tr("start"));
+                if let Some(sound) = &self.config.sound_break_end {
+                    notify::set_sound(&mut notification, sound);
+                }
+                self.notify_phase(notification);
+            }
+            Event::PomodoroCompleted { worked } => {
+                info!(
+                    event = "pomodoro-completed", worked_secs = worked.as_secs();
+                    "pomodoro completed after {worked:?} of work"
+                );
+                self.record_pomodoro(Outcome::Completed, worked);
+                self.pomodoro_completed_today();
+            }
+            Event::PomodoroAbandoned { worked } => {
+                info!(
+                    event = "pomodoro-abandoned", worked_secs = worked.as_secs();
+                    "pomodoro abandoned after {worked:?} of work"
+                );
+                self.record_pomodoro(Outcome::Abandoned, worked);
+            }
+            Event::ShortBreakStarted { counter } => {
+                debug!(event = "short-break-started"; "short break!");
+                let summary = match counter {
+                    Some((current, total)) if events.short_break_counter => self.tr_args(
+                        "short-break-counter",
+                        &[("current", current.into()), ("total", total.into())],
+                    ),
+                    _ => self.tr("short-break"),
+                };
+                let mut notification = self.break_notification();
+                notification.summary(&summary).urgency(urgency);
+                self.notify_phase(notification);
+            }
+            Event::LongBreakStarted => {
+                debug!(event = "long-break-started"; "long break!");
+                let mut notification = self.break_notification();
+                notification.urgency(urgency);
+                self.notify_phase(notification);
+            }
+            Event::NamedBreakStarted { name } => {
+                debug!(event = "named-break-started", name = name.as_str(); "{name} break!");
+                let mut notification = self.break_notification();
+                notification.urgency(urgency);
+                self.notify_phase(notification);
+            }
+            Event::BreakDeferred { reason } => {
+                info!(event = "break-deferred", reason = reason.as_str(); "deferring the break: {reason}");
+            }
+            Event::BreakSnoozed { by, count, limit } => {
+                info!(
+                    event = "break-snoozed", snoozed_secs = by.as_secs(), count = count;
+                    "break snoozed by {by:?}, {count}/{limit}"
+                );
+                let mut notification = Notification::new();
+                notification.summary(&self.tr_args(
+                    "break-snoozed",
+                    &[("count", count.into()), ("limit", limit.into())],
+                ));
+                if let Some(at) = self.phase_ends_at() {
+                    notification.body(
+                        &self.tr_args(
+                            "next-break-at",
+                            &[(
+                                "time",
+                                DateTime::<Local>::from(at)
+                                    .format("%H:%M")
+                                    .to_string()
+                                    .into(),
+                            )],
+                        ),
+                    );
+                }
+                self.notify_phase(notification);
+            }
+            Event::BreakEnded { taken, length } => {
+                info!(
+                    event = "break-ended", taken = taken;
+                    "the break has been {}", if taken { "taken" } else { "ignored" }
+                );
+                self.record_break(taken, length);
+                self.settle_break(taken);
+            }
+            Event::BreakSkipped => {
+                // chosen by the user, it is not a break in debt; neither is it in the history
+                debug!(event = "break-skipped"; "skipping the break, focus mode is on");
+            }
+            Event::DayOffStarted => {
+                info!(event = "day-off-started"; "no activity today, assuming a day off");
+            }
+            Event::DayOffEnded => {
+                info!(event = "day-off-ended"; "the day off is over");
+            }
+            Event::DailyGoalReached { completed } => {
+                info!(event = "daily-goal-reached", completed = completed; "daily goal reached");
+                let mut notification = Notification::new();
+                notification
+                    .summary(&self.tr("daily-goal-reached"))
+                    .body(&self.tr_args(
+                        "pomodoros-completed-today",
+                        &[("completed", completed.into())],
+                    ))
+                    .icon("emblem-default");
+                self.notify(notification);
+            }
+            Event::FocusEnded { skipped } => {
+                info!(event = "focus-ended", skipped = skipped; "focus mode is over");
+                let mut notification = Notification::new();
+                notification
+                    .summary(&self.tr("focus-over"))
+                    .body(&self.tr_args("focus-skipped", &[("skipped", skipped.into())]));
+                self.notify(notification);
+            }
+            Event::BreakTakenWhileLocked { locked_for } => {
+                info!(
+                    event = "break-taken-while-locked", locked_secs = locked_for.as_secs();
+                    "the screen was locked for {locked_for:?}, counting it as the break"
+                );
+            }
+            Event::IdleEnded { idle_for, reset } => {
+                info!(
+                    event = "idle-ended", idle_secs = idle_for.as_secs(), reset = reset;
+                    "back after {idle_for:?} of idle{}",
+                    if reset { ", past idle-reset" } else { "" }
+                );
+                self.record_idle(idle_for, reset);
+            }
+            Event::BreakTakenWhileIdle { idle_for } => {
+                info!(
+                    event = "break-taken-while-idle", idle_secs = idle_for.as_secs();
+                    "idle for {idle_for:?}, counting it as the break"
+                );
+            }
+            Event::BreakTakenWhileSuspended { suspended_for } => {
+                info!(
+                    event = "break-taken-while-suspended", suspended_secs = suspended_for.as_secs();
+                    "suspended for {suspended_for:?}, counting it as the break"
+                );
+            }
+            Event::Escalation { action } => {
+                debug!(event = "escalation", action:? = action; "escalating the break: {action:?}");
+                self.run_escalation_action(action);
+            }
+            Event::RemainingChanged { remaining } => {
+                debug!(
+                    event = "remaining-changed", remaining_secs = remaining.as_secs();
+                    "{remaining:?} left in the current phase"
+                );
+            }
+            Event::Paused => {
+                debug!(
+                    event = "paused", remaining_secs = self.remaining().as_secs(),
+                    percent = self.phase_percent();
+                    "timer paused with {:?} left, {}% done", self.remaining(), self.phase_percent()
+                );
+            }
+            Event::Resumed { time_left } => {
+                debug!(
+                    event = "resumed", remaining_secs = time_left.as_secs(),
+                    percent = self.phase_percent();
+                    "time left before break: {time_left:?}, {}% done", self.phase_percent()
+                );
+                if !events.resume_notification {
+                    return;
+                }
+                let time_left = time_left.as_secs();
+                let time_left = if time_left < 60 {
+                    time_left
+                } else {
+                    time_left - time_left % 60
+                };
+                let body = match &self.absence {
+                    Some(absence) => absence.describe(self.language()),
+                    None => self.tr("take-a-pause"),
+                };
+                let mut notification = Notification::new();
+                notification
+                    .summary(
+                        &self.tr_args(
+                            "until-next-break",
+                            &[(
+                                "time",
+                                humantime::format_duration(Duration::from_secs(time_left))
+                                    .to_string()
+                                    .into(),
+                            )],
+                        ),
+                    )
+                    .body(&body);
+                self.notify(notification);
+            }
+        }
+    }
+}
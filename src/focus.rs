@@ -0,0 +1,89 @@
+//! Focus mode: the breaks are skipped until it ends, for deep work or meetings, and a summary of
+//! the skipped breaks is sent when the normal cycle resumes.
+
+use std::time::{Duration, Instant};
+
+use color_eyre::Result;
+use log::info;
+use smithay_client_toolkit::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+
+use crate::{events::Event, timer::Phase, Passata};
+
+pub struct Focus {
+    until: Instant,
+    token: RegistrationToken,
+    /// Breaks skipped so far
+    skipped: u32,
+}
+
+impl Focus {
+    pub fn remaining(&self) -> Duration {
+        self.until.saturating_duration_since(Instant::now())
+    }
+}
+
+impl Passata {
+    /// Skip the breaks for `duration`, ending the current one if any; a zero duration ends the
+    /// focus mode
+    pub fn set_focus(&mut self, duration: Duration) -> Result<()> {
+        let skipped = self.focus.take().map(|focus| {
+            self.loop_handle.remove(focus.token);
+            focus.skipped
+        });
+        if duration.is_zero() {
+            if let Some(skipped) = skipped {
+                self.emit(Event::FocusEnded { skipped });
+            }
+        } else {
+            info!("focusing for {duration:?}");
+            self.focus = Some(Focus {
+                until: Instant::now() + duration,
+                token: self.arm_focus_end(duration)?,
+                skipped: skipped.unwrap_or_default(),
+            });
+            if self.phase != Phase::Work {
+                self.next_phase()?;
+            }
+        }
+        self.publish_status();
+        Ok(())
+    }
+
+    fn arm_focus_end(&mut self, duration: Duration) -> Result<RegistrationToken> {
+        let token = self
+            .loop_handle
+            .insert_source(
+                Timer::from_duration(duration),
+                |_, _, state: &mut Passata| {
+                    if let Some(focus) = state.focus.take() {
+                        state.emit(Event::FocusEnded {
+                            skipped: focus.skipped,
+                        });
+                        state.publish_status();
+                    }
+                    TimeoutAction::Drop
+                },
+            )
+            .map_err(|e| e.error)?;
+        Ok(token)
+    }
+
+    /// Whether the break due now has to be skipped, moving on to the next work interval
+    pub fn skip_break_for_focus(&mut self) -> Result<bool> {
+        let Some(focus) = &mut self.focus else {
+            return Ok(false);
+        };
+        focus.skipped += 1;
+        self.emit(Event::PomodoroCompleted {
+            worked: self.phase_length.saturating_sub(self.remaining()),
+        });
+        self.completed_pomodoros += 1;
+        self.emit(Event::BreakSkipped);
+        self.start_work_interval()?;
+        self.publish_status();
+        Ok(true)
+    }
+}
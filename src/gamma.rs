@@ -0,0 +1,294 @@
+//! Dimming and warming of the outputs during a break through wlr-gamma-control, a gentler
+//! alternative to the overlay. The gamma tables fade in when the break starts and fade out
+//! when the work starts again, then the compositor restores the original ones.
+
+use std::{
+    ffi::CStr,
+    fs::File,
+    io::{Seek, Write},
+    os::fd::AsFd,
+    time::Duration,
+};
+
+use color_eyre::{eyre::WrapErr, Result};
+use log::{debug, warn};
+use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::{
+    calloop::{
+        timer::{TimeoutAction, Timer},
+        RegistrationToken,
+    },
+    client::{
+        globals::GlobalList, protocol::wl_output::WlOutput, Connection, Dispatch, Proxy,
+        QueueHandle,
+    },
+    protocols_wlr::gamma_control::v1::client::{
+        zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1,
+        zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+    },
+};
+
+use crate::Passata;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct DimmingConfig {
+    /// Dim the outputs at the start of every break, besides the "dim" escalation step
+    pub enabled: bool,
+    /// Brightness during the break, from 0 (black) to 1 (untouched)
+    pub brightness: f32,
+    /// Color temperature during the break, in Kelvin; the colors are untouched when not set
+    pub temperature: Option<u32>,
+    /// How long fading in and out takes
+    #[serde(with = "humantime_serde")]
+    pub fade: Duration,
+}
+
+impl Default for DimmingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            brightness: 0.6,
+            temperature: None,
+            fade: Duration::from_secs(3),
+        }
+    }
+}
+
+impl DimmingConfig {
+    /// Multipliers of the red, green and blue ramps with the dimming applied at `level`, from 0
+    /// (untouched) to 1 (as configured)
+    fn factors(&self, level: f32) -> [f32; 3] {
+        let brightness = 1.0 - level * (1.0 - self.brightness);
+        let color = self.temperature.map(whitepoint).unwrap_or([1.0; 3]);
+        color.map(|channel| (1.0 - level * (1.0 - channel)) * brightness)
+    }
+}
+
+const FADE_STEP: Duration = Duration::from_millis(50);
+
+/// Color of a black body at `temperature` Kelvin as red, green and blue multipliers, following
+/// the approximation by Tanner Helland; 6500K is about neutral
+fn whitepoint(temperature: u32) -> [f32; 3] {
+    let t = temperature.clamp(1000, 6500) as f32 / 100.0;
+    let red = 255.0;
+    let green = 99.470_8 * t.ln() - 161.119_57;
+    let blue = if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    [red, green, blue].map(|channel| channel.clamp(0.0, 255.0) / 255.0)
+}
+
+/// Gamma table of `size` elements for each channel, scaled by `factors`
+fn gamma_table(size: u32, factors: [f32; 3]) -> Vec<u8> {
+    let last = size.saturating_sub(1).max(1) as f32;
+    factors
+        .iter()
+        .flat_map(|factor| {
+            (0..size).flat_map(move |i| {
+                ((i as f32 / last * factor * u16::MAX as f32) as u16).to_ne_bytes()
+            })
+        })
+        .collect()
+}
+
+struct OutputGamma {
+    output: WlOutput,
+    control: ZwlrGammaControlV1,
+    /// Length of the ramps, known after the first event
+    size: Option<u32>,
+}
+
+impl OutputGamma {
+    fn set(&self, factors: [f32; 3]) -> Result<()> {
+        let Some(size) = self.size else {
+            return Ok(());
+        };
+        let name = CStr::from_bytes_with_nul(b"passata-gamma\0").unwrap();
+        let mut file = File::from(memfd_create(name, MemFdCreateFlag::MFD_CLOEXEC)?);
+        file.write_all(&gamma_table(size, factors))?;
+        file.rewind()?;
+        self.control.set_gamma(file.as_fd());
+        Ok(())
+    }
+}
+
+impl Drop for OutputGamma {
+    fn drop(&mut self) {
+        // the compositor restores the original gamma tables
+        self.control.destroy();
+    }
+}
+
+pub struct Gamma {
+    manager: ZwlrGammaControlManagerV1,
+    outputs: Vec<OutputGamma>,
+    /// How dim the outputs are, from 0 (untouched) to 1 (as configured)
+    level: f32,
+    fade_token: Option<RegistrationToken>,
+}
+
+impl Gamma {
+    pub fn new(globals: &GlobalList, qh: &QueueHandle<Passata>) -> Result<Self> {
+        Ok(Self {
+            manager: globals
+                .bind(qh, 1..=1, ())
+                .wrap_err("wlr-gamma-control is not available")?,
+            outputs: Vec::new(),
+            level: 0.0,
+            fade_token: None,
+        })
+    }
+
+    fn add_output(&mut self, output: WlOutput, qh: &QueueHandle<Passata>) {
+        if self.outputs.iter().any(|gamma| gamma.output == output) {
+            return;
+        }
+        let control = self.manager.get_gamma_control(&output, qh, output.clone());
+        self.outputs.push(OutputGamma {
+            output,
+            control,
+            size: None,
+        });
+    }
+}
+
+impl Passata {
+    /// Fade in the dimming of every output
+    pub fn dim_outputs(&mut self) {
+        let (Some(gamma), Some(wayland)) = (&mut self.gamma, &self.wayland) else {
+            warn!("dimming is not available in this session");
+            return;
+        };
+        for output in wayland.output_state.outputs() {
+            gamma.add_output(output, &wayland.qh);
+        }
+        self.fade_gamma(1.0);
+    }
+
+    /// Fade out the dimming, restoring the outputs
+    pub fn restore_outputs(&mut self) {
+        if self.gamma.as_ref().is_some_and(|gamma| gamma.level > 0.0) {
+            self.fade_gamma(0.0);
+        }
+    }
+
+    fn fade_gamma(&mut self, target: f32) {
+        let Some(gamma) = &mut self.gamma else {
+            return;
+        };
+        if let Some(token) = gamma.fade_token.take() {
+            self.loop_handle.remove(token);
+        }
+        let step = FADE_STEP.as_secs_f32() / self.config.dimming.fade.as_secs_f32().max(1e-3);
+        let res =
+            self.loop_handle
+                .insert_source(Timer::immediate(), move |_, _, state: &mut Passata| {
+                    let Some(gamma) = &mut state.gamma else {
+                        return TimeoutAction::Drop;
+                    };
+                    gamma.level = if gamma.level < target {
+                        (gamma.level + step).min(target)
+                    } else {
+                        (gamma.level - step).max(target)
+                    };
+                    let done = gamma.level == target;
+                    if done {
+                        gamma.fade_token = None;
+                    }
+                    if done && target == 0.0 {
+                        gamma.outputs.clear();
+                    } else {
+                        state.apply_gamma();
+                    }
+                    if done {
+                        TimeoutAction::Drop
+                    } else {
+                        TimeoutAction::ToDuration(FADE_STEP)
+                    }
+                });
+        match res {
+            Ok(token) => {
+                if let Some(gamma) = &mut self.gamma {
+                    gamma.fade_token = Some(token);
+                }
+            }
+            Err(err) => warn!("fading the gamma: {}", err.error),
+        }
+    }
+
+    fn apply_gamma(&self) {
+        let Some(gamma) = &self.gamma else {
+            return;
+        };
+        let factors = self.config.dimming.factors(gamma.level);
+        for output in &gamma.outputs {
+            if let Err(err) = output.set(factors) {
+                warn!("setting the gamma table: {err:?}");
+            }
+        }
+    }
+
+    /// Dim the outputs connected during a dimmed break too
+    pub fn gamma_output_added(&mut self, output: WlOutput) {
+        if let (Some(gamma), Some(wayland)) = (&mut self.gamma, &self.wayland) {
+            if gamma.level > 0.0 {
+                gamma.add_output(output, &wayland.qh);
+            }
+        }
+    }
+
+    pub fn gamma_output_removed(&mut self, output: &WlOutput) {
+        if let Some(gamma) = &mut self.gamma {
+            gamma.outputs.retain(|gamma| &gamma.output != output);
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for Passata {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwlrGammaControlManagerV1,
+        _event: <ZwlrGammaControlManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, WlOutput> for Passata {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrGammaControlV1,
+        event: zwlr_gamma_control_v1::Event,
+        output: &WlOutput,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(gamma) = &mut state.gamma else {
+            return;
+        };
+        match event {
+            zwlr_gamma_control_v1::Event::GammaSize { size } => {
+                if let Some(output) = gamma
+                    .outputs
+                    .iter_mut()
+                    .find(|gamma| &gamma.output == output)
+                {
+                    output.size = Some(size);
+                }
+                state.apply_gamma();
+            }
+            zwlr_gamma_control_v1::Event::Failed => {
+                // another client, such as a night light, owns the gamma of the output
+                debug!("the gamma of an output cannot be changed");
+                gamma.outputs.retain(|gamma| &gamma.output != output);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Daily goal: how many pomodoros to complete each day, with the progress shown in the break
+//! notifications and in the status, and a notification once it is reached.
+
+use std::path::Path;
+
+use chrono::{Local, NaiveDate};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{events::Event, stats, Passata};
+
+/// Pomodoros completed on the current day
+pub struct DailyProgress {
+    day: NaiveDate,
+    completed: u32,
+}
+
+impl DailyProgress {
+    /// Count the pomodoros completed today from the history at `path`
+    pub fn load(path: &Path) -> Self {
+        let completed = stats::completed_today(path).unwrap_or_else(|err| {
+            warn!("counting the pomodoros completed today: {err:?}");
+            0
+        });
+        Self {
+            day: Local::now().date_naive(),
+            completed: completed as u32,
+        }
+    }
+
+    pub fn completed(&self) -> u32 {
+        if self.day == Local::now().date_naive() {
+            self.completed
+        } else {
+            0
+        }
+    }
+
+    /// Count one more pomodoro, starting over on a new day
+    fn add(&mut self) -> u32 {
+        self.completed = self.completed() + 1;
+        self.day = Local::now().date_naive();
+        self.completed
+    }
+}
+
+/// Progress towards the daily goal, as exposed in the status
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GoalProgress {
+    pub completed: u32,
+    pub goal: u32,
+    /// Share of the goal completed, capped at 100
+    pub percent: u32,
+}
+
+impl Passata {
+    pub fn goal_progress(&self) -> Option<GoalProgress> {
+        let goal = self.config.daily_goal?;
+        let completed = self.progress.completed();
+        Some(GoalProgress {
+            completed,
+            goal,
+            percent: (completed * 100 / goal).min(100),
+        })
+    }
+
+    /// Count a completed pomodoro towards the daily goal
+    pub fn pomodoro_completed_today(&mut self) {
+        let completed = self.progress.add();
+        if self.config.daily_goal == Some(completed) {
+            self.emit(Event::DailyGoalReached { completed });
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Tiny HTTP server exposing the status and a few controls, for phones, Stream Decks and home
+//! automation. Anyone who can reach the address controls the timer, unless listen-token is set;
+//! the requests sent by browsers, carrying an Origin header, are always refused so that a web
+//! page cannot drive the timer.
+//!
+//! - `GET /status` returns the status as JSON
+//! - `POST /skip` ends the current phase now
+//! - `POST /pause` pauses the timer, or resumes it if it is paused
+//!
+//! The controls answer with the updated status. The requests are read on a thread of their
+//! own, within a deadline, and only handled on the event loop once complete.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::{
+    eyre::{ensure, WrapErr},
+    Result,
+};
+use log::{debug, warn};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    generic::Generic,
+    Interest, LoopHandle, Mode, PostAction,
+};
+
+use crate::Passata;
+
+/// Time a client has to send the whole request
+const REQUEST_DEADLINE: Duration = Duration::from_secs(2);
+/// Size of the request line and the headers at most
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// Request line and headers of a request that has been read in full
+struct HttpRequest {
+    stream: TcpStream,
+    method: String,
+    path: String,
+    /// Sent by the browsers
+    origin: bool,
+    /// Token of the Authorization header, if any
+    token: Option<String>,
+}
+
+/// Listen for HTTP requests on `address`
+pub fn listen(address: SocketAddr, loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let listener =
+        TcpListener::bind(address).wrap_err_with(|| format!("binding the address {address}"))?;
+    listener.set_nonblocking(true)?;
+    let (sender, requests) = channel::channel();
+    loop_handle
+        .insert_source(requests, |event, _, state| {
+            if let channel::Event::Msg(request) = event {
+                if let Err(err) = state.handle_http(request) {
+                    warn!("handling an http connection: {err:?}");
+                }
+            }
+        })
+        .map_err(|e| e.error)?;
+    loop_handle
+        .insert_source(
+            Generic::new(listener, Interest::READ, Mode::Level),
+            move |_, listener, _| {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, from)) => {
+                            let sender = sender.clone();
+                            thread::spawn(move || read_request(stream, from, sender));
+                        }
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Read the request line and the headers from `stream`, and send them to the event loop
+fn read_request(stream: TcpStream, from: SocketAddr, sender: Sender<HttpRequest>) {
+    let head = match read_head(&stream) {
+        Ok(head) => head,
+        Err(err) => {
+            debug!("reading an http request from {from}: {err:?}");
+            return;
+        }
+    };
+    let mut lines = head.split("\r\n");
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let (method, path) = (
+        parts.next().unwrap_or_default().to_owned(),
+        parts.next().unwrap_or_default().to_owned(),
+    );
+    let mut origin = false;
+    let mut token = None;
+    for header in lines {
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        if name.eq_ignore_ascii_case("origin") {
+            origin = true;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            token = value
+                .trim()
+                .strip_prefix("Bearer ")
+                .map(|token| token.trim().to_owned());
+        }
+    }
+    // the daemon is gone if this fails
+    let _ = sender.send(HttpRequest {
+        stream,
+        method,
+        path,
+        origin,
+        token,
+    });
+}
+
+/// Read until the empty line ending the headers, within the deadline and the size limit
+fn read_head(mut stream: &TcpStream) -> Result<String> {
+    stream.set_nonblocking(false)?;
+    let deadline = Instant::now() + REQUEST_DEADLINE;
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.ends_with(b"\r\n\r\n") {
+        let left = deadline.saturating_duration_since(Instant::now());
+        ensure!(!left.is_zero(), "the request took too long");
+        stream.set_read_timeout(Some(left))?;
+        let len = stream.read(&mut buf)?;
+        ensure!(len > 0, "the connection was closed");
+        head.extend_from_slice(&buf[..len]);
+        ensure!(head.len() <= MAX_REQUEST, "the request is too large");
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+impl Passata {
+    fn handle_http(&mut self, request: HttpRequest) -> Result<()> {
+        let HttpRequest {
+            stream,
+            method,
+            path,
+            origin,
+            token,
+        } = request;
+        debug!("http: {method} {path}");
+        let authorized = self
+            .config
+            .listen_token
+            .as_ref()
+            .is_none_or(|expected| token.as_ref() == Some(expected));
+        let (status, body) = match (method.as_str(), path.as_str()) {
+            _ if origin => ("403 Forbidden", error_body("browsers are not allowed")),
+            _ if !authorized => ("401 Unauthorized", error_body("missing or wrong token")),
+            ("GET", "/status") => ("200 OK", serde_json::to_string(&self.status())?),
+            ("POST", "/skip") => match self.skip() {
+                Ok(()) => ("200 OK", serde_json::to_string(&self.status())?),
+                Err(err) => ("500 Internal Server Error", error_body(&format!("{err:?}"))),
+            },
+            ("POST", "/pause") => {
+                self.toggle_pause();
+                ("200 OK", serde_json::to_string(&self.status())?)
+            }
+            (_, "/status" | "/skip" | "/pause") => {
+                ("405 Method Not Allowed", error_body("method not allowed"))
+            }
+            _ => ("404 Not Found", error_body("not found")),
+        };
+        // the answer is small enough for the buffer of a fresh socket, never wait on the client
+        stream.set_nonblocking(true)?;
+        write!(
+            &stream,
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        Ok(())
+    }
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
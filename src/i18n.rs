@@ -0,0 +1,139 @@
+//! Translations of the messages shown to the user, as Fluent catalogs in `locales/`. The
+//! language is the configured one, or the one of the locale (LANGUAGE, LC_ALL, LC_MESSAGES,
+//! LANG); the messages missing from a catalog are shown in English.
+
+use std::{env, sync::OnceLock};
+
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource, FluentValue};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use unic_langid::LanguageIdentifier;
+
+use crate::Passata;
+
+type Bundle = FluentBundle<FluentResource>;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Language {
+    En,
+    It,
+    De,
+    Fr,
+    Es,
+}
+
+impl Language {
+    /// Language of the locale, English when it is not translated
+    pub fn detect() -> Self {
+        // LANGUAGE is a list of fallbacks, the others a single locale
+        for var in ["LANGUAGE", "LC_ALL", "LC_MESSAGES", "LANG"] {
+            let Ok(value) = env::var(var) else {
+                continue;
+            };
+            if value.is_empty() {
+                continue;
+            }
+            if let Some(language) = value.split(':').find_map(Self::from_locale) {
+                return language;
+            }
+            // the first locale set wins, even when it is not translated
+            if var != "LANGUAGE" {
+                break;
+            }
+        }
+        Self::En
+    }
+
+    /// Language of `locale`, e.g. `it_IT.UTF-8`
+    fn from_locale(locale: &str) -> Option<Self> {
+        match locale.split(['_', '.', '@']).next()? {
+            "en" | "C" | "POSIX" => Some(Self::En),
+            "it" => Some(Self::It),
+            "de" => Some(Self::De),
+            "fr" => Some(Self::Fr),
+            "es" => Some(Self::Es),
+            _ => None,
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::It => "it",
+            Self::De => "de",
+            Self::Fr => "fr",
+            Self::Es => "es",
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Self::En => include_str!("../locales/en/passata.ftl"),
+            Self::It => include_str!("../locales/it/passata.ftl"),
+            Self::De => include_str!("../locales/de/passata.ftl"),
+            Self::Fr => include_str!("../locales/fr/passata.ftl"),
+            Self::Es => include_str!("../locales/es/passata.ftl"),
+        }
+    }
+
+    /// Catalog of this language, parsed the first time it is needed
+    fn bundle(self) -> &'static Bundle {
+        static BUNDLES: [OnceLock<Bundle>; 5] = [const { OnceLock::new() }; 5];
+        BUNDLES[self as usize].get_or_init(|| {
+            let language: LanguageIdentifier = self.tag().parse().unwrap();
+            let mut bundle = Bundle::new_concurrent(vec![language]);
+            // the isolation marks around the arguments show up as boxes in some notifications
+            bundle.set_use_isolating(false);
+            // the messages that parsed are kept, the others fall back to English
+            let resource = FluentResource::try_new(self.source().to_owned()).unwrap_or_else(
+                |(resource, errors)| {
+                    warn!("parsing the {} catalog: {errors:?}", self.tag());
+                    resource
+                },
+            );
+            if let Err(errors) = bundle.add_resource(resource) {
+                warn!("loading the {} catalog: {errors:?}", self.tag());
+            }
+            bundle
+        })
+    }
+
+    /// Message `id` in this language, with the arguments `args`
+    pub fn format(self, id: &str, args: &[(&str, FluentValue)]) -> String {
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+        for language in [self, Self::En] {
+            let bundle = language.bundle();
+            let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else {
+                continue;
+            };
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+            if !errors.is_empty() {
+                warn!("formatting {id} in {}: {errors:?}", language.tag());
+            }
+            return text.into_owned();
+        }
+        warn!("no message {id} in the catalogs");
+        id.to_owned()
+    }
+}
+
+impl Passata {
+    pub fn language(&self) -> Language {
+        self.config.language.unwrap_or_else(Language::detect)
+    }
+
+    /// Message `id` in the language of the user
+    pub fn tr(&self, id: &str) -> String {
+        self.language().format(id, &[])
+    }
+
+    /// Message `id` in the language of the user, with the arguments `args`
+    pub fn tr_args(&self, id: &str, args: &[(&str, FluentValue)]) -> String {
+        self.language().format(id, args)
+    }
+}
@@ -0,0 +1,420 @@
+//! Pause the work interval while the user is idle, and start it over after a longer absence.
+//! The idle state comes from the ext-idle-notify Wayland protocol, or from the IdleHint of the
+//! logind session where the protocol is not available, or from the X server on X11. Every
+//! return from idle is kept in the history, for `passata stats idle` to show how fragmented
+//! each work interval was.
+
+use std::{
+    io::Write,
+    mem,
+    path::Path,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Local, NaiveDate};
+
+use color_eyre::{
+    eyre::{ContextCompat, WrapErr},
+    Result,
+};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::{
+    calloop::{
+        channel::{self, Sender},
+        timer::{TimeoutAction, Timer},
+        LoopHandle,
+    },
+    client::{globals::GlobalList, protocol::wl_seat::WlSeat, QueueHandle},
+    protocols::ext::idle_notify::v1::client::ext_idle_notifier_v1::ExtIdleNotifierV1,
+};
+use zbus::{blocking::Connection, proxy};
+
+use crate::{
+    error::WaylandError,
+    events::Event,
+    i18n::Language,
+    intensity::ACTIVITY_TIMEOUT,
+    stats::{self, Outcome},
+    timer::Phase,
+    typing::MICRO_PAUSE,
+    Passata,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdleBackend {
+    /// ext-idle-notify, with the idle-pause and idle-reset timeouts
+    Wayland,
+    /// IdleHint of the logind session, set by the desktop after its own timeout
+    Logind,
+    /// Idle time of the MIT-SCREEN-SAVER extension, with the same timeouts as wayland
+    #[cfg(feature = "x11")]
+    X11,
+}
+
+/// Which of the idle timeouts an event is about
+#[derive(Clone, Copy)]
+pub enum IdleLevel {
+    Pause,
+    Reset,
+    /// Short timeout measuring the input intensity
+    Activity,
+    /// Micro-pause in the typing, for grace-typing
+    Typing,
+}
+
+/// A return from idle during the work interval, told by the notification of the timer resuming
+pub struct Absence {
+    pub idle_for: Duration,
+    /// It lasted as long as the break, which counts as taken
+    pub break_taken: bool,
+    /// It lasted past idle-reset, the work interval started over
+    pub reset: bool,
+}
+
+impl Absence {
+    /// What happened, for the notification
+    pub fn describe(&self, language: Language) -> String {
+        // to the minute, like the time left
+        let secs = self.idle_for.as_secs();
+        let away = humantime::format_duration(Duration::from_secs(if secs < 60 {
+            secs
+        } else {
+            secs - secs % 60
+        }));
+        let message = if self.break_taken {
+            "away-counted-as-break"
+        } else if self.reset {
+            "away-started-over"
+        } else {
+            "away"
+        };
+        language.format(message, &[("away", away.to_string().into())])
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto",
+    gen_async = false
+)]
+pub trait Session {
+    #[zbus(property)]
+    fn idle_hint(&self) -> zbus::Result<bool>;
+
+    /// Microseconds since the epoch
+    #[zbus(property)]
+    fn idle_since_hint(&self) -> zbus::Result<u64>;
+
+    #[zbus(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+}
+
+/// Start following the IdleHint of the logind session
+pub fn watch_logind(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let connection = Connection::system()?;
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg((idle, idle_for)) => state.idle_hint_changed(idle, idle_for),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = follow_idle_hint(&connection, sender) {
+            warn!("stopped following the logind idle hint: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+fn follow_idle_hint(connection: &Connection, sender: Sender<(bool, Duration)>) -> Result<()> {
+    let session = SessionProxy::new(connection)?;
+    let idle_for = || -> Result<Duration> {
+        let since = UNIX_EPOCH + Duration::from_micros(session.idle_since_hint()?);
+        Ok(SystemTime::now().duration_since(since).unwrap_or_default())
+    };
+    sender.send((session.idle_hint()?, idle_for()?))?;
+    for change in session.receive_idle_hint_changed() {
+        sender.send((change.get()?, idle_for()?))?;
+    }
+    Ok(())
+}
+
+/// Write the idle periods of each work interval from `from` to `to`, both included, from the
+/// history at `path`. Only the records not compacted yet have them
+pub fn report(path: &Path, from: NaiveDate, to: NaiveDate, out: &mut impl Write) -> Result<()> {
+    let round = |duration: Duration| {
+        humantime::format_duration(Duration::from_secs(duration.as_secs())).to_string()
+    };
+    let mut idle_periods = Vec::new();
+    let mut intervals = 0;
+    let mut total_periods = 0;
+    for record in stats::read(path)? {
+        match record.outcome {
+            // the history is in order, the idle periods come before the end of their interval
+            Outcome::Idle => idle_periods.push(record.worked),
+            Outcome::Completed | Outcome::Abandoned => {
+                let periods = mem::take(&mut idle_periods);
+                let ended = DateTime::<Local>::from(record.ended);
+                if !(from..=to).contains(&ended.date_naive()) {
+                    continue;
+                }
+                if intervals == 0 {
+                    writeln!(
+                        out,
+                        "Work interval  Worked     Idle periods  Idle time  Longest    Avg stretch"
+                    )?;
+                }
+                intervals += 1;
+                total_periods += periods.len();
+                let idle: Duration = periods.iter().sum();
+                let started = DateTime::<Local>::from(record.ended - record.worked - idle);
+                let stretch = record.worked / (periods.len() as u32 + 1);
+                writeln!(
+                    out,
+                    "{:<13}  {:<9}  {:>12}  {:<9}  {:<9}  {}",
+                    format!("{}-{}", started.format("%H:%M"), ended.format("%H:%M")),
+                    round(record.worked),
+                    periods.len(),
+                    round(idle),
+                    round(periods.iter().max().copied().unwrap_or_default()),
+                    round(stretch),
+                )?;
+            }
+            _ => {}
+        }
+    }
+    if intervals == 0 {
+        writeln!(out, "No work intervals from {from} to {to}")?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "{total_periods} idle periods in {intervals} work intervals, {:.1} on average",
+        total_periods as f32 / intervals as f32
+    )?;
+    Ok(())
+}
+
+impl Passata {
+    /// The idle timeouts to be notified of, from the config
+    pub fn idle_timeouts(&self) -> Vec<(Duration, IdleLevel)> {
+        [
+            (self.config.idle_pause, IdleLevel::Pause),
+            (self.config.idle_reset, IdleLevel::Reset),
+            (
+                (self.config.input_intensity || self.config.end_break_on_activity)
+                    .then_some(ACTIVITY_TIMEOUT),
+                IdleLevel::Activity,
+            ),
+            (
+                self.config.grace_typing.map(|_| MICRO_PAUSE),
+                IdleLevel::Typing,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(timeout, level)| Some((timeout?, level)))
+        .collect()
+    }
+
+    /// Ask the compositor for the idle notifications of the idle timeouts
+    pub fn bind_idle_notifications(
+        &mut self,
+        globals: &GlobalList,
+        qh: &QueueHandle<Passata>,
+    ) -> Result<()> {
+        let idle_notifier = globals
+            .bind::<ExtIdleNotifierV1, Passata, ()>(qh, 1..=1, ())
+            .map_err(|source| WaylandError::Bind {
+                protocol: "ext-idle-notify-v1",
+                source,
+            })?;
+        let seat = self.idle_seat()?;
+        for (timeout, level) in self.idle_timeouts() {
+            let timeout = timeout
+                .as_millis()
+                .try_into()
+                .wrap_err_with(|| format!("the idle timeout {timeout:?} is too long"))?;
+            idle_notifier.get_idle_notification(timeout, &seat, qh, level);
+        }
+        if let Some(wayland) = &mut self.wayland {
+            wayland.idle_notifications = true;
+        }
+        Ok(())
+    }
+
+    /// The seat whose idle state is followed, the configured one or the first one
+    pub fn idle_seat(&self) -> Result<WlSeat> {
+        let wayland = self.wayland.as_ref().context("not a wayland session")?;
+        let mut seats = wayland.seat_state.seats();
+        match &self.config.seat {
+            Some(name) => seats
+                .find(|seat| {
+                    wayland
+                        .seat_state
+                        .info(seat)
+                        .and_then(|info| info.name)
+                        .as_ref()
+                        == Some(name)
+                })
+                .with_context(|| format!("seat {name:?} not found")),
+            None => seats.next().context("no seats found"),
+        }
+    }
+
+    pub fn idle_changed(&mut self, level: &IdleLevel, idled: bool) {
+        if let IdleLevel::Activity = level {
+            self.activity_changed(idled);
+            self.input_changed(idled);
+            return;
+        }
+        if let IdleLevel::Typing = level {
+            self.typing_changed(idled);
+            return;
+        }
+        if let IdleLevel::Reset = level {
+            if idled {
+                debug!("idled for too long!");
+                if let Err(err) = self.reset_work() {
+                    error!("resetting the work interval: {err:?}");
+                }
+            }
+            return;
+        }
+        self.idle = idled;
+        // when going idle, the user was last active idle-pause ago
+        self.last_active =
+            SystemTime::now() - self.config.idle_pause.filter(|_| idled).unwrap_or_default();
+        if self.day_off {
+            self.day_off_activity(idled);
+            return;
+        }
+        // don't process the idle events when a break is currently going on, besides noting
+        // that it is being taken
+        if self.phase != Phase::Work {
+            self.break_idled |= idled;
+            return;
+        }
+        if idled {
+            debug!("idled!");
+            // the user was last active idle-pause ago
+            let now = Instant::now();
+            self.idle_since = Some(
+                now.checked_sub(self.config.idle_pause.unwrap_or_default())
+                    .unwrap_or(now),
+            );
+            self.pause();
+            return;
+        }
+        if let Some(idle_since) = self.idle_since.take() {
+            let idle_for = idle_since.elapsed();
+            let reset = self
+                .config
+                .idle_reset
+                .is_some_and(|idle_reset| idle_for >= idle_reset);
+            self.emit(Event::IdleEnded { idle_for, reset });
+            let break_taken = self.credit_idle_break(idle_for);
+            self.absence = Some(Absence {
+                idle_for,
+                break_taken,
+                reset,
+            });
+        }
+        if let Some(hold) = self.resume_hold() {
+            debug!("resumed, but {hold}");
+        } else {
+            debug!("resumed!");
+            if let Err(err) = self.resume() {
+                error!("resuming the timer: {err:?}");
+            }
+        }
+        // only the resume right after it tells about it
+        self.absence = None;
+    }
+
+    /// End the break when the input resumes after the user stepped away from it, with
+    /// end-break-on-activity
+    fn input_changed(&mut self, idled: bool) {
+        self.input_idle = idled;
+        if self.phase == Phase::Work || !self.config.end_break_on_activity {
+            return;
+        }
+        if idled {
+            // the input went quiet ACTIVITY_TIMEOUT ago
+            let now = Instant::now();
+            self.break_away_since = Some(now.checked_sub(ACTIVITY_TIMEOUT).unwrap_or(now));
+            return;
+        }
+        let Some(away_since) = self.break_away_since.take() else {
+            return;
+        };
+        let away_for = away_since.elapsed();
+        if away_for < self.config.end_break_absence {
+            debug!("back after {away_for:?}, too soon to end the break");
+            return;
+        }
+        info!("back from the break early, ending it");
+        // stepping away is taking the break, for as long as it lasted
+        self.break_idled = true;
+        if let Err(err) = self.next_phase() {
+            error!("ending the break: {err:?}");
+            return;
+        }
+        // the user is back already, nobody needs to start the work
+        if let Err(err) = self.start_work() {
+            error!("starting the work interval: {err:?}");
+        }
+    }
+
+    /// Count an absence of `idle_for` as the break after the work interval, when it lasted as
+    /// long; an absence as long as the long break starts the whole cycle over
+    fn credit_idle_break(&mut self, idle_for: Duration) -> bool {
+        if !self.config.idle_break {
+            return false;
+        }
+        let next_break = self.phase_duration(self.next_event, self.next_break_type.as_deref());
+        if idle_for < next_break {
+            return false;
+        }
+        self.emit(Event::BreakTakenWhileIdle { idle_for });
+        if self
+            .long_break()
+            .is_some_and(|long_break| idle_for >= long_break)
+        {
+            self.current_short_breaks = 0;
+            self.sequence_position = 0;
+        }
+        self.credit_break();
+        true
+    }
+
+    /// The logind session went idle `idle_for` ago, or is not idle anymore
+    fn idle_hint_changed(&mut self, idle: bool, idle_for: Duration) {
+        if let Some(token) = self.idle_reset_token.take() {
+            self.loop_handle.remove(token);
+        }
+        if self.config.idle_pause.is_some() {
+            self.idle_changed(&IdleLevel::Pause, idle);
+        }
+        let Some(idle_reset) = self.config.idle_reset.filter(|_| idle) else {
+            return;
+        };
+        // logind has no timeouts, the reset is timed from when the session went idle
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(idle_reset.saturating_sub(idle_for)),
+            |_, _, state: &mut Passata| {
+                state.idle_reset_token = None;
+                state.idle_changed(&IdleLevel::Reset, true);
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(token) => self.idle_reset_token = Some(token),
+            Err(err) => warn!("arming the idle reset: {}", err.error),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Follow the idle inhibitors held through logind (video players, presentations), so that the
+//! time spent under one is treated as media time: it does not count as work and does not lead
+//! to a break. The inhibitors held through the desktop session (GNOME, KDE) defer the breaks
+//! instead.
+
+use std::{thread, time::Duration};
+
+use color_eyre::Result;
+use log::{debug, error, info, warn};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+use zbus::{blocking::Connection, proxy};
+
+use crate::{timer::Phase, Passata};
+
+/// logind does not signal the changes of the inhibitors, they have to be polled
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1",
+    gen_async = false
+)]
+trait LoginManager {
+    /// Colon separated list of the operations currently inhibited
+    #[zbus(property)]
+    fn block_inhibited(&self) -> zbus::Result<String>;
+}
+
+/// Inhibit flag of org.gnome.SessionManager for the session being marked as idle
+const GNOME_INHIBIT_IDLE: u32 = 8;
+
+#[proxy(
+    interface = "org.gnome.SessionManager",
+    default_service = "org.gnome.SessionManager",
+    default_path = "/org/gnome/SessionManager",
+    gen_async = false
+)]
+trait GnomeSessionManager {
+    fn is_inhibited(&self, flags: u32) -> zbus::Result<bool>;
+}
+
+/// Inhibitors of KDE, which also serve the org.freedesktop.ScreenSaver ones since that
+/// interface cannot be queried
+#[proxy(
+    interface = "org.freedesktop.PowerManagement.Inhibit",
+    default_service = "org.freedesktop.PowerManagement",
+    default_path = "/org/freedesktop/PowerManagement/Inhibit",
+    gen_async = false
+)]
+trait PowerManagementInhibit {
+    fn has_inhibit(&self) -> zbus::Result<bool>;
+}
+
+/// Start following whether an idle inhibitor is active
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let connection = Connection::system()?;
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg(inhibited) => state.inhibition_changed(inhibited),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = poll_inhibitors(&connection, sender) {
+            warn!("stopped following the idle inhibitors: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+fn poll_inhibitors(connection: &Connection, sender: Sender<bool>) -> Result<()> {
+    let manager = LoginManagerProxy::new(connection)?;
+    let mut last = None;
+    loop {
+        let inhibited = manager
+            .block_inhibited()?
+            .split(':')
+            .any(|what| what == "idle");
+        if last != Some(inhibited) {
+            last = Some(inhibited);
+            sender.send(inhibited)?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Start following whether an application inhibits the idle of the desktop session
+pub fn watch_session(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let connection = Connection::session()?;
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg(inhibited) => state.session_inhibition_changed(inhibited),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = poll_session_inhibitors(&connection, sender) {
+            warn!("stopped following the session inhibitors: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+fn poll_session_inhibitors(connection: &Connection, sender: Sender<bool>) -> Result<()> {
+    let gnome = GnomeSessionManagerProxy::new(connection)?;
+    let power_management = PowerManagementInhibitProxy::new(connection)?;
+    let mut last = None;
+    loop {
+        // only one of them is running, depending on the desktop
+        let inhibited = gnome.is_inhibited(GNOME_INHIBIT_IDLE).unwrap_or(false)
+            || power_management.has_inhibit().unwrap_or(false);
+        if last != Some(inhibited) {
+            last = Some(inhibited);
+            sender.send(inhibited)?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+impl Passata {
+    fn session_inhibition_changed(&mut self, inhibited: bool) {
+        debug!("session idle inhibited: {inhibited}");
+        self.session_inhibited = inhibited;
+        if let Err(err) = self.check_deferred_break() {
+            error!("starting the deferred break: {err:?}");
+        }
+    }
+
+    fn inhibition_changed(&mut self, inhibited: bool) {
+        debug!("idle inhibited: {inhibited}");
+        self.inhibited = inhibited;
+        // breaks are not interrupted, only the work time is held
+        if self.phase != Phase::Work {
+            return;
+        }
+        if inhibited {
+            info!("an idle inhibitor is active, pausing the work time");
+            self.pause();
+        } else if self.can_resume() {
+            if let Err(err) = self.resume() {
+                error!("resuming the timer: {err:?}");
+            }
+        }
+    }
+}
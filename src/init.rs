@@ -0,0 +1,197 @@
+//! Write the default configuration for new users, as it is or after asking a few questions.
+
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    process::Command,
+    time::Duration,
+};
+
+use color_eyre::{
+    eyre::{bail, ensure, WrapErr},
+    Result,
+};
+use toml_edit::{Array, DocumentMut, Value};
+use xdg::BaseDirectories;
+
+use crate::config::Config;
+
+const DEFAULT_CONFIG: &str = include_str!("../passata.toml");
+const SYSTEMD_UNIT: &str = include_str!("../passata.service");
+
+/// Write the commented default config to `config_file` and, if asked, a systemd user unit
+pub fn init(config_file: &Path, systemd: bool, force: bool) -> Result<()> {
+    write_new(config_file, DEFAULT_CONFIG, force)?;
+    println!("wrote the default config to {config_file:?}");
+
+    if systemd {
+        let exe = env::current_exe().wrap_err("finding the passata executable")?;
+        let unit = BaseDirectories::new()?.place_config_file("systemd/user/passata.service")?;
+        write_new(
+            &unit,
+            &SYSTEMD_UNIT.replace("{exe}", &exe.to_string_lossy()),
+            force,
+        )?;
+        println!("wrote the systemd unit to {unit:?}, enable it with:");
+        println!("  systemctl --user enable --now passata.service");
+    }
+    Ok(())
+}
+
+/// Ask the main settings, write the config with them to `config_file` and, if asked, install
+/// the systemd user unit and start the daemon
+pub fn setup(config_file: &Path) -> Result<()> {
+    println!("Setting up passata, press enter to keep the default in brackets.");
+    if config_file.exists() && !ask_yes_no(&format!("{config_file:?} exists, replace it?"), false)?
+    {
+        return Ok(());
+    }
+    let mut document = DEFAULT_CONFIG
+        .parse::<DocumentMut>()
+        .wrap_err("parsing the default config")?;
+
+    let interval = ask_duration("Length of the work intervals", "25m")?;
+    set(&mut document, "interval", interval);
+    let short_break = ask_duration("Length of the short breaks", "5m")?;
+    set(&mut document, "short-break", short_break);
+    if ask_yes_no("Take a long break every few pomodoros?", true)? {
+        let long_break = ask_duration("Length of the long breaks", "15m")?;
+        set(&mut document, "long-break", long_break);
+        let count = loop {
+            match ask("Short breaks before each long break", "3")?.parse::<u8>() {
+                Ok(count) if count > 0 => break count,
+                _ => println!("write a number between 1 and 255"),
+            }
+        };
+        set(
+            &mut document,
+            "short-breaks-before-long-break",
+            i64::from(count),
+        );
+    }
+    if ask_yes_no(
+        "Pause the timer while you are away from the computer?",
+        true,
+    )? {
+        let idle_pause = ask_duration("Pause after being idle for", "2m")?;
+        set(&mut document, "idle-pause", idle_pause);
+    } else {
+        document.remove("idle-pause");
+    }
+    let notifiers: &[&str] = loop {
+        match ask(
+            "Notify with desktop notifications, a fullscreen overlay on the breaks, or in the \
+             terminal (desktop/overlay/terminal)",
+            "desktop",
+        )?
+        .as_str()
+        {
+            "desktop" => break &["desktop", "terminal"],
+            "overlay" => break &["overlay", "desktop", "terminal"],
+            "terminal" => break &["terminal"],
+            _ => println!("write desktop, overlay or terminal"),
+        }
+    };
+    set(
+        &mut document,
+        "notifiers",
+        Array::from_iter(notifiers.iter().copied()),
+    );
+
+    let tmp = config_file.with_extension("toml.tmp");
+    write_new(&tmp, &document.to_string(), true)?;
+    if let Err(err) = Config::load(&tmp) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err.into());
+    }
+    fs::rename(&tmp, config_file).wrap_err_with(|| format!("replacing {config_file:?}"))?;
+    println!("wrote the config to {config_file:?}");
+
+    let exe = env::current_exe().wrap_err("finding the passata executable")?;
+    if ask_yes_no("Start passata with the graphical session (systemd)?", true)? {
+        let unit = BaseDirectories::new()?.place_config_file("systemd/user/passata.service")?;
+        write_new(
+            &unit,
+            &SYSTEMD_UNIT.replace("{exe}", &exe.to_string_lossy()),
+            true,
+        )?;
+        println!("wrote the systemd unit to {unit:?}");
+        if ask_yes_no("Start it now?", true)? {
+            run(Command::new("systemctl").args(["--user", "enable", "--now", "passata.service"]))?;
+        }
+    } else if ask_yes_no("Start it now?", true)? {
+        run(Command::new(exe)
+            .arg("--config")
+            .arg(config_file)
+            .arg("--daemon"))?;
+    }
+    Ok(())
+}
+
+/// Print `question` and read the answer, `default` when it is empty
+fn ask(question: &str, default: &str) -> Result<String> {
+    print!("{question} [{default}]: ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        bail!("the setup has been interrupted");
+    }
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_owned())
+}
+
+fn ask_yes_no(question: &str, default: bool) -> Result<bool> {
+    loop {
+        match ask(question, if default { "y" } else { "n" })?
+            .to_lowercase()
+            .as_str()
+        {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("answer y or n"),
+        }
+    }
+}
+
+/// Ask a duration, returned in the humantime format of the config
+fn ask_duration(question: &str, default: &str) -> Result<String> {
+    loop {
+        let answer = ask(question, default)?;
+        match humantime::parse_duration(&answer) {
+            Ok(duration) if duration > Duration::ZERO => {
+                return Ok(humantime::format_duration(duration).to_string())
+            }
+            Ok(_) => println!("the duration must be greater than zero"),
+            Err(err) => println!("{err}, write e.g. 25m or 1h 30m"),
+        }
+    }
+}
+
+/// Set `key` of the root table, keeping its comments when it is already there
+fn set(document: &mut DocumentMut, key: &str, value: impl Into<Value>) {
+    let mut value = value.into();
+    if let Some(old) = document.get(key).and_then(|item| item.as_value()) {
+        *value.decor_mut() = old.decor().clone();
+    }
+    document[key] = toml_edit::value(value);
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .status()
+        .wrap_err_with(|| format!("running {command:?}"))?;
+    ensure!(status.success(), "{command:?} failed with {status}");
+    Ok(())
+}
+
+fn write_new(path: &Path, contents: &str, force: bool) -> Result<()> {
+    ensure!(
+        force || !path.exists(),
+        "{path:?} already exists, pass --force to overwrite it"
+    );
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).wrap_err_with(|| format!("creating {dir:?}"))?;
+    }
+    fs::write(path, contents).wrap_err_with(|| format!("writing {path:?}"))
+}
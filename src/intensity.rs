@@ -0,0 +1,87 @@
+//! Input intensity of the work intervals: a short idle notification tells when the keyboard
+//! and the mouse are in use, and the share of the work interval spent using them is stored
+//! with each pomodoro. The longest stretch between the idle gaps scores the adaptive breaks.
+
+use std::time::{Duration, Instant};
+
+use crate::Passata;
+
+/// Inactivity after which the user no longer counts as typing or moving the mouse
+pub const ACTIVITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Intensity {
+    /// Active time of the work interval, up to `active_since`
+    active: Duration,
+    /// Start of the current stretch of activity
+    active_since: Option<Instant>,
+    /// Longest stretch of activity of the work interval, up to `active_since`
+    longest: Duration,
+}
+
+impl Intensity {
+    /// The user counts as active until the first idle notification says otherwise
+    pub fn new() -> Self {
+        Self {
+            active: Duration::ZERO,
+            active_since: Some(Instant::now()),
+            longest: Duration::ZERO,
+        }
+    }
+}
+
+impl Passata {
+    /// The input went quiet for ACTIVITY_TIMEOUT, or resumed
+    pub fn activity_changed(&mut self, idled: bool) {
+        let Some(intensity) = &mut self.intensity else {
+            return;
+        };
+        if idled {
+            if let Some(since) = intensity.active_since.take() {
+                let stretch = since.elapsed();
+                intensity.active += stretch;
+                intensity.longest = intensity.longest.max(stretch);
+            }
+        } else {
+            intensity.active_since.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Start counting the activity of a new work interval
+    pub fn reset_intensity(&mut self) {
+        if let Some(intensity) = &mut self.intensity {
+            intensity.active = Duration::ZERO;
+            intensity.longest = Duration::ZERO;
+            if intensity.active_since.is_some() {
+                intensity.active_since = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Share of the `worked` time spent using the keyboard or the mouse, from 0 to 1
+    pub fn intensity(&self, worked: Duration) -> Option<f32> {
+        let intensity = self.intensity.as_ref()?;
+        if worked.is_zero() {
+            return None;
+        }
+        let active = intensity.active
+            + intensity
+                .active_since
+                .map(|since| since.elapsed())
+                .unwrap_or_default();
+        Some((active.as_secs_f32() / worked.as_secs_f32()).min(1.0))
+    }
+
+    /// Share of the `worked` time taken by the longest stretch of activity, from 0 to 1
+    pub fn longest_stretch_share(&self, worked: Duration) -> Option<f32> {
+        let intensity = self.intensity.as_ref()?;
+        if worked.is_zero() {
+            return None;
+        }
+        let current = intensity
+            .active_since
+            .map(|since| since.elapsed())
+            .unwrap_or_default();
+        let longest = intensity.longest.max(current);
+        Some((longest.as_secs_f32() / worked.as_secs_f32()).min(1.0))
+    }
+}
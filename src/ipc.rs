@@ -0,0 +1,357 @@
+//! Control socket of the daemon. Each connection carries a single request and its response,
+//! both encoded as one line of JSON.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    time::Duration,
+};
+
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
+use log::{debug, warn, LevelFilter};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    generic::Generic, Interest, LoopHandle, Mode, PostAction,
+};
+
+use crate::{error::IpcError, journal::JournalEntry, schedule::Upcoming, status::Status, Passata};
+
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "request")]
+pub enum Request {
+    Status,
+    /// The phases until the end of the day
+    Plan,
+    /// The next `count` breaks
+    Next {
+        count: usize,
+    },
+    /// Add time to the running phase
+    Extend {
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+    /// Remove time from the running phase
+    Shorten {
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+    /// Pause the timer, or resume it if it is paused
+    TogglePause,
+    /// Show the progress widget, or hide it if it is shown
+    ToggleWidget,
+    /// End the current phase now
+    Skip,
+    /// Start the work interval waiting for the user
+    Start,
+    /// Skip the breaks for `duration`, or stop skipping them if zero
+    Focus {
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+    /// Postpone the coming break
+    Snooze,
+    /// Turn the work interval into a stopwatch session, counting up until stopped
+    Stopwatch,
+    /// End the stopwatch session and start its break
+    Stop,
+    /// Label the work with a task, or remove the label
+    Task {
+        label: Option<String>,
+    },
+    /// Change a setting, in the running daemon and in the config file
+    Set {
+        key: String,
+        value: String,
+    },
+    /// The last events of the timer, and the new ones as they happen with `follow`
+    Events {
+        follow: bool,
+    },
+    /// Keep the connection open and receive the log records
+    Log {
+        level: String,
+        /// Only forward the records of this module, e.g. "overlay"
+        subsystem: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "response")]
+pub enum Response {
+    Status(Status),
+    Plan {
+        current: Status,
+        phases: Vec<Upcoming>,
+    },
+    Events {
+        events: Vec<JournalEntry>,
+    },
+    /// An event that just happened, for the clients following them
+    Event(JournalEntry),
+    /// The log records follow, one for each line
+    Following,
+    Log {
+        line: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Listen for requests on `path`, replacing any stale socket left there
+pub fn listen(path: &Path, loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    if path.exists() {
+        ensure_not_running(path)?;
+        fs::remove_file(path).wrap_err_with(|| format!("removing the stale socket {path:?}"))?;
+    }
+    let listener =
+        UnixListener::bind(path).wrap_err_with(|| format!("binding the socket {path:?}"))?;
+    listener.set_nonblocking(true)?;
+    loop_handle
+        .insert_source(
+            Generic::new(listener, Interest::READ, Mode::Level),
+            |_, listener, state| {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(err) = state.handle_connection(stream) {
+                                warn!("handling an ipc connection: {err:?}");
+                            }
+                        }
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Whether a daemon is listening on `path`
+pub fn is_running(path: &Path) -> bool {
+    UnixStream::connect(path).is_ok()
+}
+
+fn ensure_not_running(path: &Path) -> Result<()> {
+    if is_running(path) {
+        bail!("passata is already running, found its socket at {path:?}");
+    }
+    Ok(())
+}
+
+/// Send `request` to the daemon listening on `path` and wait for its response
+pub fn send(path: &Path, request: &Request) -> Result<Response> {
+    stream(path, request)?
+        .next()
+        .unwrap_or_else(|| Err(IpcError::NoResponse.into()))
+}
+
+/// Send `request` to the daemon listening on `path` and read the responses until the connection
+/// is closed
+pub fn stream(path: &Path, request: &Request) -> Result<impl Iterator<Item = Result<Response>>> {
+    let mut stream = UnixStream::connect(path).map_err(|source| IpcError::NotRunning {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(BufReader::new(stream)
+        .lines()
+        .map(|line| serde_json::from_str(&line?).wrap_err("parsing the response of the daemon")))
+}
+
+impl Passata {
+    fn handle_connection(&mut self, stream: UnixStream) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+        stream.set_write_timeout(Some(SOCKET_TIMEOUT))?;
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line)?;
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => self.handle_request(request, &stream),
+            Err(err) => Response::Error {
+                message: format!("invalid request: {err}"),
+            },
+        };
+        let mut line = serde_json::to_string(&response)?;
+        line.push('\n');
+        (&stream).write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request, stream: &UnixStream) -> Response {
+        match request {
+            Request::Status => {
+                debug!("ipc: status");
+                Response::Status(self.status())
+            }
+            Request::Plan => {
+                debug!("ipc: plan");
+                Response::Plan {
+                    current: self.status(),
+                    phases: self.plan(),
+                }
+            }
+            Request::Next { count } => {
+                debug!("ipc: next {count}");
+                Response::Plan {
+                    current: self.status(),
+                    phases: self.next_breaks(count),
+                }
+            }
+            Request::Extend { duration } => {
+                debug!("ipc: extend {duration:?}");
+                self.adjust_remaining(|remaining| remaining + duration)
+            }
+            Request::Shorten { duration } => {
+                debug!("ipc: shorten {duration:?}");
+                self.adjust_remaining(|remaining| remaining.saturating_sub(duration))
+            }
+            Request::TogglePause => {
+                debug!("ipc: toggle pause");
+                self.toggle_pause();
+                Response::Status(self.status())
+            }
+            Request::ToggleWidget => {
+                debug!("ipc: toggle widget");
+                self.toggle_widget();
+                Response::Status(self.status())
+            }
+            Request::Skip => {
+                debug!("ipc: skip");
+                match self.skip() {
+                    Ok(()) => Response::Status(self.status()),
+                    Err(err) => Response::Error {
+                        message: format!("{err:?}"),
+                    },
+                }
+            }
+            Request::Start => {
+                debug!("ipc: start");
+                match self.start_work() {
+                    Ok(()) => Response::Status(self.status()),
+                    Err(err) => Response::Error {
+                        message: format!("{err:?}"),
+                    },
+                }
+            }
+            Request::Focus { duration } => {
+                debug!("ipc: focus {duration:?}");
+                match self.set_focus(duration) {
+                    Ok(()) => Response::Status(self.status()),
+                    Err(err) => Response::Error {
+                        message: format!("{err:?}"),
+                    },
+                }
+            }
+            Request::Snooze => {
+                debug!("ipc: snooze");
+                match self.snooze() {
+                    Ok(()) => Response::Status(self.status()),
+                    Err(err) => Response::Error {
+                        message: format!("{err:?}"),
+                    },
+                }
+            }
+            Request::Stopwatch => {
+                debug!("ipc: stopwatch");
+                match self.start_stopwatch() {
+                    Ok(()) => Response::Status(self.status()),
+                    Err(err) => Response::Error {
+                        message: format!("{err:?}"),
+                    },
+                }
+            }
+            Request::Stop => {
+                debug!("ipc: stop");
+                match self.stop_stopwatch() {
+                    Ok(()) => Response::Status(self.status()),
+                    Err(err) => Response::Error {
+                        message: format!("{err:?}"),
+                    },
+                }
+            }
+            Request::Task { label } => {
+                debug!("ipc: task {label:?}");
+                self.task = label;
+                self.publish_status();
+                Response::Status(self.status())
+            }
+            Request::Set { key, value } => {
+                debug!("ipc: set {key} {value}");
+                match self.set_config(&key, &value) {
+                    Ok(()) => Response::Status(self.status()),
+                    Err(err) => Response::Error {
+                        message: format!("{err:?}"),
+                    },
+                }
+            }
+            Request::Events { follow } => {
+                debug!("ipc: events, follow: {follow}");
+                let events = self.journal.entries();
+                if follow {
+                    match stream.try_clone() {
+                        Ok(stream) => self.journal.follow(stream),
+                        Err(err) => {
+                            return Response::Error {
+                                message: format!("{err:?}"),
+                            }
+                        }
+                    }
+                }
+                Response::Events { events }
+            }
+            Request::Log { level, subsystem } => {
+                debug!("ipc: log {level} {subsystem:?}");
+                self.follow_log(stream, &level, subsystem)
+            }
+        }
+    }
+
+    fn follow_log(
+        &mut self,
+        stream: &UnixStream,
+        level: &str,
+        subsystem: Option<String>,
+    ) -> Response {
+        let level = match level.parse::<LevelFilter>() {
+            Ok(level) => level,
+            Err(err) => {
+                return Response::Error {
+                    message: format!("invalid log level {level:?}: {err}"),
+                }
+            }
+        };
+        match stream.try_clone() {
+            Ok(stream) => {
+                self.log_followers.add(stream, level, subsystem);
+                Response::Following
+            }
+            Err(err) => Response::Error {
+                message: format!("{err:?}"),
+            },
+        }
+    }
+
+    fn adjust_remaining(&mut self, f: impl FnOnce(Duration) -> Duration) -> Response {
+        // a deferred break is extended or shortened from now
+        self.end_deferral();
+        match self.set_remaining(f(self.remaining())) {
+            Ok(()) => Response::Status(self.status()),
+            Err(err) => Response::Error {
+                message: format!("{err:?}"),
+            },
+        }
+    }
+}
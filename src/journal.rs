@@ -0,0 +1,93 @@
+//! The last events of the timer state machine, kept in memory to tell why a break did or did
+//! not start without raising the log level. `passata events` prints them, and with `--follow`
+//! streams the new ones as they happen.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::Write,
+    os::unix::net::UnixStream,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{events::Event, ipc::Response, timer::Phase, Passata};
+
+/// How many events are kept
+const JOURNAL_SIZE: usize = 256;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct JournalEntry {
+    #[serde(with = "humantime_serde")]
+    pub at: SystemTime,
+    /// The event with its fields
+    pub event: String,
+    /// Phase the event happened in
+    pub phase: Phase,
+    pub paused: bool,
+    #[serde(with = "humantime_serde")]
+    pub remaining: Duration,
+}
+
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({}, {} left{})",
+            DateTime::<Local>::from(self.at).format("%H:%M:%S"),
+            self.event,
+            self.phase.name(),
+            humantime::format_duration(Duration::from_secs(self.remaining.as_secs())),
+            if self.paused { ", paused" } else { "" }
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct Journal {
+    entries: VecDeque<JournalEntry>,
+    /// Clients streaming the new events
+    followers: Vec<UnixStream>,
+}
+
+impl Journal {
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    pub fn follow(&mut self, stream: UnixStream) {
+        self.followers.push(stream);
+    }
+
+    fn push(&mut self, entry: JournalEntry) {
+        if !self.followers.is_empty() {
+            if let Ok(mut line) = serde_json::to_string(&Response::Event(entry.clone())) {
+                line.push('\n');
+                // the followers that went away are dropped
+                self.followers
+                    .retain(|mut stream| stream.write_all(line.as_bytes()).is_ok());
+            }
+        }
+        if self.entries.len() == JOURNAL_SIZE {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+impl Passata {
+    /// Keep `event` in the journal, with the state it happened in
+    pub fn journal_event(&mut self, event: &Event) {
+        let entry = JournalEntry {
+            at: SystemTime::now(),
+            event: format!("{event:?}"),
+            phase: self.phase,
+            paused: self.is_paused(),
+            remaining: self.remaining(),
+        };
+        self.journal.push(entry);
+    }
+}
@@ -0,0 +1,99 @@
+//! Follow the LockedHint of the logind session, so that locking the screen during a work
+//! interval can pause it or count as the break that follows, and lock it for the breaks.
+
+use std::{process::Command, thread, time::Instant};
+
+use color_eyre::Result;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+use zbus::blocking::Connection;
+
+use crate::{events::Event, idle::SessionProxy, timer::Phase, Passata};
+
+/// What the time spent with the screen locked during a work interval counts as
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LockPolicy {
+    /// Keep counting it as work
+    #[default]
+    Ignore,
+    /// Hold the work interval until the screen is unlocked
+    Pause,
+    /// Hold the work interval, and start a new one if the screen stayed locked for as long as
+    /// the break that follows it
+    Break,
+}
+
+/// Lock the session through logind, leaving the locking to the screen locker of the desktop
+pub fn lock_session() {
+    if let Err(err) = Command::new("loginctl").arg("lock-session").status() {
+        warn!("locking the session: {err}");
+    }
+}
+
+/// Start following whether the session is locked
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let connection = Connection::system()?;
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg(locked) => state.lock_changed(locked),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = follow_locked_hint(&connection, sender) {
+            warn!("stopped following the session lock: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+fn follow_locked_hint(connection: &Connection, sender: Sender<bool>) -> Result<()> {
+    let session = SessionProxy::new(connection)?;
+    sender.send(session.locked_hint()?)?;
+    for change in session.receive_locked_hint_changed() {
+        sender.send(change.get()?)?;
+    }
+    Ok(())
+}
+
+impl Passata {
+    fn lock_changed(&mut self, locked: bool) {
+        debug!("session locked: {locked}");
+        if locked {
+            // breaks are not interrupted, and neither is a lock already being followed
+            if self.phase != Phase::Work || self.locked_since.is_some() {
+                return;
+            }
+            info!("the screen is locked, pausing the work time");
+            self.locked_since = Some(Instant::now());
+            self.pause();
+            return;
+        }
+        let Some(locked_since) = self.locked_since.take() else {
+            return;
+        };
+        // the phase has been skipped by hand in the meantime
+        if self.phase != Phase::Work {
+            return;
+        }
+        let locked_for = locked_since.elapsed();
+        let next_break = self.phase_duration(self.next_event, self.next_break_type.as_deref());
+        if self.config.lock_policy == LockPolicy::Break && locked_for >= next_break {
+            self.emit(Event::BreakTakenWhileLocked { locked_for });
+            self.credit_break();
+        }
+        if !self.can_resume() {
+            self.publish_status();
+            return;
+        }
+        if let Err(err) = self.resume() {
+            error!("resuming the timer: {err:?}");
+        }
+    }
+}
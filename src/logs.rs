@@ -0,0 +1,155 @@
+//! Format the log of the daemon, and forward it to the clients following it over the control
+//! socket.
+
+use std::{
+    io::{self, Write},
+    os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
+};
+
+use flexi_logger::{default_format, DeferredNow};
+use log::{
+    kv::{self, Key, Value, VisitSource},
+    LevelFilter, Log, Metadata, Record, SetLoggerError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number};
+
+use crate::ipc::Response;
+
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, with the structured fields of the record next to the message
+    Json,
+}
+
+/// Collect the structured fields of a record, keeping numbers and booleans as such
+struct JsonFields<'a>(&'a mut Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(n) = value.to_u64() {
+            Number::from(n).into()
+        } else if let Some(n) = value.to_i64() {
+            Number::from(n).into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// Format function writing each record as a JSON line, for journald and log aggregators
+pub fn json_format(w: &mut dyn Write, now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+    let mut object = Map::new();
+    object.insert("timestamp".to_owned(), now.format_rfc3339().into());
+    object.insert("level".to_owned(), record.level().as_str().into());
+    object.insert("target".to_owned(), record.target().into());
+    object.insert("message".to_owned(), record.args().to_string().into());
+    record
+        .key_values()
+        .visit(&mut JsonFields(&mut object))
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    serde_json::to_writer(&mut *w, &object)?;
+    Ok(())
+}
+
+struct Follower {
+    stream: UnixStream,
+    level: LevelFilter,
+    subsystem: Option<String>,
+}
+
+impl Follower {
+    fn wants(&self, record: &Record) -> bool {
+        if record.level() > self.level {
+            return false;
+        }
+        let Some(subsystem) = &self.subsystem else {
+            return true;
+        };
+        let module = record.module_path().unwrap_or_default();
+        let module = module.strip_prefix("passata::").unwrap_or(module);
+        module == subsystem
+            || module
+                .strip_prefix(subsystem.as_str())
+                .is_some_and(|rest| rest.starts_with("::"))
+    }
+}
+
+/// Sends every record to the connected followers, dropping the ones that went away
+#[derive(Clone, Default)]
+pub struct Followers(Arc<Mutex<Vec<Follower>>>);
+
+impl Followers {
+    pub fn add(&self, stream: UnixStream, level: LevelFilter, subsystem: Option<String>) {
+        self.0.lock().unwrap().push(Follower {
+            stream,
+            level,
+            subsystem,
+        });
+    }
+
+    fn want_level(&self, metadata: &Metadata) -> bool {
+        let followers = self.0.lock().unwrap();
+        followers
+            .iter()
+            .any(|follower| metadata.level() <= follower.level)
+    }
+
+    fn write(&self, now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+        let mut followers = self.0.lock().unwrap();
+        if !followers.iter().any(|follower| follower.wants(record)) {
+            return Ok(());
+        }
+        let mut line = Vec::new();
+        write!(line, "{} ", now.format("%Y-%m-%d %H:%M:%S"))?;
+        default_format(&mut line, now, record)?;
+        let mut response = serde_json::to_string(&Response::Log {
+            line: String::from_utf8_lossy(&line).into_owned(),
+        })?;
+        response.push('\n');
+        followers.retain(|follower| {
+            !follower.wants(record) || (&follower.stream).write_all(response.as_bytes()).is_ok()
+        });
+        Ok(())
+    }
+}
+
+/// Global logger passing each record both to the file or the terminal, as filtered by the log
+/// specification, and to the followers, as filtered by each of them; a follower can then ask for
+/// records more verbose than the specification lets through
+pub struct Tee {
+    inner: Box<dyn Log>,
+    followers: Followers,
+}
+
+impl Tee {
+    pub fn install(inner: Box<dyn Log>, followers: Followers) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(Self { inner, followers }))?;
+        log::set_max_level(LevelFilter::Trace);
+        Ok(())
+    }
+}
+
+impl Log for Tee {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata) || self.followers.want_level(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.inner.log(record);
+        // a follower that cannot be written to is dropped, there is no one else to tell
+        let _ = self.followers.write(&mut DeferredNow::new(), record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
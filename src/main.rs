@@ -1,28 +1,91 @@
+mod activities;
+mod algorithm;
+mod align;
+mod apps;
+mod backup;
+mod bar;
+mod battery;
+mod bench;
+mod breaks;
+mod calendar;
+mod capabilities;
+mod cli;
+mod command;
+mod config;
+mod crash;
+mod dayoff;
+mod debt;
+mod error;
+mod escalation;
+mod events;
+mod focus;
+mod gamma;
+mod goal;
+mod http;
+mod i18n;
+mod idle;
+mod inhibit;
+mod init;
+mod intensity;
+mod ipc;
+mod journal;
+mod lock;
+mod logs;
+mod meetings;
+mod mic;
+mod mpris;
+mod mqtt;
+mod network;
+mod notify;
+mod overlay;
 mod pipewire;
+mod profile;
+mod prompt;
+mod quiet;
+mod reconnect;
+mod report;
+mod schedule;
+mod session;
+mod signals;
+mod snooze;
+mod stats;
+mod status;
+mod stopwatch;
+mod streak;
+mod survey;
+mod suspend;
+mod team;
+mod timeclock;
+mod timer;
+mod tray;
+mod tui;
+mod typing;
+mod watch;
+mod watchdog;
+mod widget;
+mod workspaces;
+#[cfg(feature = "x11")]
+mod x11;
 
 use std::{
+    collections::HashSet,
+    net::UdpSocket,
     path::PathBuf,
-    process::{exit, Command},
-    time::{Duration, Instant},
+    process::{exit, Child},
+    time::{Duration, Instant, SystemTime},
 };
 
 use color_eyre::{
-    eyre::{ensure, eyre, ContextCompat, WrapErr},
+    eyre::{eyre, ContextCompat, WrapErr},
     Result,
 };
-use figment::{
-    providers::{Env, Format, Toml},
-    Figment,
-};
-use flexi_logger::{Duplicate, FileSpec, Logger};
-use lexopt::Arg::{Long, Short};
-use log::debug;
+use flexi_logger::{FileSpec, Logger};
+use log::warn;
 use nix::unistd::fork;
-use notify_rust::Notification;
-use serde::{Deserialize, Serialize};
+use regex::RegexSet;
 use smithay_client_toolkit::reexports::{
-    calloop::Dispatcher,
-    client::{Connection, Dispatch},
+    calloop::{channel::Sender, LoopHandle, RegistrationToken},
+    client::{globals::GlobalList, Connection, Dispatch, EventQueue, QueueHandle},
     protocols::ext::idle_notify::v1::client::{
         ext_idle_notification_v1::{self, ExtIdleNotificationV1},
         ext_idle_notifier_v1::ExtIdleNotifierV1,
@@ -30,12 +93,9 @@ use smithay_client_toolkit::reexports::{
 };
 use smithay_client_toolkit::{
     delegate_registry, delegate_seat,
+    output::OutputState,
     reexports::{
-        calloop::{
-            timer::{TimeoutAction, Timer},
-            EventLoop,
-        },
-        calloop_wayland_source::WaylandSource,
+        calloop::EventLoop, calloop_wayland_source::WaylandSource,
         client::globals::registry_queue_init,
     },
     registry::{ProvidesRegistryState, RegistryState},
@@ -44,232 +104,648 @@ use smithay_client_toolkit::{
 };
 use xdg::BaseDirectories;
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
-struct Config {
-    #[serde(rename = "interval", with = "humantime_serde")]
-    pub work_interval: Duration,
-    #[serde(with = "humantime_serde")]
-    pub short_break: Duration,
-    #[serde(with = "humantime_serde")]
-    pub long_break: Option<Duration>,
-    pub short_breaks_before_long_break: Option<u8>,
-    #[serde(with = "humantime_serde")]
-    pub idle_timeout: Option<Duration>,
-}
-
-#[derive(PartialEq)]
-enum NextEvent {
-    Work,
-    ShortBreak,
-    LongBreak,
-}
-
-struct Args {
-    config: Option<PathBuf>,
-    daemon: bool,
-}
-
-fn parse_args() -> Result<Args, lexopt::Error> {
-    let mut config: Option<PathBuf> = None;
-    let mut daemon = false;
-    let mut parser = lexopt::Parser::from_env();
-    while let Some(arg) = parser.next()? {
-        match arg {
-            Short('c') | Long("config") => {
-                config = Some(PathBuf::from(parser.value()?));
-            }
-            Short('d') | Long("daemon") => {
-                daemon = true;
-            }
-            _ => return Err(arg.unexpected()),
-        }
-    }
-
-    Ok(Args { config, daemon })
-}
-
-enum IdleStatus {
-    Idled,
-    Resumed,
-}
+use crate::{
+    activities::ActivityRotation,
+    apps::WorkApps,
+    battery::BatteryPolicy,
+    capabilities::Capabilities,
+    cli::parse_args,
+    config::Config,
+    error::WaylandError,
+    events::NotificationAction,
+    focus::Focus,
+    gamma::Gamma,
+    goal::DailyProgress,
+    i18n::Language,
+    idle::{Absence, IdleBackend, IdleLevel},
+    intensity::Intensity,
+    journal::Journal,
+    lock::LockPolicy,
+    logs::{Followers, LogFormat, Tee},
+    mpris::Mpris,
+    mqtt::Mqtt,
+    overlay::Overlay,
+    quiet::HeldNotification,
+    stats::Stats,
+    status::Health,
+    streak::Streak,
+    timer::Phase,
+    tray::Tray,
+    widget::Widget,
+    workspaces::WorkspaceCountdown,
+};
 
 struct Passata {
-    next_event: NextEvent,
+    /// The phase currently running
+    phase: Phase,
+    /// Full length of the current phase
+    phase_length: Duration,
+    next_event: Phase,
     current_short_breaks: u8,
+    completed_pomodoros: u32,
     config: Config,
-    registry_state: RegistryState,
-    seat_state: SeatState,
+    /// Connection state of the compositor, none on X11
+    wayland: Option<Wayland>,
+    loop_handle: LoopHandle<'static, Passata>,
+    timer_token: Option<RegistrationToken>,
     /// When the system is currently in idle, the remaining time before a break is stored here
-    time_passed: Option<Duration>,
+    paused_remaining: Option<Duration>,
+    /// The timer is held until the user resumes it, either after pausing it by hand or while
+    /// waiting to start the work interval
+    user_paused: bool,
     /// Determine when the timer was started
     timer_started: Instant,
-    /// either Idled or Resumed
-    idle_status: Option<IdleStatus>,
+    /// Same as `timer_started`, on the wall clock
+    timer_started_wall: SystemTime,
+    /// Duration the timer was armed with
+    timer_duration: Duration,
+    mpris: Option<Mpris>,
+    mqtt: Option<Mqtt>,
+    tray: Option<Tray>,
+    workspace_countdown: Option<WorkspaceCountdown>,
+    overlay: Option<Overlay>,
+    /// Progress ring in a corner of the screen, while shown
+    widget: Option<Widget>,
+    gamma: Option<Gamma>,
+    work_apps: Option<WorkApps>,
+    /// Whether the user is currently idle
+    idle: bool,
+    /// When the user went idle during the work interval
+    idle_since: Option<Instant>,
+    /// Pending reset of the work interval, when following the logind idle hint
+    idle_reset_token: Option<RegistrationToken>,
+    /// The return from idle being handled, for the notification of the timer resuming
+    absence: Option<Absence>,
+    /// When the system was suspended, until it resumes
+    suspended_at: Option<SystemTime>,
+    /// When the screen has been locked during the work interval, if it still is
+    locked_since: Option<Instant>,
+    /// Whether an idle inhibitor is active, making the current time media time
+    inhibited: bool,
+    /// Whether the focused application is not one of the work apps
+    off_work_app: bool,
+    /// Whether the laptop runs on battery
+    on_battery: bool,
+    /// Whether an application inhibits the idle of the desktop session, deferring the breaks
+    session_inhibited: bool,
+    /// Whether an application records from the microphone, deferring the breaks
+    mic_in_use: bool,
+    /// Regexes of the meeting windows
+    meeting_windows: RegexSet,
+    /// Whether a meeting window is focused, deferring the breaks
+    in_meeting: bool,
+    /// Pending steps of the break escalation ladder
+    escalation_tokens: Vec<RegistrationToken>,
+    focused_workspace: Option<String>,
+    /// The work interval is over but the break is being held back
+    break_deferred: bool,
+    /// Starts the deferred break once it has been held back for max-deferral
+    deferral_token: Option<RegistrationToken>,
+    /// The current break has been delivered without escalating
+    break_quiet: bool,
+    /// The user went idle during the current break, taking it
+    break_idled: bool,
+    /// No input for a few seconds
+    input_idle: bool,
+    /// The user stepped away from the input during the current break, since then
+    break_away_since: Option<Instant>,
+    /// Breaks skipped in a row, paid off by taking one
+    breaks_skipped: u32,
+    /// Pomodoros completed today, towards the daily goal
+    progress: DailyProgress,
+    streak: Streak,
+    /// Desktop notification of the current phase, replaced by the next one
+    phase_notification: Option<u32>,
+    /// What the notifications held during the quiet hours announced, for the digest
+    quiet_digest: Vec<HeldNotification>,
+    /// Where the config has been read from, rewritten by `passata set`
+    config_path: PathBuf,
+    /// Name of the profile overriding the timings
+    active_profile: Option<String>,
+    /// Name of the time range of the day overriding the timings of the current work interval
+    active_time_range: Option<String>,
+    /// Name of the break from the sequence currently running
+    break_type: Option<String>,
+    /// Name of the break from the sequence that follows the current work interval
+    next_break_type: Option<String>,
+    /// Position in the sequence of the break after `next_break_type`, or work intervals
+    /// counted for the breaks taken every few of them
+    sequence_position: usize,
+    /// Activity suggested for the current break
+    activity: Option<String>,
+    activity_rotation: ActivityRotation,
+    /// Length of the work interval before the current break
+    worked_before_break: Duration,
+    /// Length of the work interval the survey shown last refers to, until it is answered
+    survey_worked: Option<Duration>,
+    /// Label of the task being worked on
+    task: Option<String>,
+    /// Input activity of the work interval, when measured
+    intensity: Option<Intensity>,
+    /// The input has been used within MICRO_PAUSE
+    typing: bool,
+    /// Grace window given to finish typing before the break, while open
+    typing_grace_token: Option<RegistrationToken>,
+    /// When the user was last seen using the computer
+    last_active: SystemTime,
+    /// Nobody used the computer by day-off.after, the daemon is dormant
+    day_off: bool,
+    /// Wakes up from the day off after a while of sustained activity
+    day_off_wake_token: Option<RegistrationToken>,
+    /// Dialog shown to start the work interval, while it is open
+    break_prompt: Option<Child>,
+    /// Command of the current break, while it runs
+    break_command: Option<Child>,
+    /// Commands of past breaks that have been stopped, until they exit
+    stopped_commands: Vec<Child>,
+    /// Breaks are skipped until the focus mode ends
+    focus: Option<Focus>,
+    /// The work interval counts up until stopped, followed by a proportional break
+    stopwatch: bool,
+    /// Times the break after the current work interval has been snoozed
+    snoozes: u32,
+    stats: Stats,
+    /// When the daemon started
+    started: Instant,
+    health: Health,
+    /// Clients receiving the log over the control socket
+    log_followers: Followers,
+    /// The last events of the timer
+    journal: Journal,
+    notification_actions: Sender<NotificationAction>,
+    /// A notification server is running, the desktop notifier is skipped until then
+    notification_server: bool,
+    /// Desktop notifications whose actions are being waited for
+    action_waiters: HashSet<u32>,
+    /// Socket the breaks are announced to the team from, in team mode
+    team_socket: Option<UdpSocket>,
+    /// Running the benchmark, nothing must reach the desktop
+    bench: bool,
+}
+
+fn main() {
+    if let Err(err) = error::install_handler() {
+        eprintln!("Error: {err:?}");
+    }
+    if let Err(err) = run() {
+        let code = error::exit_code(&err);
+        eprintln!("Error: {:?}", error::with_hint(err));
+        exit(code);
+    }
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     let args = parse_args()?;
     let xdg = BaseDirectories::with_prefix("passata")?;
+    let config_file = args.config.unwrap_or(xdg.get_config_file("passata.toml"));
+
+    if let Some(command) = args.command {
+        return cli::run(command, &session::client_socket(&xdg)?, &config_file);
+    }
+    let socket = session::socket(&xdg)?;
+
+    let config = Config::load(&config_file)?;
+    let meeting_windows = config.meetings.regex_set()?;
 
     let mut logger = Logger::try_with_env_or_str("info")?;
+    if config.log_format == LogFormat::Json {
+        logger = logger.format(logs::json_format);
+    }
+    let log_followers = Followers::default();
 
     if args.daemon {
-        logger = logger.log_to_file(FileSpec::default().directory(xdg.get_state_home()));
+        let mut log_file = FileSpec::default().directory(xdg.get_state_home());
+        // the sessions of the same user each log to their own file
+        if let Some(session) = session::name() {
+            log_file = log_file.discriminant(session);
+        }
+        logger = logger.log_to_file(log_file);
         match unsafe { fork()? } {
             nix::unistd::ForkResult::Parent { child: _ } => exit(0),
             nix::unistd::ForkResult::Child => {}
         }
-    } else {
-        logger = logger.duplicate_to_stderr(Duplicate::Warn);
     }
 
-    logger.start()?;
-
-    let config_file = args.config.unwrap_or(xdg.get_config_file("passata.toml"));
-    ensure!(
-        config_file.exists(),
-        "Could not find config file {config_file:?}"
+    // the handle shuts the file writer down when dropped, keep it until the daemon exits
+    let (logger, _log_handle) = logger.build()?;
+    Tee::install(logger, log_followers.clone())?;
+    crash::install_hook(
+        xdg.place_state_file(session::file_name("crash-status", "json"))?,
+        config.language.unwrap_or_else(Language::detect),
     );
 
-    let config: Config = Figment::new()
-        .merge(Toml::file(config_file))
-        .merge(Env::prefixed("PASSATA"))
-        .extract()?;
-
-    let conn = Connection::connect_to_env().unwrap();
+    let conn = match Connection::connect_to_env() {
+        Ok(conn) => Some(conn),
+        #[cfg(feature = "x11")]
+        Err(err) if std::env::var_os("DISPLAY").is_some() => {
+            warn!("running on X11, there is no wayland compositor: {err}");
+            None
+        }
+        Err(err) => return Err(WaylandError::from(err).into()),
+    };
 
-    let (globals, event_queue) = registry_queue_init(&conn).unwrap();
-    let qh = event_queue.handle();
+    let (globals, event_queue) = match &conn {
+        Some(conn) => {
+            let (globals, event_queue) = registry_queue_init(conn).map_err(WaylandError::from)?;
+            (Some(globals), Some(event_queue))
+        }
+        None => (None, None),
+    };
+    let qh = event_queue.as_ref().map(EventQueue::handle);
+    let wayland_globals = globals.as_ref().zip(qh.as_ref());
 
     let mut event_loop = EventLoop::<Passata>::try_new()?;
-    WaylandSource::new(conn.clone(), event_queue)
-        .insert(event_loop.handle())
-        .map_err(|e| eyre!("insterting the wayland source into the event loop: {e}"))?;
+    // before any thread is spawned, so that they all keep the signals blocked
+    signals::listen(&event_loop.handle())?;
+
+    // the benchmark completes thousands of pomodoros, keep them out of the history
+    let history = if args.bench {
+        std::env::temp_dir().join(format!("passata-bench-{}.jsonl", std::process::id()))
+    } else {
+        xdg.place_data_file("history.jsonl")?
+    };
 
-    let registry_state = RegistryState::new(&globals);
     let mut state = Passata {
-        next_event: NextEvent::ShortBreak,
+        phase: Phase::Work,
+        phase_length: config.work_interval,
+        next_event: Phase::ShortBreak,
         current_short_breaks: 0,
+        completed_pomodoros: 0,
         config,
-        registry_state,
-        seat_state: SeatState::new(&globals, &qh),
-        time_passed: None,
+        wayland: conn
+            .as_ref()
+            .zip(wayland_globals)
+            .map(|(conn, (globals, qh))| Wayland::new(conn.clone(), globals, qh)),
+        loop_handle: event_loop.handle(),
+        timer_token: None,
+        paused_remaining: None,
+        user_paused: false,
         timer_started: Instant::now(),
-        idle_status: None,
+        timer_started_wall: SystemTime::now(),
+        timer_duration: Duration::ZERO,
+        mpris: None,
+        mqtt: None,
+        tray: None,
+        workspace_countdown: None,
+        overlay: None,
+        widget: None,
+        gamma: None,
+        work_apps: None,
+        idle: false,
+        idle_since: None,
+        idle_reset_token: None,
+        absence: None,
+        suspended_at: None,
+        locked_since: None,
+        inhibited: false,
+        off_work_app: false,
+        on_battery: false,
+        session_inhibited: false,
+        mic_in_use: false,
+        meeting_windows,
+        in_meeting: false,
+        escalation_tokens: Vec::new(),
+        focused_workspace: None,
+        break_deferred: false,
+        deferral_token: None,
+        break_quiet: false,
+        break_idled: false,
+        input_idle: false,
+        break_away_since: None,
+        breaks_skipped: debt::skipped_in_row(&history),
+        progress: DailyProgress::load(&history),
+        streak: Streak::load(&history),
+        phase_notification: None,
+        quiet_digest: Vec::new(),
+        config_path: config_file,
+        active_profile: None,
+        active_time_range: None,
+        break_type: None,
+        next_break_type: None,
+        sequence_position: 0,
+        activity: None,
+        activity_rotation: ActivityRotation::default(),
+        worked_before_break: Duration::ZERO,
+        survey_worked: None,
+        task: None,
+        intensity: None,
+        // like the intensity, the user counts as active until told otherwise
+        typing: true,
+        typing_grace_token: None,
+        last_active: SystemTime::now(),
+        day_off: false,
+        day_off_wake_token: None,
+        break_prompt: None,
+        break_command: None,
+        stopped_commands: Vec::new(),
+        focus: None,
+        stopwatch: false,
+        snoozes: 0,
+        stats: Stats::new(history.clone()),
+        started: Instant::now(),
+        health: Health::default(),
+        log_followers,
+        journal: Journal::default(),
+        notification_actions: events::listen_notification_actions(&event_loop.handle())?,
+        notification_server: true,
+        action_waiters: HashSet::new(),
+        team_socket: None,
+        bench: args.bench,
     };
 
-    let idle_notifier = globals.bind::<ExtIdleNotifierV1, Passata, ()>(&qh, 1..=1, ())?;
-    let seat = state.seat_state.seats().next().context("no seats found")?;
-    if let Some(idle_timeout) = state.config.idle_timeout {
-        idle_notifier.get_idle_notification(
-            idle_timeout.as_millis().try_into().unwrap(),
-            &seat,
-            &qh,
-            (),
-        );
-        //notification_cb);
-    }
-
-    let handle = event_loop.handle();
-    let timer = Timer::from_duration(state.config.work_interval);
-    let dispatcher = Dispatcher::new(timer, move |_instant, _, state: &mut Passata| {
-        state.timer_started = Instant::now();
-        match state.next_event {
-            NextEvent::Work => {
-                debug!("work again!");
-                state.next_event = if let Some(short_breaks_before_long_break) =
-                    state.config.short_breaks_before_long_break
-                {
-                    if state.current_short_breaks == short_breaks_before_long_break {
-                        state.current_short_breaks = 0;
-                        NextEvent::LongBreak
-                    } else {
-                        state.current_short_breaks += 1;
-                        NextEvent::ShortBreak
-                    }
-                } else {
-                    NextEvent::ShortBreak
-                };
-                TimeoutAction::ToDuration(state.config.work_interval)
+    if let (Some(conn), Some(mut event_queue)) = (conn, event_queue) {
+        // the names of the seats and outputs are only known after their first events
+        event_queue
+            .roundtrip(&mut state)
+            .context("receiving the seats and outputs")?;
+        let token = WaylandSource::new(conn, event_queue)
+            .insert(event_loop.handle())
+            .map_err(|e| eyre!("insterting the wayland source into the event loop: {e}"))?;
+        if let Some(wayland) = &mut state.wayland {
+            wayland.token = Some(token);
+        }
+    }
+
+    state.select_time_range();
+    state.phase_length = state.work_interval();
+    if !state.config.sequence.is_empty() {
+        state.advance_cycle();
+    }
+
+    if state.bench {
+        state.config.badge = None;
+        let res = bench::run(&mut state);
+        let _ = std::fs::remove_file(&history);
+        let _ = std::fs::remove_file(history.with_extension("lock"));
+        return res;
+    }
+
+    ipc::listen(&socket, &state.loop_handle)?;
+    if let Some(address) = state.config.listen {
+        state.config.ensure_network_allowed("listen")?;
+        http::listen(address, &state.loop_handle)?;
+    }
+    if let Some(address) = state.config.team.listen {
+        state.config.ensure_network_allowed("team")?;
+        let secret = state.config.team.secret.clone().unwrap_or_default();
+        state.team_socket = Some(team::listen(address, secret, &state.loop_handle)?);
+    }
+
+    let capabilities = Capabilities::detect(globals.as_ref(), &state.config.capabilities);
+    capabilities.filter_notifiers(&mut state.config.notifiers);
+    state.notification_server = capabilities.notification_server;
+    if capabilities.may_gain_notification_server(&state.config.capabilities) {
+        capabilities::watch_notification_server(&state.loop_handle)?;
+    }
+
+    match wayland_globals {
+        Some((globals, qh)) => state.bind_wayland(globals, qh, &capabilities),
+        None if state.follows_toplevels() => {
+            warn!(
+                "the work apps and the meeting windows will not be followed: not a wayland session"
+            );
+        }
+        None => {}
+    }
+
+    let idle_backend = state
+        .config
+        .idle_backend
+        .unwrap_or_else(|| capabilities.idle_backend());
+    match idle_backend {
+        IdleBackend::Wayland => {
+            let (globals, qh) =
+                wayland_globals.context("the wayland idle backend needs a wayland compositor")?;
+            state.bind_idle_notifications(globals, qh)?;
+            if state.config.input_intensity {
+                state.intensity = Some(Intensity::new());
+            }
+        }
+        #[cfg(feature = "x11")]
+        IdleBackend::X11 => {
+            if let Err(err) = x11::watch_idle(&state.loop_handle, state.idle_timeouts()) {
+                warn!("idle will be ignored: {err:?}");
+            } else if state.config.input_intensity {
+                state.intensity = Some(Intensity::new());
+            }
+        }
+        IdleBackend::Logind => {
+            if state.config.input_intensity {
+                warn!("the input intensity needs the wayland idle backend");
+            }
+            if state.config.grace_typing.is_some() {
+                warn!("grace-typing needs the wayland or the x11 idle backend");
+            }
+            if state.config.end_break_on_activity {
+                warn!("end-break-on-activity needs the wayland or the x11 idle backend");
             }
-            NextEvent::ShortBreak => {
-                debug!("short break!");
-                state.next_event = NextEvent::Work;
-                let summary_part = if let Some(short_breaks_before_long_break) =
-                    state.config.short_breaks_before_long_break
-                {
-                    format!(
-                        " ({}/{})",
-                        state.current_short_breaks,
-                        short_breaks_before_long_break + 1
-                    )
-                } else {
-                    "".to_owned()
-                };
-                Notification::new()
-                    .summary(&format!("Short break{}", summary_part))
-                    .body("Take a pause!")
-                    .show()
-                    .unwrap();
-                TimeoutAction::ToDuration(state.config.short_break)
+            if let Err(err) = idle::watch_logind(&state.loop_handle) {
+                warn!("idle will be ignored: {err:?}");
             }
-            NextEvent::LongBreak => {
-                state.next_event = NextEvent::Work;
-                Notification::new()
-                    .summary("Long break")
-                    .body("Take a long pause!")
-                    .show()
-                    .unwrap();
-                TimeoutAction::ToDuration(state.config.long_break.unwrap())
+        }
+    }
+
+    if !state.config.workspaces.quiet.is_empty() {
+        if let Err(err) = workspaces::watch(&state.loop_handle) {
+            warn!("quiet workspaces will be ignored: {err:?}");
+        }
+    }
+
+    if let Some(workspace) = state.config.workspaces.countdown.clone() {
+        match WorkspaceCountdown::new(workspace, state.status()) {
+            Ok(countdown) => state.workspace_countdown = Some(countdown),
+            Err(err) => warn!("the time left will not be shown on the workspace: {err:?}"),
+        }
+    }
+
+    if state.config.idle_inhibitors {
+        if let Err(err) = inhibit::watch(&state.loop_handle) {
+            warn!("idle inhibitors will be ignored: {err:?}");
+        }
+    }
+
+    if state
+        .config
+        .respect_inhibitors
+        .unwrap_or_else(|| capabilities.respect_inhibitors())
+    {
+        if let Err(err) = inhibit::watch_session(&state.loop_handle) {
+            warn!("the session inhibitors will be ignored: {err:?}");
+        }
+    }
+
+    if state.config.defer_on_mic {
+        if let Err(err) = mic::watch(&state.loop_handle) {
+            warn!("the microphone will be ignored: {err:?}");
+        }
+    }
+
+    if state.config.on_battery != BatteryPolicy::Ignore {
+        if let Err(err) = battery::watch(&state.loop_handle) {
+            warn!("the battery will be ignored: {err:?}");
+        }
+    }
+
+    if state.config.lock_policy != LockPolicy::Ignore {
+        if let Err(err) = lock::watch(&state.loop_handle) {
+            warn!("the screen lock will be ignored: {err:?}");
+        }
+    }
+
+    if !state.config.calendar.sources.is_empty() {
+        if let Err(err) = calendar::watch(&state.loop_handle, state.config.calendar.refresh) {
+            warn!("the calendar will be ignored: {err:?}");
+        }
+    }
+
+    if let Some(after) = state.config.day_off.after {
+        dayoff::watch(&state.loop_handle, after)?;
+    }
+
+    if state.config.quiet_hours.end.is_some() {
+        quiet::watch(&state.loop_handle)?;
+    }
+
+    if state.config.report.weekly {
+        report::schedule(&state.loop_handle, state.config.report.at)?;
+    }
+
+    if !state.config.network_profiles.is_empty() {
+        if let Err(err) = network::watch(&state.loop_handle) {
+            warn!("network profiles will be ignored: {err:?}");
+        }
+    }
+
+    let remaining = state.align_phase_length(state.phase_length);
+    state.schedule(remaining)?;
+    watchdog::start(&state.loop_handle)?;
+    if capabilities.system_bus {
+        if let Err(err) = suspend::watch(&state.loop_handle) {
+            warn!("the suspends will be noticed late, as clock jumps: {err:?}");
+        }
+    }
+    if state.config.history.raw_retention.is_some() {
+        stats::schedule_compaction(&state.loop_handle)?;
+    }
+
+    if state.config.mpris {
+        match Mpris::new(state.status()) {
+            Ok(mpris) => state.mpris = Some(mpris),
+            Err(err) => warn!("could not expose the mpris player: {err}"),
+        }
+    }
+
+    if let Some(mqtt) = state.config.mqtt.clone() {
+        state.config.ensure_network_allowed("mqtt")?;
+        state.mqtt = Some(Mqtt::new(mqtt, history, state.status()));
+    }
+
+    if state.config.tray {
+        match Tray::new(state.status(), state.language()) {
+            Ok(tray) => {
+                state.tray = Some(tray);
+                state.tick_tray()?;
             }
+            Err(err) => warn!("could not show the tray icon: {err}"),
         }
-    });
-    let registration_token = handle.register_dispatcher(dispatcher.clone()).unwrap();
+    }
 
     loop {
-        event_loop
-            .dispatch(None, &mut state)
-            .context("dispatching the event loop")?;
-
-        // don't process the idle events when a break is currently going on
-        if NextEvent::Work != state.next_event {
-            if let Some(idle_event) = state.idle_status.take() {
-                match idle_event {
-                    IdleStatus::Idled => {
-                        handle.disable(&registration_token)?;
-                        state.time_passed = Some(state.timer_started.elapsed());
-                    }
-                    IdleStatus::Resumed => {
-                        let time_left = state.config.work_interval - state.time_passed.unwrap();
-                        dispatcher.as_source_mut().set_duration(time_left);
-                        debug!("time left before break: {time_left:?}");
-                        handle.enable(&registration_token)?;
-                        state.timer_started = Instant::now();
-                        let time_left = time_left.as_secs();
-                        let time_left = if time_left < 60 {
-                            time_left
-                        } else {
-                            time_left - time_left % 60
-                        };
-                        Notification::new()
-                            .summary(&format!(
-                                "{} until next break",
-                                humantime::format_duration(Duration::from_secs(time_left))
-                            ))
-                            .body("Take a pause!")
-                            .show()
-                            .unwrap();
-                    }
+        if let Err(err) = event_loop.dispatch(None, &mut state) {
+            if !state.wayland_lost() {
+                return Err(err).context("dispatching the event loop");
+            }
+            warn!("lost the connection to the wayland compositor: {err}");
+            state.wayland_disconnected()?;
+        }
+    }
+}
+
+/// Wayland state of the session, shared by the handlers of the seats and of the outputs
+struct Wayland {
+    conn: Connection,
+    registry_state: RegistryState,
+    seat_state: SeatState,
+    output_state: OutputState,
+    qh: QueueHandle<Passata>,
+    /// Source of the event queue in the event loop
+    token: Option<RegistrationToken>,
+    /// The idle state comes from the idle notifications of the compositor
+    idle_notifications: bool,
+}
+
+impl Wayland {
+    fn new(conn: Connection, globals: &GlobalList, qh: &QueueHandle<Passata>) -> Self {
+        Self {
+            conn,
+            registry_state: RegistryState::new(globals),
+            seat_state: SeatState::new(globals, qh),
+            output_state: OutputState::new(globals, qh),
+            qh: qh.clone(),
+            token: None,
+            idle_notifications: false,
+        }
+    }
+}
+
+impl Passata {
+    /// The wayland state, only for the state accessors of the handlers: they are called while
+    /// dispatching the events of the compositor, which are only received with a compositor
+    fn wayland_mut(&mut self) -> &mut Wayland {
+        self.wayland
+            .as_mut()
+            .expect("wayland events are only received with a compositor")
+    }
+
+    /// Create the overlay, the widget, the dimming and the work apps on the globals of the
+    /// compositor
+    fn bind_wayland(
+        &mut self,
+        globals: &GlobalList,
+        qh: &QueueHandle<Passata>,
+        capabilities: &Capabilities,
+    ) {
+        if capabilities.layer_shell {
+            match Overlay::new(globals, qh, &self.loop_handle, capabilities.idle_inhibit) {
+                Ok(overlay) => self.overlay = Some(overlay),
+                Err(err) => warn!("the break overlay will not be available: {err:?}"),
+            }
+            if self.config.widget.enabled {
+                self.show_widget();
+            }
+        } else {
+            warn!("the break overlay will not be available: the compositor has no layer shell");
+        }
+
+        if capabilities.gamma_control {
+            match Gamma::new(globals, qh) {
+                Ok(gamma) => self.gamma = Some(gamma),
+                Err(err) => warn!("dimming will not be available: {err:?}"),
+            }
+        }
+
+        if self.follows_toplevels() {
+            match WorkApps::new(globals, qh) {
+                Ok(work_apps) => self.work_apps = Some(work_apps),
+                Err(err) => {
+                    warn!("the work apps and the meeting windows will not be followed: {err:?}")
                 }
             }
         }
     }
+
+    /// Whether the toplevels are needed, for the work apps, the meeting windows or the
+    /// enforcement exceptions
+    fn follows_toplevels(&self) -> bool {
+        !self.config.work_apps.apps.is_empty()
+            || !self.meeting_windows.is_empty()
+            || !self.config.enforcement_exceptions.is_empty()
+    }
 }
 
 impl SeatHandler for Passata {
     fn seat_state(&mut self) -> &mut SeatState {
-        &mut self.seat_state
+        &mut self.wayland_mut().seat_state
     }
 
     fn new_seat(
@@ -313,10 +789,10 @@ delegate_seat!(Passata);
 
 impl ProvidesRegistryState for Passata {
     fn registry(&mut self) -> &mut RegistryState {
-        &mut self.registry_state
+        &mut self.wayland_mut().registry_state
     }
 
-    registry_handlers!(SeatState);
+    registry_handlers!(OutputState, SeatState);
 }
 
 impl Dispatch<ExtIdleNotifierV1, ()> for Passata {
@@ -331,25 +807,20 @@ impl Dispatch<ExtIdleNotifierV1, ()> for Passata {
     }
 }
 
-impl Dispatch<ExtIdleNotificationV1, ()> for Passata {
+impl Dispatch<ExtIdleNotificationV1, IdleLevel> for Passata {
     fn event(
         passata: &mut Self,
         _proxy: &ExtIdleNotificationV1,
         event: <ExtIdleNotificationV1 as smithay_client_toolkit::reexports::client::Proxy>::Event,
-        _data: &(),
+        level: &IdleLevel,
         _conn: &Connection,
         _qh: &smithay_client_toolkit::reexports::client::QueueHandle<Self>,
     ) {
-        match event {
-            ext_idle_notification_v1::Event::Idled => {
-                passata.idle_status = Some(IdleStatus::Idled);
-                debug!("idled!");
-            }
-            ext_idle_notification_v1::Event::Resumed => {
-                passata.idle_status = Some(IdleStatus::Resumed);
-                debug!("resumed!");
-            }
+        let idled = match event {
+            ext_idle_notification_v1::Event::Idled => true,
+            ext_idle_notification_v1::Event::Resumed => false,
             _ => unreachable!(),
-        }
+        };
+        passata.idle_changed(level, idled);
     }
 }
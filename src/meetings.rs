@@ -0,0 +1,45 @@
+//! Meeting windows: the breaks are deferred while the focused toplevel looks like a meeting,
+//! by its title or app id, catching the meetings listened to with the microphone muted.
+
+use log::{debug, error};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{apps::WorkApps, Passata};
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct MeetingsConfig {
+    /// Regexes matched against the title and the app id of the focused toplevel, e.g.
+    /// "Zoom Meeting"; the focus is not followed for them when empty
+    pub windows: Vec<String>,
+}
+
+impl MeetingsConfig {
+    pub fn regex_set(&self) -> Result<RegexSet, regex::Error> {
+        RegexSet::new(&self.windows)
+    }
+}
+
+impl Passata {
+    pub fn meeting_window_changed(&mut self) {
+        if self.meeting_windows.is_empty() {
+            return;
+        }
+        // without a focused toplevel, e.g. while switching, the last verdict holds
+        let Some((app_id, title)) = self.work_apps.as_ref().and_then(WorkApps::focused_window)
+        else {
+            return;
+        };
+        let in_meeting =
+            self.meeting_windows.is_match(title) || self.meeting_windows.is_match(app_id);
+        if in_meeting == self.in_meeting {
+            return;
+        }
+        debug!("focused {app_id} {title:?}, a meeting: {in_meeting}");
+        self.in_meeting = in_meeting;
+        if let Err(err) = self.check_deferred_break() {
+            error!("starting the deferred break: {err:?}");
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Defer the breaks while an application records from the microphone, as during a call, until
+//! it stops. The capture streams are followed through the pipewire registry.
+
+use std::thread;
+
+use color_eyre::Result;
+use log::{debug, error, warn};
+use smithay_client_toolkit::reexports::calloop::{channel, LoopHandle};
+
+use crate::{pipewire, Passata};
+
+/// Start following the use of the microphone
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg(in_use) => state.mic_changed(in_use),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = pipewire::follow_capture(sender) {
+            warn!("stopped following the microphone: {err}");
+        }
+    });
+    Ok(())
+}
+
+impl Passata {
+    fn mic_changed(&mut self, in_use: bool) {
+        debug!("microphone in use: {in_use}");
+        self.mic_in_use = in_use;
+        if let Err(err) = self.check_deferred_break() {
+            error!("starting the deferred break: {err:?}");
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Expose the timer as a MPRIS media player, so that bars and OSDs that already display the
+//! media progress can show the current phase without any specific support. What MPRIS has no
+//! place for is on the `org.passata.Progress` interface of the same object.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use zbus::{
+    block_on,
+    blocking::{connection, Connection},
+    interface,
+    zvariant::{ObjectPath, Value},
+};
+
+use crate::status::Status;
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.passata";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+pub struct Mpris {
+    connection: Connection,
+}
+
+impl Mpris {
+    pub fn new(status: Status) -> zbus::Result<Self> {
+        let connection = connection::Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, Root)?
+            .serve_at(
+                OBJECT_PATH,
+                Progress {
+                    percent: status.progress(),
+                },
+            )?
+            .serve_at(OBJECT_PATH, Player { status })?
+            .build()?;
+        Ok(Self { connection })
+    }
+
+    pub fn update(&self, status: Status) -> zbus::Result<()> {
+        let object_server = self.connection.object_server();
+        let iface_ref = object_server.interface::<_, Progress>(OBJECT_PATH)?;
+        let mut progress = iface_ref.get_mut();
+        let percent = status.progress();
+        if progress.percent != percent {
+            progress.percent = percent;
+            block_on(progress.percent_changed(iface_ref.signal_context()))?;
+        }
+        drop(progress);
+        let iface_ref = object_server.interface::<_, Player>(OBJECT_PATH)?;
+        let mut player = iface_ref.get_mut();
+        player.status = status;
+        block_on(player.playback_status_changed(iface_ref.signal_context()))?;
+        block_on(player.metadata_changed(iface_ref.signal_context()))
+    }
+}
+
+struct Root;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "passata"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct Progress {
+    percent: u32,
+}
+
+#[interface(name = "org.passata.Progress")]
+impl Progress {
+    /// Share of the phase done, from 0 to 100
+    #[zbus(property)]
+    fn percent(&self) -> u32 {
+        self.percent
+    }
+}
+
+struct Player {
+    status: Status,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {}
+
+    fn previous(&self) {}
+
+    fn pause(&self) {}
+
+    fn play_pause(&self) {}
+
+    fn stop(&self) {}
+
+    fn play(&self) {}
+
+    fn seek(&self, _offset: i64) {}
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        if self.status.paused {
+            "Paused"
+        } else {
+            "Playing"
+        }
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, Value<'static>> {
+        let phase = self.status.phase;
+        // every phase is a different track, so that clients reset the progress
+        let track_id = format!(
+            "/org/passata/phase/{}/{}",
+            self.status.completed_pomodoros,
+            phase.name().replace(' ', "_").to_lowercase()
+        );
+        // e.g. "Short break at 14:05"
+        let upcoming = self
+            .status
+            .upcoming
+            .iter()
+            .map(|upcoming| {
+                format!(
+                    "{} at {}",
+                    upcoming.name(),
+                    DateTime::<Local>::from(upcoming.at).format("%H:%M")
+                )
+            })
+            .collect::<Vec<_>>();
+        HashMap::from([
+            (
+                "mpris:trackid".to_owned(),
+                Value::from(ObjectPath::from_string_unchecked(track_id)),
+            ),
+            (
+                "mpris:length".to_owned(),
+                Value::from(self.status.length.as_micros() as i64),
+            ),
+            ("xesam:title".to_owned(), Value::from(phase.name())),
+            ("xesam:artist".to_owned(), Value::from(vec!["passata"])),
+            ("passata:upcoming".to_owned(), Value::from(upcoming)),
+        ])
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.status.elapsed().as_micros() as i64
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        false
+    }
+}
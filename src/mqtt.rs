@@ -0,0 +1,243 @@
+//! Publisher of the timer state to an MQTT broker, with the Home Assistant discovery metadata
+//! so that the sensors show up by themselves. Only the few packets needed to publish are
+//! implemented (MQTT 3.1.1, QoS 0), in a thread that reconnects when the broker goes away.
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use color_eyre::{eyre::bail, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{stats, status::Status};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MqttConfig {
+    /// Address of the broker, as host:port
+    pub broker: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Prefix of the state topics, also identifying the device in Home Assistant
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    #[serde(default = "default_discovery_prefix")]
+    pub discovery_prefix: String,
+}
+
+fn default_topic() -> String {
+    "passata".to_owned()
+}
+
+fn default_discovery_prefix() -> String {
+    "homeassistant".to_owned()
+}
+
+/// The broker drops the connection after one and a half keep alive without packets
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+/// How often the time left is refreshed, pinging the broker too
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Sensors announced to Home Assistant: object id, name and extra discovery fields
+const SENSORS: [(&str, &str, &str); 4] = [
+    ("phase", "Phase", r#"{"icon": "mdi:timer-outline"}"#),
+    (
+        "remaining",
+        "Time left",
+        r#"{"device_class": "duration", "unit_of_measurement": "s"}"#,
+    ),
+    (
+        "completed_today",
+        "Pomodoros today",
+        r#"{"state_class": "total_increasing", "icon": "mdi:fruit-cherries"}"#,
+    ),
+    ("paused", "Paused", r#"{"icon": "mdi:pause"}"#),
+];
+
+pub struct Mqtt {
+    sender: Sender<Status>,
+}
+
+impl Mqtt {
+    /// Start publishing to the broker, beginning with `status`; the completed pomodoros are
+    /// counted from the history at `history`
+    pub fn new(config: MqttConfig, history: PathBuf, status: Status) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut latest = status;
+            loop {
+                if let Err(err) = publish(&config, &history, &receiver, &mut latest) {
+                    warn!("publishing to the mqtt broker {}: {err:?}", config.broker);
+                }
+                match receiver.recv_timeout(RECONNECT_DELAY) {
+                    Ok(status) => latest = status,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // the daemon is gone
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    pub fn update(&self, status: Status) -> Result<()> {
+        self.sender.send(status)?;
+        Ok(())
+    }
+}
+
+/// Connect to the broker and publish every status received, until the connection fails
+fn publish(
+    config: &MqttConfig,
+    history: &Path,
+    receiver: &Receiver<Status>,
+    latest: &mut Status,
+) -> Result<()> {
+    let mut stream = TcpStream::connect(&config.broker)?;
+    stream.set_read_timeout(Some(REFRESH_INTERVAL))?;
+    let availability = format!("{}/availability", config.topic);
+    connect(&mut stream, config, &availability)?;
+    debug!("connected to the mqtt broker {}", config.broker);
+    for (object, name, extra) in SENSORS {
+        let mut discovery: serde_json::Value = serde_json::from_str(extra)?;
+        discovery["name"] = json!(name);
+        discovery["unique_id"] = json!(format!("{}_{object}", config.topic));
+        discovery["state_topic"] = json!(format!("{}/{object}", config.topic));
+        discovery["availability_topic"] = json!(availability);
+        discovery["device"] = json!({ "identifiers": [config.topic], "name": config.topic });
+        let topic = format!(
+            "{}/sensor/{}/{object}/config",
+            config.discovery_prefix, config.topic
+        );
+        publish_packet(&mut stream, &topic, discovery.to_string().as_bytes())?;
+    }
+    publish_packet(&mut stream, &availability, b"online")?;
+    publish_status(&mut stream, config, history, latest)?;
+    loop {
+        match receiver.recv_timeout(REFRESH_INTERVAL) {
+            Ok(status) => *latest = status,
+            Err(RecvTimeoutError::Timeout) => {
+                // keep the connection alive, the broker answers with a PINGRESP
+                stream.write_all(&[0xc0, 0x00])?;
+                let mut response = [0; 2];
+                stream.read_exact(&mut response)?;
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                stream.write_all(&[0xe0, 0x00])?;
+                return Ok(());
+            }
+        }
+        publish_status(&mut stream, config, history, latest)?;
+    }
+}
+
+fn publish_status(
+    stream: &mut TcpStream,
+    config: &MqttConfig,
+    history: &Path,
+    status: &Status,
+) -> Result<()> {
+    let completed_today = stats::completed_today(history).unwrap_or_else(|err| {
+        warn!("counting the pomodoros of today: {err:?}");
+        0
+    });
+    for (object, value) in [
+        (
+            "phase",
+            status
+                .break_type
+                .as_deref()
+                .unwrap_or(status.phase.name())
+                .to_owned(),
+        ),
+        ("remaining", status.remaining().as_secs().to_string()),
+        ("completed_today", completed_today.to_string()),
+        (
+            "paused",
+            if status.paused { "on" } else { "off" }.to_owned(),
+        ),
+    ] {
+        publish_packet(
+            stream,
+            &format!("{}/{object}", config.topic),
+            value.as_bytes(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Send CONNECT, with the availability topic set to offline as last will, and wait for
+/// CONNACK
+fn connect(stream: &mut TcpStream, config: &MqttConfig, availability: &str) -> Result<()> {
+    let mut packet = Vec::new();
+    put_bytes(&mut packet, b"MQTT");
+    // protocol level 4 (3.1.1)
+    packet.push(4);
+    // clean session, retained will with QoS 0
+    let mut flags = 0x02 | 0x04 | 0x20;
+    if config.username.is_some() {
+        flags |= 0x80;
+    }
+    if config.password.is_some() {
+        flags |= 0x40;
+    }
+    packet.push(flags);
+    packet.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+    put_bytes(&mut packet, config.topic.as_bytes());
+    put_bytes(&mut packet, availability.as_bytes());
+    put_bytes(&mut packet, b"offline");
+    for field in [&config.username, &config.password].into_iter().flatten() {
+        put_bytes(&mut packet, field.as_bytes());
+    }
+    write_packet(stream, 0x10, &packet)?;
+    let mut connack = [0; 4];
+    stream.read_exact(&mut connack)?;
+    if connack[0] != 0x20 {
+        bail!("unexpected packet {:#x} instead of CONNACK", connack[0]);
+    }
+    if connack[3] != 0 {
+        bail!("the broker refused the connection with code {}", connack[3]);
+    }
+    Ok(())
+}
+
+/// Send a retained PUBLISH with QoS 0
+fn publish_packet(stream: &mut TcpStream, topic: &str, payload: &[u8]) -> io::Result<()> {
+    let mut packet = Vec::new();
+    put_bytes(&mut packet, topic.as_bytes());
+    packet.extend_from_slice(payload);
+    write_packet(stream, 0x31, &packet)
+}
+
+/// Append `bytes` prefixed by their length, as MQTT encodes strings
+fn put_bytes(packet: &mut Vec<u8>, bytes: &[u8]) {
+    packet.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    packet.extend_from_slice(bytes);
+}
+
+/// Write the fixed header, with the remaining length encoded 7 bits at a time, and `body`
+fn write_packet(stream: &mut TcpStream, header: u8, body: &[u8]) -> io::Result<()> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    stream.write_all(&packet)
+}
@@ -0,0 +1,114 @@
+//! Follow the Wi-Fi network NetworkManager is connected to, so that a profile can be chosen
+//! for each location.
+
+use std::thread;
+
+use color_eyre::Result;
+use log::{debug, warn};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+use zbus::{blocking::Connection, proxy, zvariant::OwnedObjectPath};
+
+use crate::Passata;
+
+const WIRELESS_CONNECTION: &str = "802-11-wireless";
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager",
+    gen_async = false
+)]
+trait NetworkManager {
+    #[zbus(property)]
+    fn primary_connection(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager",
+    gen_async = false
+)]
+trait ActiveConnection {
+    #[zbus(property, name = "Type")]
+    fn connection_type(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn specific_object(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.AccessPoint",
+    default_service = "org.freedesktop.NetworkManager",
+    gen_async = false
+)]
+trait AccessPoint {
+    #[zbus(property)]
+    fn ssid(&self) -> zbus::Result<Vec<u8>>;
+}
+
+/// Start following the SSID of the primary connection
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let connection = Connection::system()?;
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg(ssid) => state.network_changed(ssid),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = follow_primary_connection(&connection, sender) {
+            warn!("stopped following the network: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+fn follow_primary_connection(
+    connection: &Connection,
+    sender: Sender<Option<String>>,
+) -> Result<()> {
+    let network_manager = NetworkManagerProxy::new(connection)?;
+    sender.send(ssid(connection, &network_manager.primary_connection()?))?;
+    for change in network_manager.receive_primary_connection_changed() {
+        sender.send(ssid(connection, &change.get()?))?;
+    }
+    Ok(())
+}
+
+/// SSID of the active connection at `path`, if it is a Wi-Fi one
+fn ssid(connection: &Connection, path: &OwnedObjectPath) -> Option<String> {
+    // "/" means that there is no connection
+    if path.as_str() == "/" {
+        return None;
+    }
+    let res = (|| -> zbus::Result<Option<String>> {
+        let active = ActiveConnectionProxy::builder(connection)
+            .path(path)?
+            .build()?;
+        if active.connection_type()? != WIRELESS_CONNECTION {
+            return Ok(None);
+        }
+        let access_point = AccessPointProxy::builder(connection)
+            .path(active.specific_object()?)?
+            .build()?;
+        Ok(Some(
+            String::from_utf8_lossy(&access_point.ssid()?).into_owned(),
+        ))
+    })();
+    res.unwrap_or_else(|err| {
+        debug!("reading the SSID of {path:?}: {err}");
+        None
+    })
+}
+
+impl Passata {
+    fn network_changed(&mut self, ssid: Option<String>) {
+        debug!("connected to the network {ssid:?}");
+        let profile = ssid.and_then(|ssid| self.config.network_profiles.get(&ssid).cloned());
+        self.set_profile(profile);
+    }
+}
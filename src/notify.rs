@@ -0,0 +1,211 @@
+//! Ways of bringing a notification to the user, tried in the configured order until one of
+//! them works, so that a missing notification daemon does not leave the breaks unnoticed.
+
+use std::{
+    io::{self, Write},
+    thread,
+};
+
+use log::{error, warn};
+use notify_rust::{Hint, Notification};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::NotifyError, escalation::TONE_DURATION, events::NotificationAction, pipewire,
+    timer::Phase, Passata,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotifierKind {
+    /// Desktop notification, through the notification daemon
+    Desktop,
+    /// Line on the standard output, with the terminal bell
+    Terminal,
+    /// Fullscreen overlay, for the breaks only
+    Overlay,
+    /// Tone played through pipewire
+    Sound,
+}
+
+/// What happens to the desktop notification of the previous phase when a new one is shown
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationMode {
+    /// It stays, next to the new one
+    #[default]
+    Stack,
+    /// The new one takes its place
+    Replace,
+}
+
+pub fn default_notifiers() -> Vec<NotifierKind> {
+    vec![NotifierKind::Desktop, NotifierKind::Terminal]
+}
+
+/// Ask the notification daemon to play `sound` with `notification`, a file when it is a path
+/// and a name from the XDG sound theme otherwise
+pub fn set_sound(notification: &mut Notification, sound: &str) {
+    if sound.contains('/') {
+        notification.hint(Hint::SoundFile(sound.to_owned()));
+    } else {
+        notification.sound_name(sound);
+    }
+}
+
+pub trait Notifier {
+    /// Deliver `notification`, returning its id if the notification daemon shows it
+    fn notify(
+        &self,
+        passata: &mut Passata,
+        notification: &Notification,
+    ) -> Result<Option<u32>, NotifyError>;
+}
+
+struct Desktop;
+
+impl Notifier for Desktop {
+    fn notify(
+        &self,
+        passata: &mut Passata,
+        notification: &Notification,
+    ) -> Result<Option<u32>, NotifyError> {
+        let handle = notification.show()?;
+        let id = handle.id();
+        // a replaced notification keeps its id, and the thread already waiting on it
+        if notification.actions.is_empty() || !passata.action_waiters.insert(id) {
+            return Ok(Some(id));
+        }
+        let sender = passata.notification_actions.clone();
+        thread::spawn(move || {
+            handle.wait_for_action(|action| {
+                // the daemon is gone if this fails
+                if let Some(action) = NotificationAction::from_id(action) {
+                    let _ = sender.send(action);
+                }
+                let _ = sender.send(NotificationAction::Released { id });
+            })
+        });
+        Ok(Some(id))
+    }
+}
+
+struct Terminal;
+
+impl Notifier for Terminal {
+    fn notify(
+        &self,
+        _passata: &mut Passata,
+        notification: &Notification,
+    ) -> Result<Option<u32>, NotifyError> {
+        let mut stdout = io::stdout().lock();
+        writeln!(
+            stdout,
+            "\x07{}: {}",
+            notification.summary,
+            notification.body.replace('\n', " - ")
+        )?;
+        stdout.flush()?;
+        Ok(None)
+    }
+}
+
+struct Overlay;
+
+impl Notifier for Overlay {
+    fn notify(
+        &self,
+        passata: &mut Passata,
+        _notification: &Notification,
+    ) -> Result<Option<u32>, NotifyError> {
+        if passata.phase == Phase::Work {
+            return Err(NotifyError::Unavailable(
+                "the overlay only announces breaks",
+            ));
+        }
+        if passata.overlay.is_none() {
+            return Err(NotifyError::Unavailable(
+                "the overlay is not available in this session",
+            ));
+        }
+        if passata.enforcement_exempt() {
+            return Err(NotifyError::Unavailable(
+                "the focused app is an enforcement exception",
+            ));
+        }
+        passata.show_overlay();
+        Ok(None)
+    }
+}
+
+struct Sound;
+
+impl Notifier for Sound {
+    fn notify(
+        &self,
+        _passata: &mut Passata,
+        _notification: &Notification,
+    ) -> Result<Option<u32>, NotifyError> {
+        thread::spawn(|| {
+            if let Err(err) = pipewire::play_tone(TONE_DURATION) {
+                warn!("playing the notification tone: {err:?}");
+            }
+        });
+        Ok(None)
+    }
+}
+
+impl NotifierKind {
+    fn notifier(self) -> Box<dyn Notifier> {
+        match self {
+            NotifierKind::Desktop => Box::new(Desktop),
+            NotifierKind::Terminal => Box::new(Terminal),
+            NotifierKind::Overlay => Box::new(Overlay),
+            NotifierKind::Sound => Box::new(Sound),
+        }
+    }
+}
+
+impl Passata {
+    /// Deliver `notification` through the first notifier that works, returning its id if the
+    /// notification daemon shows it
+    pub fn notify(&mut self, notification: Notification) -> Option<u32> {
+        if self.in_quiet_hours() {
+            self.hold_notification(&notification);
+            return None;
+        }
+        for kind in self.config.notifiers.clone() {
+            if kind == NotifierKind::Desktop && !self.notification_server {
+                continue;
+            }
+            match kind.notifier().notify(self, &notification) {
+                Ok(id) => return id,
+                Err(err) => {
+                    match err.hint() {
+                        Some(hint) => warn!("notifying through {kind:?}: {err}, {hint}"),
+                        None => warn!("notifying through {kind:?}: {err}"),
+                    }
+                    self.health.notification_failures += 1;
+                }
+            }
+        }
+        error!("no notifier could show {:?}", notification.summary);
+        None
+    }
+
+    /// Deliver the notification of a phase starting or of its reminders, in place of the
+    /// previous one with notification-mode = "replace"
+    pub fn notify_phase(&mut self, mut notification: Notification) {
+        if self.config.notification_mode == NotificationMode::Stack {
+            self.notify(notification);
+            return;
+        }
+        if let Some(id) = self.phase_notification {
+            notification.id(id);
+        }
+        // a notification shown some other way leaves the previous one to be replaced
+        if let Some(id) = self.notify(notification) {
+            self.phase_notification = Some(id);
+        }
+    }
+}
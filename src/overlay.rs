@@ -0,0 +1,1052 @@
+//! Fullscreen layer-shell surfaces covering every output during a break, with the time left
+//! and a message drawn in the middle.
+
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::{
+    eyre::{bail, ensure, eyre, WrapErr},
+    Result,
+};
+use fontdue::{Font, FontSettings, Metrics};
+use image::{
+    imageops::{self, FilterType},
+    RgbaImage,
+};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::{
+    compositor::{CompositorHandler, CompositorState},
+    delegate_compositor, delegate_layer, delegate_output, delegate_shm,
+    output::{OutputHandler, OutputState},
+    reexports::{
+        calloop::{
+            channel::{self, Sender},
+            timer::{TimeoutAction, Timer},
+            LoopHandle, RegistrationToken,
+        },
+        client::{
+            globals::GlobalList,
+            protocol::{wl_output, wl_shm, wl_surface},
+            Connection, Dispatch, Proxy, QueueHandle,
+        },
+        protocols::wp::idle_inhibit::zv1::client::{
+            zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1,
+            zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+        },
+    },
+    shell::{
+        wlr_layer::{
+            Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+            LayerSurfaceConfigure,
+        },
+        WaylandSurface,
+    },
+    shm::{slot::SlotPool, Shm, ShmHandler},
+};
+
+use crate::Passata;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct OverlayConfig {
+    /// Color filling the screen, as #rrggbb or #rrggbbaa
+    pub color: Color,
+    /// Directory of images to cycle through, one for each break
+    pub images: Option<PathBuf>,
+    /// Names of the outputs to cover, e.g. "DP-1"; every output when empty. Only the main
+    /// overlay settings are used for this
+    pub outputs: Vec<String>,
+    /// Animated circle to breathe along with, drawn over the overlay
+    pub breathing: Option<BreathingConfig>,
+    /// Keep the screen from blanking or locking while the overlay is shown. The user does not
+    /// go idle in the meantime, so the Wayland idle backend cannot tell whether the break has
+    /// been taken
+    pub inhibit_idle: bool,
+    /// Show the time left in the break
+    pub countdown: bool,
+    /// Messages shown under the countdown, one for each break
+    pub messages: Vec<String>,
+    /// Font family as known to fontconfig, or path of a font file
+    pub font: String,
+    /// Height of the countdown in pixels, the messages are half as big
+    pub font_size: f32,
+    /// Color of the text, as #rrggbb or #rrggbbaa
+    pub text_color: Color,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            color: Color([0x1e, 0x1e, 0x2e, 0xe6]),
+            images: None,
+            outputs: Vec::new(),
+            breathing: None,
+            inhibit_idle: false,
+            countdown: true,
+            messages: vec![
+                "Look away from the screen".to_owned(),
+                "Stand up and stretch".to_owned(),
+                "Drink some water".to_owned(),
+            ],
+            font: "sans-serif".to_owned(),
+            font_size: 96.0,
+            text_color: Color([0xcd, 0xd6, 0xf4, 0xff]),
+        }
+    }
+}
+
+/// What is drawn on the overlay of the current break, besides the images and the breathing guide
+pub struct OverlayStyle {
+    pub color: Color,
+    /// Time left in the break, when the countdown is shown
+    pub countdown: Option<Duration>,
+    pub font: String,
+    pub font_size: f32,
+    pub text_color: Color,
+}
+
+/// Rhythm of the breathing guide: the circle grows while inhaling, stays open while holding the
+/// breath, shrinks while exhaling and stays closed while resting
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct BreathingConfig {
+    #[serde(with = "humantime_serde")]
+    pub inhale: Duration,
+    #[serde(with = "humantime_serde")]
+    pub hold: Duration,
+    #[serde(with = "humantime_serde")]
+    pub exhale: Duration,
+    #[serde(with = "humantime_serde")]
+    pub rest: Duration,
+    /// Color of the circle, as #rrggbb or #rrggbbaa
+    pub color: Color,
+}
+
+impl Default for BreathingConfig {
+    fn default() -> Self {
+        Self {
+            inhale: Duration::from_secs(4),
+            hold: Duration::from_secs(2),
+            exhale: Duration::from_secs(6),
+            rest: Duration::ZERO,
+            color: Color([0xcd, 0xd6, 0xf4, 0x80]),
+        }
+    }
+}
+
+impl BreathingConfig {
+    /// How open the circle is after `elapsed` of breathing, from 0 to 1
+    fn expansion(&self, elapsed: Duration) -> f32 {
+        let cycle = self.inhale + self.hold + self.exhale + self.rest;
+        if cycle.is_zero() {
+            return 1.0;
+        }
+        // eased in and out, so that the turns of the breath are not abrupt
+        let ease = |t: Duration, length: Duration| {
+            (1.0 - (t.as_secs_f32() / length.as_secs_f32() * PI).cos()) / 2.0
+        };
+        let mut t = Duration::from_nanos((elapsed.as_nanos() % cycle.as_nanos()) as u64);
+        if t < self.inhale {
+            return ease(t, self.inhale);
+        }
+        t -= self.inhale;
+        if t < self.hold {
+            return 1.0;
+        }
+        t -= self.hold;
+        if t < self.exhale {
+            return 1.0 - ease(t, self.exhale);
+        }
+        0.0
+    }
+}
+
+/// RGBA color
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub [u8; 4]);
+
+impl Color {
+    /// Premultiplied ARGB8888 pixel, as expected by wl_shm
+    pub fn to_argb8888(self) -> [u8; 4] {
+        let [r, g, b, a] = self.0;
+        let premultiply = |c: u8| (c as u16 * a as u16 / 255) as u8;
+        u32::from_be_bytes([a, premultiply(r), premultiply(g), premultiply(b)]).to_le_bytes()
+    }
+
+    /// Premultiplied ARGB8888 pixel of `self` drawn over `background`
+    fn over(self, background: Color) -> [u8; 4] {
+        let [r, g, b, a] = self.0;
+        let [br, bg, bb, ba] = background.0;
+        let a = a as u16;
+        let blend = |c: u8, bc: u8| ((c as u16 * a + bc as u16 * (255 - a)) / 255) as u8;
+        Color([
+            blend(r, br),
+            blend(g, bg),
+            blend(b, bb),
+            (a + ba as u16 * (255 - a) / 255) as u8,
+        ])
+        .to_argb8888()
+    }
+}
+
+impl FromStr for Color {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let hex = s
+            .strip_prefix('#')
+            .ok_or_else(|| eyre!("color {s:?} must start with '#'"))?;
+        let mut color = [0, 0, 0, 0xff];
+        match hex.len() {
+            6 | 8 => {
+                for (i, c) in color.iter_mut().enumerate().take(hex.len() / 2) {
+                    *c = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                        .wrap_err_with(|| format!("invalid color {s:?}"))?;
+                }
+            }
+            _ => bail!("color {s:?} must be in the form #rrggbb or #rrggbbaa"),
+        }
+        Ok(Self(color))
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = color_eyre::Report;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.0;
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+/// Images from a directory, shown one for each break
+struct Slideshow {
+    dir: PathBuf,
+    next: usize,
+    /// Image of the current break, decoded once for all the outputs
+    image: Option<RgbaImage>,
+    /// The current image scaled to each output size, as wl_shm pixels
+    scaled: HashMap<(u32, u32), Vec<u8>>,
+}
+
+impl Slideshow {
+    fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            next: 0,
+            image: None,
+            scaled: HashMap::new(),
+        }
+    }
+
+    /// Load the next image of the directory, which is read again to pick up new images
+    fn advance(&mut self) {
+        self.image = None;
+        self.scaled.clear();
+        let images = match self.list() {
+            Ok(images) => images,
+            Err(err) => {
+                warn!("reading the overlay images: {err:?}");
+                return;
+            }
+        };
+        if images.is_empty() {
+            warn!("no images found in {:?}", self.dir);
+            return;
+        }
+        let path = &images[self.next % images.len()];
+        self.next = self.next.wrapping_add(1);
+        debug!("showing {path:?} on the overlay");
+        match image::open(path) {
+            Ok(image) => self.image = Some(image.into_rgba8()),
+            Err(err) => warn!("loading the overlay image {path:?}: {err}"),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<PathBuf>> {
+        let mut images = fs::read_dir(&self.dir)
+            .wrap_err_with(|| format!("opening {:?}", self.dir))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        matches!(ext.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg")
+                    })
+            })
+            .collect::<Vec<_>>();
+        images.sort();
+        Ok(images)
+    }
+
+    fn pixels(&mut self, width: u32, height: u32, background: Color) -> Option<&[u8]> {
+        let image = self.image.as_ref()?;
+        Some(
+            self.scaled
+                .entry((width, height))
+                .or_insert_with(|| cover(image, width, height, background)),
+        )
+    }
+}
+
+/// Blend the premultiplied `pixel` over `dst`, with `coverage` from 0 to 1
+pub fn blend(dst: &mut [u8], pixel: [u8; 4], coverage: f32) {
+    let alpha = pixel[3] as f32 * coverage / 255.0;
+    // both are premultiplied, the background only has to be dimmed
+    for (dst, src) in dst.iter_mut().zip(pixel) {
+        *dst = (src as f32 * coverage + *dst as f32 * (1.0 - alpha)) as u8;
+    }
+}
+
+/// Blend a circle of `color` centered on the canvas, anti-aliasing its border
+fn draw_circle(canvas: &mut [u8], width: u32, height: u32, radius: f32, color: Color) {
+    let pixel = color.to_argb8888();
+    let (center_x, center_y) = (width as f32 / 2.0, height as f32 / 2.0);
+    let top = (center_y - radius - 1.0).max(0.0) as u32;
+    let bottom = ((center_y + radius + 1.0) as u32).min(height);
+    let left = (center_x - radius - 1.0).max(0.0) as u32;
+    let right = ((center_x + radius + 1.0) as u32).min(width);
+    for y in top..bottom {
+        for x in left..right {
+            let distance = (x as f32 + 0.5 - center_x).hypot(y as f32 + 0.5 - center_y);
+            let coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+            if coverage == 0.0 {
+                continue;
+            }
+            let i = (y * width + x) as usize * 4;
+            blend(&mut canvas[i..i + 4], pixel, coverage);
+        }
+    }
+}
+
+/// Font of the overlay text, with the glyphs rasterized so far
+struct LoadedFont {
+    font: Font,
+    /// Glyphs by character and size, the countdown redraws the same few every second
+    glyphs: HashMap<(char, u32), (Metrics, Vec<u8>)>,
+}
+
+impl LoadedFont {
+    fn new(font: Font) -> Self {
+        Self {
+            font,
+            glyphs: HashMap::new(),
+        }
+    }
+
+    fn glyph(&mut self, c: char, size: f32) -> &(Metrics, Vec<u8>) {
+        self.glyphs
+            .entry((c, size.to_bits()))
+            .or_insert_with(|| self.font.rasterize(c, size))
+    }
+}
+
+/// Fonts of the overlay by name, loaded in the background the first time they are asked for, as
+/// fontconfig can be slow
+struct Fonts {
+    /// None for the fonts still loading or that failed to load
+    loaded: HashMap<String, Option<LoadedFont>>,
+    /// Sends the fonts loaded in the background back to the event loop
+    sender: Sender<(String, Option<Font>)>,
+}
+
+impl Fonts {
+    fn get(&mut self, name: &str) -> Option<&mut LoadedFont> {
+        if !self.loaded.contains_key(name) {
+            self.loaded.insert(name.to_owned(), None);
+            let name = name.to_owned();
+            let sender = self.sender.clone();
+            thread::spawn(move || {
+                let font = load_font(&name)
+                    .inspect_err(|err| warn!("the overlay text will not be shown: {err:?}"))
+                    .ok();
+                // the daemon is gone if this fails
+                let _ = sender.send((name, font));
+            });
+        }
+        self.loaded.get_mut(name)?.as_mut()
+    }
+}
+
+/// Blend a line of `text` in `color`, centered horizontally with its baseline at `baseline`
+#[allow(clippy::too_many_arguments)]
+fn draw_text(
+    canvas: &mut [u8],
+    width: u32,
+    height: u32,
+    font: &mut LoadedFont,
+    text: &str,
+    size: f32,
+    baseline: f32,
+    color: Color,
+) {
+    let pixel = color.to_argb8888();
+    let line_width: f32 = text
+        .chars()
+        .map(|c| font.glyph(c, size).0.advance_width)
+        .sum();
+    let mut pen = (width as f32 - line_width) / 2.0;
+    for c in text.chars() {
+        let (metrics, bitmap) = font.glyph(c, size);
+        // ymin is the offset of the bottom of the glyph above the baseline
+        let left = (pen + metrics.xmin as f32).round() as i64;
+        let top = (baseline - metrics.height as f32 - metrics.ymin as f32).round() as i64;
+        for (row, coverages) in bitmap.chunks_exact(metrics.width.max(1)).enumerate() {
+            let y = top + row as i64;
+            if !(0..height as i64).contains(&y) {
+                continue;
+            }
+            for (column, &coverage) in coverages.iter().enumerate() {
+                let x = left + column as i64;
+                if coverage == 0 || !(0..width as i64).contains(&x) {
+                    continue;
+                }
+                let i = (y as usize * width as usize + x as usize) * 4;
+                blend(&mut canvas[i..i + 4], pixel, coverage as f32 / 255.0);
+            }
+        }
+        pen += metrics.advance_width;
+    }
+}
+
+/// Load the font file at `name`, or the one fontconfig picks for the family `name`
+fn load_font(name: &str) -> Result<Font> {
+    let path = if Path::new(name).is_file() {
+        PathBuf::from(name)
+    } else {
+        let output = Command::new("fc-match")
+            .args(["--format=%{file}", name])
+            .output()
+            .wrap_err("running fc-match")?;
+        ensure!(
+            output.status.success() && !output.stdout.is_empty(),
+            "fontconfig has no font for {name:?}"
+        );
+        PathBuf::from(String::from_utf8(output.stdout)?)
+    };
+    let data = fs::read(&path).wrap_err_with(|| format!("reading the font {path:?}"))?;
+    Font::from_bytes(data, FontSettings::default())
+        .map_err(|err| eyre!("loading the font {path:?}: {err}"))
+}
+
+/// Scale `image` to fill `width`x`height`, cropping the sides that do not fit
+fn cover(image: &RgbaImage, width: u32, height: u32, background: Color) -> Vec<u8> {
+    let (image_width, image_height) = image.dimensions();
+    let (crop_width, crop_height) =
+        if image_width as u64 * height as u64 > image_height as u64 * width as u64 {
+            let crop_width = image_height as u64 * width as u64 / height as u64;
+            (crop_width as u32, image_height)
+        } else {
+            let crop_height = image_width as u64 * height as u64 / width as u64;
+            (image_width, crop_height as u32)
+        };
+    let cropped = imageops::crop_imm(
+        image,
+        (image_width - crop_width) / 2,
+        (image_height - crop_height) / 2,
+        crop_width.max(1),
+        crop_height.max(1),
+    );
+    imageops::resize(&cropped, width, height, FilterType::Triangle)
+        .pixels()
+        .flat_map(|pixel| Color(pixel.0).over(background))
+        .collect()
+}
+
+struct OverlaySurface {
+    output: wl_output::WlOutput,
+    layer: LayerSurface,
+    /// Size given by the last configure
+    size: Option<(u32, u32)>,
+    /// Keeps the outputs on while the surface is shown
+    idle_inhibitor: Option<ZwpIdleInhibitorV1>,
+    /// A frame of the breathing guide has been requested, and not drawn yet
+    frame_pending: bool,
+}
+
+impl Drop for OverlaySurface {
+    fn drop(&mut self) {
+        if let Some(idle_inhibitor) = &self.idle_inhibitor {
+            idle_inhibitor.destroy();
+        }
+    }
+}
+
+pub struct Overlay {
+    compositor: CompositorState,
+    layer_shell: LayerShell,
+    /// Not every compositor supports inhibiting the idle
+    idle_inhibit_manager: Option<ZwpIdleInhibitManagerV1>,
+    shm: Shm,
+    pool: SlotPool,
+    surfaces: Vec<OverlaySurface>,
+    /// Slideshows of every images directory shown so far, so that each one keeps its position
+    slideshows: HashMap<PathBuf, Slideshow>,
+    /// Images directory of the overlay being shown
+    images: Option<PathBuf>,
+    /// Breathing guide of the overlay being shown, animated since the overlay appeared
+    breathing: Option<(BreathingConfig, Instant)>,
+    /// Whether the overlay being shown inhibits the idle
+    inhibit_idle: bool,
+    /// Position in the messages, advanced for each break
+    next_message: usize,
+    /// Message of the overlay being shown
+    message: Option<String>,
+    fonts: Fonts,
+    /// Redraws the countdown every second while the overlay is shown
+    tick_token: Option<RegistrationToken>,
+}
+
+impl Overlay {
+    /// Bind the globals of the overlay, and the idle inhibit manager if `idle_inhibit`
+    pub fn new(
+        globals: &GlobalList,
+        qh: &QueueHandle<Passata>,
+        loop_handle: &LoopHandle<'static, Passata>,
+        idle_inhibit: bool,
+    ) -> Result<Self> {
+        let shm = Shm::bind(globals, qh).wrap_err("wl_shm is not available")?;
+        let (sender, fonts) = channel::channel();
+        loop_handle
+            .insert_source(fonts, |event, _, state| {
+                if let channel::Event::Msg((name, font)) = event {
+                    state.overlay_font_loaded(name, font);
+                }
+            })
+            .map_err(|e| e.error)?;
+        // the pool grows as needed when the outputs are configured
+        let pool = SlotPool::new(256 * 256 * 4, &shm)?;
+        Ok(Self {
+            compositor: CompositorState::bind(globals, qh)
+                .wrap_err("wl_compositor is not available")?,
+            layer_shell: LayerShell::bind(globals, qh).wrap_err("layer shell is not available")?,
+            idle_inhibit_manager: idle_inhibit
+                .then(|| globals.bind(qh, 1..=1, ()).ok())
+                .flatten(),
+            shm,
+            pool,
+            surfaces: Vec::new(),
+            slideshows: HashMap::new(),
+            images: None,
+            breathing: None,
+            inhibit_idle: false,
+            next_message: 0,
+            message: None,
+            fonts: Fonts {
+                loaded: HashMap::new(),
+                sender,
+            },
+            tick_token: None,
+        })
+    }
+
+    pub fn is_shown(&self) -> bool {
+        !self.surfaces.is_empty()
+    }
+
+    pub fn compositor(&self) -> &CompositorState {
+        &self.compositor
+    }
+
+    /// Create a layer surface for the other surfaces of passata, like the widget
+    pub fn create_layer(
+        &self,
+        qh: &QueueHandle<Passata>,
+        layer: Layer,
+        namespace: &str,
+        output: Option<&wl_output::WlOutput>,
+    ) -> LayerSurface {
+        let surface = self.compositor.create_surface(qh);
+        self.layer_shell
+            .create_layer_surface(qh, surface, layer, Some(namespace), output)
+    }
+
+    /// Draw a new buffer of `width` by `height` with `paint` and show it on `layer`
+    pub fn paint(
+        &mut self,
+        layer: &LayerSurface,
+        width: u32,
+        height: u32,
+        paint: impl FnOnce(&mut [u8]),
+    ) -> Result<()> {
+        let (buffer, canvas) = self.pool.create_buffer(
+            width as i32,
+            height as i32,
+            width as i32 * 4,
+            wl_shm::Format::Argb8888,
+        )?;
+        paint(canvas);
+        layer
+            .wl_surface()
+            .damage_buffer(0, 0, width as i32, height as i32);
+        buffer.attach_to(layer.wl_surface())?;
+        layer.commit();
+        Ok(())
+    }
+
+    /// Cover every output with the overlay, showing the next image from `images`, the next
+    /// message from `messages` and the breathing guide if given, and keeping the outputs on if
+    /// `inhibit_idle`
+    pub fn show(
+        &mut self,
+        outputs: impl Iterator<Item = wl_output::WlOutput>,
+        qh: &QueueHandle<Passata>,
+        images: Option<&Path>,
+        messages: &[String],
+        breathing: Option<BreathingConfig>,
+        inhibit_idle: bool,
+    ) {
+        if !self.is_shown() {
+            self.message = (!messages.is_empty())
+                .then(|| messages[self.next_message % messages.len()].clone());
+            self.next_message = self.next_message.wrapping_add(1);
+            self.images = images.map(Path::to_path_buf);
+            self.breathing = breathing.map(|breathing| (breathing, Instant::now()));
+            self.inhibit_idle = inhibit_idle;
+            if inhibit_idle && self.idle_inhibit_manager.is_none() {
+                warn!("the compositor does not support inhibiting the idle");
+            }
+            if let Some(dir) = &self.images {
+                self.slideshows
+                    .entry(dir.clone())
+                    .or_insert_with(|| Slideshow::new(dir.clone()))
+                    .advance();
+            }
+        }
+        for output in outputs {
+            self.add_output(output, qh);
+        }
+    }
+
+    pub fn hide(&mut self) {
+        // dropping the layer surfaces destroys them
+        self.surfaces.clear();
+    }
+
+    fn add_output(&mut self, output: wl_output::WlOutput, qh: &QueueHandle<Passata>) {
+        if self.surfaces.iter().any(|surface| surface.output == output) {
+            return;
+        }
+        let surface = self.compositor.create_surface(qh);
+        let layer = self.layer_shell.create_layer_surface(
+            qh,
+            surface,
+            Layer::Overlay,
+            Some("passata"),
+            Some(&output),
+        );
+        layer.set_anchor(Anchor::all());
+        layer.set_exclusive_zone(-1);
+        layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+        // the first commit without a buffer asks the compositor for a configure
+        layer.commit();
+        let idle_inhibitor = self
+            .idle_inhibit_manager
+            .as_ref()
+            .filter(|_| self.inhibit_idle)
+            .map(|manager| manager.create_inhibitor(layer.wl_surface(), qh, ()));
+        self.surfaces.push(OverlaySurface {
+            output,
+            layer,
+            size: None,
+            idle_inhibitor,
+            frame_pending: false,
+        });
+    }
+
+    fn remove_output(&mut self, output: &wl_output::WlOutput) {
+        self.surfaces.retain(|surface| &surface.output != output);
+    }
+
+    /// Draw the surface at `index` in `surfaces`, once it has been configured
+    fn draw(
+        &mut self,
+        index: usize,
+        style: &OverlayStyle,
+        qh: &QueueHandle<Passata>,
+    ) -> Result<()> {
+        let surface = &mut self.surfaces[index];
+        let Some((width, height)) = surface.size else {
+            return Ok(());
+        };
+        let layer = surface.layer.clone();
+        // keep animating until the overlay is hidden, with a single frame requested at a time
+        if self.breathing.is_some() && !surface.frame_pending {
+            layer.wl_surface().frame(qh, layer.wl_surface().clone());
+            surface.frame_pending = true;
+        }
+        let color = style.color;
+        let stride = width as i32 * 4;
+        let (buffer, canvas) = self.pool.create_buffer(
+            width as i32,
+            height as i32,
+            stride,
+            wl_shm::Format::Argb8888,
+        )?;
+        match self
+            .images
+            .as_ref()
+            .and_then(|dir| self.slideshows.get_mut(dir))
+            .and_then(|slideshow| slideshow.pixels(width, height, color))
+        {
+            Some(pixels) => canvas.copy_from_slice(pixels),
+            None => {
+                let pixel = color.to_argb8888();
+                canvas
+                    .chunks_exact_mut(4)
+                    .for_each(|chunk| chunk.copy_from_slice(&pixel));
+            }
+        }
+        if let Some((breathing, started)) = &self.breathing {
+            let size = width.min(height) as f32;
+            let radius = size * (0.1 + 0.2 * breathing.expansion(started.elapsed()));
+            draw_circle(canvas, width, height, radius, breathing.color);
+        }
+        let countdown = style.countdown.map(|remaining| {
+            let secs = remaining.as_secs();
+            format!("{:02}:{:02}", secs / 60, secs % 60)
+        });
+        let lines = [
+            countdown.map(|text| (text, style.font_size)),
+            self.message
+                .clone()
+                .map(|text| (text, style.font_size / 2.0)),
+        ];
+        if lines.iter().any(Option::is_some) {
+            let screen = width.min(height) as f32;
+            let block: f32 = lines.iter().flatten().map(|(_, size)| size * 1.2).sum();
+            // under the breathing guide fully open, or in the middle of the screen
+            let mut baseline = if self.breathing.is_some() {
+                height as f32 / 2.0 + screen * 0.3 + style.font_size * 0.25
+            } else {
+                (height as f32 - block) / 2.0
+            };
+            if let Some(font) = self.fonts.get(&style.font) {
+                for (text, size) in lines.iter().flatten() {
+                    baseline += size;
+                    draw_text(
+                        canvas,
+                        width,
+                        height,
+                        font,
+                        text,
+                        *size,
+                        baseline,
+                        style.text_color,
+                    );
+                    baseline += size * 0.2;
+                }
+            }
+        }
+        layer
+            .wl_surface()
+            .damage_buffer(0, 0, width as i32, height as i32);
+        buffer.attach_to(layer.wl_surface())?;
+        layer.commit();
+        Ok(())
+    }
+}
+
+impl Passata {
+    pub fn show_overlay(&mut self) {
+        if self.in_quiet_hours() || self.enforcement_exempt() {
+            return;
+        }
+        let images = self.overlay_config().images.clone();
+        let messages = self.overlay_config().messages.clone();
+        let countdown = self.overlay_config().countdown;
+        let breathing = self.overlay_config().breathing.clone();
+        let inhibit_idle = self.overlay_config().inhibit_idle;
+        let outputs = self
+            .wayland
+            .iter()
+            .flat_map(|wayland| wayland.output_state.outputs())
+            .filter(|output| self.overlay_covers(output))
+            .collect::<Vec<_>>();
+        let (Some(overlay), Some(wayland)) = (&mut self.overlay, &self.wayland) else {
+            warn!("the overlay is not available in this session");
+            return;
+        };
+        overlay.show(
+            outputs.into_iter(),
+            &wayland.qh,
+            images.as_deref(),
+            &messages,
+            breathing,
+            inhibit_idle,
+        );
+        if countdown && overlay.tick_token.is_none() {
+            let res = self.loop_handle.insert_source(
+                Timer::from_duration(Duration::from_secs(1)),
+                |_, _, state: &mut Passata| {
+                    state.redraw_overlay();
+                    TimeoutAction::ToDuration(Duration::from_secs(1))
+                },
+            );
+            match res {
+                Ok(token) => overlay.tick_token = Some(token),
+                Err(err) => warn!("the overlay countdown will not be updated: {err}"),
+            }
+        }
+    }
+
+    /// What the overlay of the current break draws
+    fn overlay_style(&self) -> OverlayStyle {
+        let config = self.overlay_config();
+        OverlayStyle {
+            color: config.color,
+            countdown: config.countdown.then(|| self.remaining()),
+            font: config.font.clone(),
+            font_size: config.font_size,
+            text_color: config.text_color,
+        }
+    }
+
+    /// Draw every surface of the overlay again, to update the countdown
+    fn redraw_overlay(&mut self) {
+        let style = self.overlay_style();
+        let (Some(overlay), Some(wayland)) = (&mut self.overlay, &self.wayland) else {
+            return;
+        };
+        for index in 0..overlay.surfaces.len() {
+            if let Err(err) = overlay.draw(index, &style, &wayland.qh) {
+                warn!("drawing the overlay: {err:?}");
+            }
+        }
+    }
+
+    /// The font `name` has been loaded in the background, draw the text with it
+    fn overlay_font_loaded(&mut self, name: String, font: Option<Font>) {
+        let Some(overlay) = &mut self.overlay else {
+            return;
+        };
+        overlay.fonts.loaded.insert(name, font.map(LoadedFont::new));
+        self.redraw_overlay();
+    }
+
+    /// Whether the overlay should be shown on `output`, matching it by name
+    fn overlay_covers(&self, output: &wl_output::WlOutput) -> bool {
+        let outputs = &self.config.overlay.outputs;
+        outputs.is_empty()
+            || self
+                .wayland
+                .as_ref()
+                .and_then(|wayland| wayland.output_state.info(output))
+                .and_then(|info| info.name)
+                .is_some_and(|name| outputs.contains(&name))
+    }
+
+    pub fn hide_overlay(&mut self) {
+        if let Some(overlay) = &mut self.overlay {
+            overlay.hide();
+            if let Some(token) = overlay.tick_token.take() {
+                self.loop_handle.remove(token);
+            }
+        }
+    }
+}
+
+impl CompositorHandler for Passata {
+    fn scale_factor_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_factor: i32,
+    ) {
+    }
+
+    fn transform_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_transform: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        _time: u32,
+    ) {
+        let style = self.overlay_style();
+        let Some(overlay) = &mut self.overlay else {
+            return;
+        };
+        // the overlay might have been hidden since the frame was requested
+        let Some(index) = overlay
+            .surfaces
+            .iter()
+            .position(|overlay_surface| overlay_surface.layer.wl_surface() == surface)
+        else {
+            return;
+        };
+        overlay.surfaces[index].frame_pending = false;
+        if let Err(err) = overlay.draw(index, &style, qh) {
+            warn!("drawing the overlay: {err:?}");
+        }
+    }
+
+    fn surface_enter(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+
+    fn surface_leave(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl OutputHandler for Passata {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.wayland_mut().output_state
+    }
+
+    fn new_output(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        self.gamma_output_added(output.clone());
+        if !self.overlay_covers(&output) {
+            return;
+        }
+        if let Some(overlay) = &mut self.overlay {
+            if overlay.is_shown() {
+                overlay.add_output(output, qh);
+            }
+        }
+    }
+
+    fn update_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+
+    fn output_destroyed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        self.gamma_output_removed(&output);
+        if let Some(overlay) = &mut self.overlay {
+            overlay.remove_output(&output);
+        }
+    }
+}
+
+impl LayerShellHandler for Passata {
+    fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, layer: &LayerSurface) {
+        if self.widget_closed(layer) {
+            return;
+        }
+        if let Some(overlay) = &mut self.overlay {
+            overlay.surfaces.retain(|surface| &surface.layer != layer);
+        }
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _serial: u32,
+    ) {
+        if self.widget_configured(layer, configure.new_size) {
+            return;
+        }
+        let style = self.overlay_style();
+        let Some(overlay) = &mut self.overlay else {
+            return;
+        };
+        let (width, height) = configure.new_size;
+        if width == 0 || height == 0 {
+            return;
+        }
+        let Some(index) = overlay
+            .surfaces
+            .iter()
+            .position(|surface| &surface.layer == layer)
+        else {
+            return;
+        };
+        overlay.surfaces[index].size = Some((width, height));
+        if let Err(err) = overlay.draw(index, &style, qh) {
+            warn!("drawing the overlay: {err:?}");
+        }
+    }
+}
+
+impl ShmHandler for Passata {
+    fn shm_state(&mut self) -> &mut Shm {
+        // wl_shm is only bound together with the overlay
+        &mut self
+            .overlay
+            .as_mut()
+            .expect("wl_shm events without the overlay")
+            .shm
+    }
+}
+
+impl Dispatch<ZwpIdleInhibitManagerV1, ()> for Passata {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpIdleInhibitManagerV1,
+        _event: <ZwpIdleInhibitManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwpIdleInhibitorV1, ()> for Passata {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpIdleInhibitorV1,
+        _event: <ZwpIdleInhibitorV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_compositor!(Passata);
+delegate_output!(Passata);
+delegate_shm!(Passata);
+delegate_layer!(Passata);
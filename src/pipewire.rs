@@ -5,19 +5,44 @@
 //!
 //! tut: https://docs.pipewire.org/page_tutorial4.html
 
+use color_eyre::{
+    eyre::{eyre, ContextCompat},
+    Result,
+};
 use pipewire as pw;
-use pw::{properties::properties, spa};
-use spa::pod::Pod;
+use pw::{
+    node::{Node, NodeChangeMask, NodeListener, NodeState},
+    properties::properties,
+    spa,
+    types::ObjectType,
+};
+use smithay_client_toolkit::reexports::calloop::channel::Sender;
+use spa::{pod::Pod, utils::dict::DictRef};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    time::Duration,
+};
 
 pub const DEFAULT_RATE: u32 = 44100;
 pub const DEFAULT_CHANNELS: u32 = 2;
 pub const DEFAULT_VOLUME: f64 = 0.7;
 pub const PI_2: f64 = std::f64::consts::PI + std::f64::consts::PI;
 pub const CHAN_SIZE: usize = std::mem::size_of::<i16>();
+/// Media class of the streams recording from a source, like the microphone
+const CAPTURE_CLASS: &str = "Stream/Input/Audio";
 
-pub fn play_tone() -> Result<(), pw::Error> {
+/// Play a tone for `duration`, blocking until it has finished
+pub fn play_tone(duration: Duration) -> Result<()> {
     pw::init();
     let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let weak_mainloop = mainloop.downgrade();
+    let timer = mainloop.loop_().add_timer(move |_| {
+        if let Some(mainloop) = weak_mainloop.upgrade() {
+            mainloop.quit();
+        }
+    });
     let context = pw::context::Context::new(&mainloop)?;
     let core = context.connect(None)?;
 
@@ -86,11 +111,11 @@ pub fn play_tone() -> Result<(), pw::Error> {
             properties: audio_info.into(),
         }),
     )
-    .unwrap()
+    .map_err(|err| eyre!("serializing the audio format: {err:?}"))?
     .0
     .into_inner();
 
-    let mut params = [Pod::from_bytes(&values).unwrap()];
+    let mut params = [Pod::from_bytes(&values).context("the audio format is not a valid pod")?];
 
     stream.connect(
         spa::utils::Direction::Output,
@@ -101,6 +126,113 @@ pub fn play_tone() -> Result<(), pw::Error> {
         &mut params,
     )?;
 
+    timer.update_timer(Some(duration), None).into_result()?;
+    mainloop.run();
+
+    Ok(())
+}
+
+/// Stream capturing audio, followed by `follow_capture`
+struct Capture {
+    // dropped before the node it listens to
+    _listener: NodeListener,
+    _node: Node,
+    /// Recording right now, not only open
+    running: bool,
+    /// Recording what is played, like a screen recorder or a level meter, not a source
+    monitor: bool,
+}
+
+/// Whether the stream with `props` records the monitor of a sink
+fn is_monitor(props: &DictRef) -> bool {
+    props.get("stream.monitor") == Some("true")
+        || props.get("stream.capture.sink") == Some("true")
+        || props
+            .get(*pw::keys::TARGET_OBJECT)
+            .is_some_and(|target| target.ends_with(".monitor"))
+}
+
+/// Follow the streams capturing audio, sending whether any is recording from a source each
+/// time it changes. Blocks for as long as pipewire is running
+pub fn follow_capture(sender: Sender<bool>) -> Result<(), pw::Error> {
+    pw::init();
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = Rc::new(core.get_registry()?);
+
+    let captures = Rc::new(RefCell::new(HashMap::<u32, Capture>::new()));
+    // send whether a capture is recording from a source, when it changed
+    let update = Rc::new({
+        let captures = captures.clone();
+        let in_use = Cell::new(false);
+        move || {
+            let recording = captures
+                .borrow()
+                .values()
+                .any(|capture| capture.running && !capture.monitor);
+            if in_use.replace(recording) != recording {
+                let _ = sender.send(recording);
+            }
+        }
+    });
+    let _listener = registry
+        .add_listener_local()
+        .global({
+            let registry = Rc::downgrade(&registry);
+            let captures = captures.clone();
+            let update = update.clone();
+            move |global| {
+                let props = global.props;
+                if global.type_ != ObjectType::Node
+                    || props.and_then(|props| props.get(*pw::keys::MEDIA_CLASS))
+                        != Some(CAPTURE_CLASS)
+                {
+                    return;
+                }
+                let Some(Ok(node)) = registry
+                    .upgrade()
+                    .map(|registry| registry.bind::<Node, _>(global))
+                else {
+                    return;
+                };
+                let id = global.id;
+                let listener =
+                    node.add_listener_local()
+                        .info({
+                            let captures = captures.clone();
+                            let update = update.clone();
+                            move |info| {
+                                if let Some(capture) = captures.borrow_mut().get_mut(&id) {
+                                    capture.running = matches!(info.state(), NodeState::Running);
+                                    if let Some(props) = info.props().filter(|_| {
+                                        info.change_mask().contains(NodeChangeMask::PROPS)
+                                    }) {
+                                        capture.monitor = is_monitor(props);
+                                    }
+                                }
+                                update();
+                            }
+                        })
+                        .register();
+                captures.borrow_mut().insert(
+                    id,
+                    Capture {
+                        _listener: listener,
+                        _node: node,
+                        running: false,
+                        monitor: props.is_some_and(is_monitor),
+                    },
+                );
+            }
+        })
+        .global_remove(move |id| {
+            let removed = captures.borrow_mut().remove(&id);
+            if removed.is_some() {
+                update();
+            }
+        })
+        .register();
     mainloop.run();
 
     Ok(())
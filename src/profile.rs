@@ -0,0 +1,199 @@
+use std::{str::FromStr, time::Duration};
+
+use chrono::{Datelike, Local, NaiveTime, TimeZone, Weekday};
+use color_eyre::{eyre::WrapErr, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::Passata;
+
+/// Timing overrides applied on top of the main settings
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    #[serde(rename = "interval", default, with = "humantime_serde")]
+    pub work_interval: Option<Duration>,
+    #[serde(default, with = "humantime_serde")]
+    pub short_break: Option<Duration>,
+    #[serde(default, with = "humantime_serde")]
+    pub long_break: Option<Duration>,
+    pub short_breaks_before_long_break: Option<u8>,
+}
+
+/// Timings resulting from the main settings and the overrides
+pub struct Timings {
+    pub work_interval: Duration,
+    pub short_break: Duration,
+    pub long_break: Option<Duration>,
+    pub short_breaks_before_long_break: Option<u8>,
+}
+
+/// Timing overrides applied to the work intervals starting in a range of the day
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TimeRangeProfile {
+    pub from: TimeOfDay,
+    /// End of the range, excluded; a range ending before it starts goes past midnight
+    pub to: TimeOfDay,
+    #[serde(flatten)]
+    pub timings: Profile,
+}
+
+impl TimeRangeProfile {
+    fn contains(&self, time: NaiveTime) -> bool {
+        in_range(self.from, self.to, time)
+    }
+}
+
+/// Whether `time` is between `from` and `to`, excluded; a range ending before it starts goes
+/// past midnight
+pub fn in_range(from: TimeOfDay, to: TimeOfDay, time: NaiveTime) -> bool {
+    if from.0 <= to.0 {
+        from.0 <= time && time < to.0
+    } else {
+        from.0 <= time || time < to.0
+    }
+}
+
+/// Time of the day, as HH:MM
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(pub NaiveTime);
+
+impl FromStr for TimeOfDay {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        NaiveTime::parse_from_str(s, "%H:%M")
+            .map(Self)
+            .wrap_err_with(|| format!("invalid time {s:?}, expected HH:MM"))
+    }
+}
+
+impl TimeOfDay {
+    /// Time until this time of the day is next reached on one of the days accepted by `on`,
+    /// today at the earliest
+    pub fn until_next(self, on: impl Fn(Weekday) -> bool) -> Duration {
+        let now = Local::now();
+        let mut day = now.date_naive();
+        loop {
+            // a time skipped by the DST change is looked for on the next day
+            if let Some(next) = Local
+                .from_local_datetime(&day.and_time(self.0))
+                .earliest()
+                .filter(|next| *next > now && on(day.weekday()))
+            {
+                return (next - now).to_std().unwrap_or_default();
+            }
+            day = day.succ_opt().unwrap();
+        }
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = color_eyre::Report;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.0.format("%H:%M").to_string()
+    }
+}
+
+impl Passata {
+    fn profile(&self) -> Option<&Profile> {
+        self.active_profile
+            .as_ref()
+            .and_then(|name| self.config.profiles.get(name))
+    }
+
+    /// Overrides of the timings, the time range of the day first and then the profile
+    fn overrides<'a>(&'a self, time_range: Option<&str>) -> impl Iterator<Item = &'a Profile> {
+        time_range
+            .and_then(|name| self.config.intervals.get(name))
+            .map(|range| &range.timings)
+            .into_iter()
+            .chain(self.profile())
+    }
+
+    /// Timings in effect with the time range of the day `time_range`
+    pub fn timings_for(&self, time_range: Option<&str>) -> Timings {
+        let overrides = || self.overrides(time_range);
+        let timings = self.algorithm_timings(Timings {
+            work_interval: overrides()
+                .find_map(|profile| profile.work_interval)
+                .unwrap_or(self.config.work_interval),
+            short_break: overrides()
+                .find_map(|profile| profile.short_break)
+                .unwrap_or(self.config.short_break),
+            long_break: overrides()
+                .find_map(|profile| profile.long_break)
+                .or(self.config.long_break),
+            short_breaks_before_long_break: overrides()
+                .find_map(|profile| profile.short_breaks_before_long_break)
+                .or(self.config.short_breaks_before_long_break),
+        });
+        self.battery_timings(timings)
+    }
+
+    /// Timings currently in effect
+    pub fn timings(&self) -> Timings {
+        self.timings_for(self.active_time_range.as_deref())
+    }
+
+    pub fn work_interval(&self) -> Duration {
+        self.timings().work_interval
+    }
+
+    pub fn short_break(&self) -> Duration {
+        self.timings().short_break
+    }
+
+    pub fn long_break(&self) -> Option<Duration> {
+        self.timings().long_break
+    }
+
+    pub fn short_breaks_before_long_break(&self) -> Option<u8> {
+        self.timings().short_breaks_before_long_break
+    }
+
+    /// Time range of the day whose overrides apply to a work interval starting at `time`; when
+    /// ranges overlap, the first by name wins
+    pub fn time_range_at(&self, time: NaiveTime) -> Option<&str> {
+        self.config
+            .intervals
+            .iter()
+            .filter(|(_, range)| range.contains(time))
+            .map(|(name, _)| name.as_str())
+            .min()
+    }
+
+    /// Pick the time range of the day whose overrides apply to the work interval starting now
+    pub fn select_time_range(&mut self) {
+        let name = self.time_range_at(Local::now().time()).map(str::to_owned);
+        if self.active_time_range == name {
+            return;
+        }
+        info!(
+            "switching to the intervals of {}",
+            name.as_deref().unwrap_or("the whole day")
+        );
+        self.active_time_range = name;
+    }
+
+    /// Switch to the profile `name`, taking effect from the next phase
+    pub fn set_profile(&mut self, name: Option<String>) {
+        if self.active_profile == name {
+            return;
+        }
+        info!(
+            "switching to the profile {}",
+            name.as_deref().unwrap_or("default")
+        );
+        self.active_profile = name;
+    }
+}
@@ -0,0 +1,96 @@
+//! Dialog closing the break, for the notification servers that drop the actions: the work
+//! interval waits until its button is clicked. The dialog is drawn by zenity, and closed when
+//! the work starts some other way.
+
+use std::process::{Child, Command};
+
+use color_eyre::Result;
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{command::watch_exit, events::NotificationAction, i18n::Language, Passata};
+
+/// How the end of the break is acknowledged when the work interval waits for the user
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BreakPrompt {
+    /// Through the action of the notification
+    #[default]
+    Notification,
+    /// Through a dialog that must be clicked, holding every work interval until then
+    Dialog,
+}
+
+fn spawn_dialog(language: Language) -> Result<Child> {
+    Ok(Command::new("zenity")
+        .args([
+            "--info".to_owned(),
+            "--title=passata".to_owned(),
+            format!("--text={}", language.format("break-over", &[])),
+            format!("--ok-label={}", language.format("start-working", &[])),
+            "--icon-name=alarm-symbolic".to_owned(),
+        ])
+        .spawn()?)
+}
+
+impl Passata {
+    /// Show the dialog starting the work interval when clicked, returning false if it could not
+    /// be shown
+    pub fn show_break_prompt(&mut self) -> bool {
+        self.dismiss_break_prompt();
+        // the notification standing in for it is held for the digest
+        if self.in_quiet_hours() {
+            return false;
+        }
+        let child = match spawn_dialog(self.language()) {
+            Ok(child) => child,
+            Err(err) => {
+                warn!("showing the break dialog: {err:?}");
+                return false;
+            }
+        };
+        watch_exit(
+            &child,
+            self.notification_actions.clone(),
+            NotificationAction::PromptExited { pid: child.id() },
+        );
+        self.break_prompt = Some(child);
+        true
+    }
+
+    /// Close the dialog if it is still shown
+    pub fn dismiss_break_prompt(&mut self) {
+        if let Some(mut child) = self.break_prompt.take() {
+            // it has exited already if this fails, it is reaped either way
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    /// The dialog `pid` exited, starting the work interval if its button was clicked
+    pub fn break_prompt_exited(&mut self, pid: u32) {
+        let Some(child) = self.break_prompt.as_mut().filter(|child| child.id() == pid) else {
+            // dismissed already
+            return;
+        };
+        let status = match child.try_wait() {
+            Ok(Some(status)) => status,
+            // still running
+            Ok(None) => return,
+            Err(err) => {
+                warn!("waiting for the break dialog: {err}");
+                self.break_prompt = None;
+                return;
+            }
+        };
+        self.break_prompt = None;
+        // killed or closed without clicking the button otherwise
+        if !status.success() {
+            debug!("the break dialog exited with {status}");
+            return;
+        }
+        if let Err(err) = self.start_work() {
+            error!("starting the work interval: {err:?}");
+        }
+    }
+}
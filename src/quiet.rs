@@ -0,0 +1,93 @@
+//! Quiet hours: between start and end the time is tracked as usual, but the breaks are quiet:
+//! no notification, overlay, dimming, lock, dialog or break command, and no escalation. What
+//! the notifications would have announced is kept for a digest, sent once the quiet hours are
+//! over.
+
+use std::{fmt::Write, mem, time::Duration};
+
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use log::debug;
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
+
+use crate::{
+    profile::{self, TimeOfDay},
+    Passata,
+};
+
+/// How often the wall clock is checked for the end of the quiet hours, that a timer set for
+/// it would miss across a suspend or a change of the clock
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct QuietHoursConfig {
+    pub start: Option<TimeOfDay>,
+    /// End of the quiet hours, excluded; before the start they go past midnight
+    pub end: Option<TimeOfDay>,
+}
+
+/// Held during the quiet hours for the digest: when, and what it announced
+pub type HeldNotification = (DateTime<Local>, String);
+
+/// Send the digest once the quiet hours are over
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    loop_handle
+        .insert_source(
+            Timer::from_duration(DIGEST_CHECK_INTERVAL),
+            |_, _, state: &mut Passata| {
+                if !state.in_quiet_hours() {
+                    state.send_quiet_digest();
+                }
+                TimeoutAction::ToDuration(DIGEST_CHECK_INTERVAL)
+            },
+        )
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+impl Passata {
+    pub fn in_quiet_hours(&self) -> bool {
+        let quiet_hours = &self.config.quiet_hours;
+        let (Some(start), Some(end)) = (quiet_hours.start, quiet_hours.end) else {
+            return false;
+        };
+        profile::in_range(start, end, Local::now().time())
+    }
+
+    /// Keep what `notification` announces for the digest
+    pub fn hold_notification(&mut self, notification: &Notification) {
+        debug!("quiet hours, holding {:?}", notification.summary);
+        // the reminders of the same break add nothing to the digest
+        if self
+            .quiet_digest
+            .last()
+            .is_some_and(|(_, summary)| *summary == notification.summary)
+        {
+            return;
+        }
+        self.quiet_digest
+            .push((Local::now(), notification.summary.clone()));
+    }
+
+    fn send_quiet_digest(&mut self) {
+        let held = mem::take(&mut self.quiet_digest);
+        if held.is_empty() {
+            return;
+        }
+        let mut body = String::new();
+        for (at, summary) in &held {
+            let _ = writeln!(body, "{} {summary}", at.format("%H:%M"));
+        }
+        let mut notification = Notification::new();
+        notification
+            .summary(&self.tr("during-quiet-hours"))
+            .body(body.trim_end());
+        self.notify(notification);
+    }
+}
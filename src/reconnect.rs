@@ -0,0 +1,103 @@
+//! Survive the compositor going away: when the wayland connection drops, the timer keeps
+//! running without the overlay, the dimming and the wayland idle notifications, while the
+//! connection is tried again with a growing delay. Once the session is back, the globals are
+//! bound again like at startup.
+
+use std::time::Duration;
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use log::{debug, info, warn};
+use smithay_client_toolkit::reexports::{
+    calloop::timer::{TimeoutAction, Timer},
+    calloop_wayland_source::WaylandSource,
+    client::{globals::registry_queue_init, Connection},
+};
+
+use crate::{capabilities::Capabilities, error::WaylandError, idle::IdleLevel, Passata, Wayland};
+
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(60);
+
+impl Passata {
+    /// Whether the wayland connection is dead, once the event loop failed
+    pub fn wayland_lost(&self) -> bool {
+        self.wayland
+            .as_ref()
+            .is_some_and(|wayland| wayland.conn.backend().last_error().is_some())
+    }
+
+    /// Drop everything bound to the dead connection and start connecting again
+    pub fn wayland_disconnected(&mut self) -> Result<()> {
+        let Some(wayland) = self.wayland.take() else {
+            return Ok(());
+        };
+        self.health.wayland_disconnections += 1;
+        if let Some(token) = wayland.token {
+            self.loop_handle.remove(token);
+        }
+        self.hide_widget();
+        self.overlay = None;
+        self.gamma = None;
+        self.work_apps = None;
+
+        let idle_notifications = wayland.idle_notifications;
+        let mut delay = FIRST_RETRY;
+        self.loop_handle
+            .insert_source(
+                Timer::from_duration(delay),
+                move |_, _, state: &mut Passata| match state.reconnect(idle_notifications) {
+                    Ok(()) => {
+                        info!("connected to the wayland compositor again");
+                        TimeoutAction::Drop
+                    }
+                    Err(err) => {
+                        debug!("connecting to the wayland compositor: {err:?}");
+                        delay = (delay * 2).min(MAX_RETRY);
+                        TimeoutAction::ToDuration(delay)
+                    }
+                },
+            )
+            .map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    fn reconnect(&mut self, idle_notifications: bool) -> Result<()> {
+        let conn = Connection::connect_to_env().map_err(WaylandError::from)?;
+        let (globals, mut event_queue) = registry_queue_init(&conn).map_err(WaylandError::from)?;
+        let qh = event_queue.handle();
+        self.wayland = Some(Wayland::new(conn.clone(), &globals, &qh));
+        // the names of the seats and outputs are only known after their first events
+        if let Err(err) = event_queue.roundtrip(self) {
+            self.wayland = None;
+            return Err(err).context("receiving the seats and outputs");
+        }
+        let token = match WaylandSource::new(conn, event_queue).insert(self.loop_handle.clone()) {
+            Ok(token) => token,
+            Err(err) => {
+                self.wayland = None;
+                return Err(eyre!(
+                    "inserting the wayland source into the event loop: {err}"
+                ));
+            }
+        };
+        if let Some(wayland) = &mut self.wayland {
+            wayland.token = Some(token);
+        }
+
+        let capabilities = Capabilities::detect(Some(&globals), &self.config.capabilities);
+        self.bind_wayland(&globals, &qh, &capabilities);
+        if idle_notifications {
+            if let Err(err) = self.bind_idle_notifications(&globals, &qh) {
+                warn!("idle will be ignored: {err:?}");
+            }
+            // the new notifications start with the user active, only telling when idle again
+            if self.idle {
+                self.idle_changed(&IdleLevel::Pause, false);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+//! Summary of the previous week from the history: pomodoros, focus time and how many breaks
+//! were actually taken. Printed by `passata report`, and sent as a notification every Monday
+//! morning when enabled.
+
+use std::{io::Write, path::Path};
+
+use chrono::{Days, Local, NaiveDate, Weekday};
+use color_eyre::Result;
+use log::{error, info};
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
+
+use crate::{
+    i18n::Language,
+    profile::TimeOfDay,
+    stats::{self, DailyAggregate},
+    Passata,
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ReportConfig {
+    /// Send the summary of the previous week every Monday
+    pub weekly: bool,
+    pub at: TimeOfDay,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            weekly: false,
+            at: "09:00".parse().unwrap(),
+        }
+    }
+}
+
+/// Monday and Sunday of the week before the current one
+fn last_week() -> (NaiveDate, NaiveDate) {
+    let today = Local::now().date_naive();
+    let monday = today.week(Weekday::Mon).first_day() - Days::new(7);
+    (monday, monday + Days::new(6))
+}
+
+/// Totals of the days from `from` to `to`, both included, from the history at `path` and its
+/// daily aggregates; the best streak is the longest of a single day
+fn totals(path: &Path, from: NaiveDate, to: NaiveDate) -> Result<DailyAggregate> {
+    let mut totals = DailyAggregate {
+        day: from,
+        ..Default::default()
+    };
+    for aggregate in stats::daily(path, |day| from <= day && day <= to)?.values() {
+        totals.merge(aggregate);
+    }
+    Ok(totals)
+}
+
+/// Summary of the previous week in `language`, one fact per line
+fn summary(path: &Path, language: Language) -> Result<Vec<String>> {
+    let (monday, sunday) = last_week();
+    let totals = totals(path, monday, sunday)?;
+    let mut lines = vec![language.format(
+        "report-pomodoros",
+        &[
+            ("completed", totals.completed.into()),
+            ("abandoned", totals.abandoned.into()),
+        ],
+    )];
+    let minutes = totals.worked.as_secs() / 60;
+    lines.push(language.format(
+        "report-focus",
+        &[
+            ("hours", (minutes / 60).into()),
+            ("minutes", format!("{:02}", minutes % 60).into()),
+        ],
+    ));
+    if totals.best_streak > 1 {
+        lines.push(language.format(
+            "report-best-streak",
+            &[("streak", totals.best_streak.into())],
+        ));
+    }
+    let breaks = totals.breaks_taken + totals.breaks_ignored;
+    if breaks > 0 {
+        lines.push(language.format(
+            "report-breaks",
+            &[
+                ("percent", (totals.breaks_taken * 100 / breaks).into()),
+                ("taken", totals.breaks_taken.into()),
+                ("breaks", breaks.into()),
+            ],
+        ));
+    }
+    Ok(lines)
+}
+
+/// Write the summary of the previous week from the history at `path` to `out`, in `language`
+pub fn print(path: &Path, language: Language, out: &mut impl Write) -> Result<()> {
+    let (monday, sunday) = last_week();
+    let week = language.format(
+        "report-week",
+        &[
+            ("monday", monday.to_string().into()),
+            ("sunday", sunday.to_string().into()),
+        ],
+    );
+    writeln!(out, "{week}")?;
+    for line in summary(path, language)? {
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// Send the summary every Monday at `at`
+pub fn schedule(loop_handle: &LoopHandle<'static, Passata>, at: TimeOfDay) -> Result<()> {
+    let next = move || at.until_next(|day| day == Weekday::Mon);
+    loop_handle
+        .insert_source(
+            Timer::from_duration(next()),
+            move |_, _, state: &mut Passata| {
+                if let Err(err) = state.send_weekly_report() {
+                    error!("sending the weekly report: {err:?}");
+                }
+                TimeoutAction::ToDuration(next())
+            },
+        )
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+impl Passata {
+    fn send_weekly_report(&mut self) -> Result<()> {
+        let lines = summary(self.stats.path(), self.language())?;
+        info!(event = "weekly-report"; "last week: {}", lines.join(", "));
+        let mut notification = Notification::new();
+        notification
+            .summary(&self.tr("last-week"))
+            .body(&lines.join("\n"))
+            .icon("x-office-calendar");
+        self.notify(notification);
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+//! The cycle of work intervals and breaks, and its projection over the next phases.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{breaks::BreakType, profile::Timings, timer::Phase, Passata};
+
+/// How many phases are projected in the status
+const UPCOMING_PHASES: usize = 3;
+/// How many breaks `passata next` projects by default
+pub const NEXT_BREAKS: usize = 3;
+/// Most breaks projected at once, the count comes from the clients
+const MAX_NEXT_BREAKS: usize = 100;
+
+/// A phase that will start later, assuming the timer keeps running
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Upcoming {
+    pub phase: Phase,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_type: Option<String>,
+    #[serde(with = "humantime_serde")]
+    pub starts_in: Duration,
+    #[serde(with = "humantime_serde")]
+    pub at: SystemTime,
+    #[serde(with = "humantime_serde")]
+    pub length: Duration,
+}
+
+impl Upcoming {
+    pub fn name(&self) -> &str {
+        self.break_type.as_deref().unwrap_or(self.phase.name())
+    }
+}
+
+/// The break following a work interval, from the sequence if one is configured, from the
+/// breaks taken every few work intervals, or from the short/long alternation otherwise,
+/// advancing the counters of the cycle
+fn break_after_work(
+    sequence: &[String],
+    breaks: &HashMap<String, BreakType>,
+    timings: &Timings,
+    current_short_breaks: &mut u8,
+    sequence_position: &mut usize,
+) -> (Phase, Option<String>) {
+    if !sequence.is_empty() {
+        let name = sequence[*sequence_position % sequence.len()].clone();
+        *sequence_position = (*sequence_position + 1) % sequence.len();
+        let long = breaks.get(&name).is_some_and(|break_type| break_type.long);
+        let phase = if long {
+            Phase::LongBreak
+        } else {
+            Phase::ShortBreak
+        };
+        return (phase, Some(name));
+    }
+    if breaks.values().any(|break_type| break_type.every.is_some()) {
+        *sequence_position += 1;
+        let due = breaks
+            .iter()
+            .filter_map(|(name, break_type)| Some((name, break_type, break_type.every?)))
+            .filter(|(_, _, every)| *sequence_position % *every as usize == 0)
+            // the first name wins among the same every, to be the same on every run
+            .max_by(|(a, _, a_every), (b, _, b_every)| a_every.cmp(b_every).then(b.cmp(a)));
+        if let Some((name, break_type, _)) = due {
+            let phase = if break_type.long {
+                Phase::LongBreak
+            } else {
+                Phase::ShortBreak
+            };
+            return (phase, Some(name.clone()));
+        }
+    }
+    let phase = match timings.short_breaks_before_long_break {
+        // the count can be past it once the setting has been lowered
+        Some(short_breaks_before_long_break)
+            if *current_short_breaks >= short_breaks_before_long_break =>
+        {
+            *current_short_breaks = 0;
+            Phase::LongBreak
+        }
+        Some(_) => {
+            *current_short_breaks += 1;
+            Phase::ShortBreak
+        }
+        None => Phase::ShortBreak,
+    };
+    (phase, None)
+}
+
+impl Passata {
+    /// Choose the break that follows the work interval that just started
+    pub fn advance_cycle(&mut self) {
+        let mut current_short_breaks = self.current_short_breaks;
+        let mut sequence_position = self.sequence_position;
+        let (phase, break_type) = break_after_work(
+            &self.config.sequence,
+            &self.config.breaks,
+            &self.timings(),
+            &mut current_short_breaks,
+            &mut sequence_position,
+        );
+        self.current_short_breaks = current_short_breaks;
+        self.sequence_position = sequence_position;
+        self.next_event = phase;
+        self.next_break_type = break_type;
+    }
+
+    /// Length of `phase`, or of the named break `break_type`
+    pub fn phase_duration(&self, phase: Phase, break_type: Option<&str>) -> Duration {
+        self.phase_duration_with(&self.timings(), phase, break_type)
+    }
+
+    fn phase_duration_with(
+        &self,
+        timings: &Timings,
+        phase: Phase,
+        break_type: Option<&str>,
+    ) -> Duration {
+        if let Some(break_type) = break_type.and_then(|name| self.config.breaks.get(name)) {
+            return break_type.duration;
+        }
+        match phase {
+            Phase::Work => timings.work_interval,
+            Phase::ShortBreak => timings.short_break,
+            Phase::LongBreak => timings.long_break.unwrap_or(timings.short_break),
+        }
+    }
+
+    /// The phases following the current one, as they would start if the timer keeps running
+    /// from now, for as long as `keep` accepts them
+    fn project(&self, mut keep: impl FnMut(&Upcoming) -> bool) -> Vec<Upcoming> {
+        let now = SystemTime::now();
+        let mut current_short_breaks = self.current_short_breaks;
+        let mut sequence_position = self.sequence_position;
+        let mut time_range = self.active_time_range.clone();
+        let mut phase = self.next_event;
+        let mut break_type = self.next_break_type.clone();
+        let mut starts_in = self.remaining();
+        let mut upcoming = Vec::new();
+        loop {
+            let at = now + starts_in;
+            if phase == Phase::Work {
+                let start = DateTime::<Local>::from(at).time();
+                time_range = self.time_range_at(start).map(str::to_owned);
+            }
+            let timings = self.timings_for(time_range.as_deref());
+            let length = self.phase_duration_with(&timings, phase, break_type.as_deref());
+            let next = Upcoming {
+                phase,
+                break_type: break_type.clone(),
+                starts_in,
+                at,
+                length,
+            };
+            if !keep(&next) {
+                return upcoming;
+            }
+            upcoming.push(next);
+            starts_in += length;
+            (phase, break_type) = match phase {
+                Phase::Work => break_after_work(
+                    &self.config.sequence,
+                    &self.config.breaks,
+                    &timings,
+                    &mut current_short_breaks,
+                    &mut sequence_position,
+                ),
+                Phase::ShortBreak | Phase::LongBreak => (Phase::Work, None),
+            };
+        }
+    }
+
+    /// The next phases, as they would start if the timer keeps running from now
+    pub fn upcoming(&self) -> Vec<Upcoming> {
+        let mut count = 0;
+        self.project(|_| {
+            count += 1;
+            count <= UPCOMING_PHASES
+        })
+    }
+
+    /// The next `count` breaks, as they would start if the timer keeps running from now; at
+    /// most `MAX_NEXT_BREAKS`
+    pub fn next_breaks(&self, count: usize) -> Vec<Upcoming> {
+        let count = count.min(MAX_NEXT_BREAKS);
+        let mut breaks = 0;
+        let mut upcoming = self.project(|upcoming| {
+            if breaks == count {
+                return false;
+            }
+            if upcoming.phase != Phase::Work {
+                breaks += 1;
+            }
+            true
+        });
+        upcoming.retain(|upcoming| upcoming.phase != Phase::Work);
+        upcoming
+    }
+
+    /// The phases starting before the end of the day
+    pub fn plan(&self) -> Vec<Upcoming> {
+        let today = Local::now().date_naive();
+        self.project(|upcoming| DateTime::<Local>::from(upcoming.at).date_naive() == today)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timings(short_breaks_before_long_break: Option<u8>) -> Timings {
+        Timings {
+            work_interval: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Some(Duration::from_secs(15 * 60)),
+            short_breaks_before_long_break,
+        }
+    }
+
+    fn break_type(toml: &str) -> BreakType {
+        toml::from_str(toml).unwrap()
+    }
+
+    /// The next `count` breaks, from the start of the cycle
+    fn cycle(
+        sequence: &[String],
+        breaks: &HashMap<String, BreakType>,
+        timings: &Timings,
+        count: usize,
+    ) -> Vec<(Phase, Option<String>)> {
+        let mut current_short_breaks = 0;
+        let mut sequence_position = 0;
+        (0..count)
+            .map(|_| {
+                break_after_work(
+                    sequence,
+                    breaks,
+                    timings,
+                    &mut current_short_breaks,
+                    &mut sequence_position,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn short_and_long_breaks_alternate() {
+        let breaks = cycle(&[], &HashMap::new(), &timings(Some(2)), 4);
+        assert_eq!(
+            breaks,
+            [
+                (Phase::ShortBreak, None),
+                (Phase::ShortBreak, None),
+                (Phase::LongBreak, None),
+                (Phase::ShortBreak, None),
+            ]
+        );
+        let breaks = cycle(&[], &HashMap::new(), &timings(None), 3);
+        assert!(breaks.iter().all(|next| *next == (Phase::ShortBreak, None)));
+    }
+
+    #[test]
+    fn long_break_after_lowering_the_setting() {
+        let mut current_short_breaks = 5;
+        let mut sequence_position = 0;
+        let next = break_after_work(
+            &[],
+            &HashMap::new(),
+            &timings(Some(2)),
+            &mut current_short_breaks,
+            &mut sequence_position,
+        );
+        assert_eq!(next, (Phase::LongBreak, None));
+        assert_eq!(current_short_breaks, 0);
+    }
+
+    #[test]
+    fn sequence_wraps() {
+        let sequence = ["stretch".to_owned(), "walk".to_owned()];
+        let breaks = HashMap::from([
+            ("stretch".to_owned(), break_type("duration = \"5m\"")),
+            (
+                "walk".to_owned(),
+                break_type("duration = \"20m\"\nlong = true"),
+            ),
+        ]);
+        let next = cycle(&sequence, &breaks, &timings(Some(2)), 3);
+        assert_eq!(
+            next,
+            [
+                (Phase::ShortBreak, Some("stretch".to_owned())),
+                (Phase::LongBreak, Some("walk".to_owned())),
+                (Phase::ShortBreak, Some("stretch".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn breaks_every_few_work_intervals() {
+        let breaks = HashMap::from([
+            (
+                "stretch".to_owned(),
+                break_type("duration = \"5m\"\nevery = 2"),
+            ),
+            (
+                "walk".to_owned(),
+                break_type("duration = \"20m\"\nevery = 4\nlong = true"),
+            ),
+        ]);
+        let next = cycle(&[], &breaks, &timings(None), 4);
+        assert_eq!(
+            next,
+            [
+                (Phase::ShortBreak, None),
+                (Phase::ShortBreak, Some("stretch".to_owned())),
+                (Phase::ShortBreak, None),
+                // the largest every wins
+                (Phase::LongBreak, Some("walk".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn first_name_wins_among_the_same_every() {
+        let breaks = HashMap::from([
+            ("b".to_owned(), break_type("duration = \"5m\"\nevery = 1")),
+            ("a".to_owned(), break_type("duration = \"5m\"\nevery = 1")),
+        ]);
+        let next = cycle(&[], &breaks, &timings(None), 1);
+        assert_eq!(next, [(Phase::ShortBreak, Some("a".to_owned()))]);
+    }
+}
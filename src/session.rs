@@ -0,0 +1,52 @@
+//! Files of the instance running in a wayland session, namespaced by $WAYLAND_DISPLAY so that
+//! the sessions of the same user on a multi-session machine each run their own instance without
+//! clobbering the socket, the log and the state of the others. The history stays shared.
+
+use std::{env, fs, path::PathBuf};
+
+use color_eyre::Result;
+use xdg::BaseDirectories;
+
+/// Name of the wayland session, from its display socket
+pub fn name() -> Option<String> {
+    let display = env::var_os("WAYLAND_DISPLAY")?;
+    // an absolute path is allowed for the socket, only its name tells the session apart
+    let name = PathBuf::from(display)
+        .file_name()?
+        .to_string_lossy()
+        .into_owned();
+    (!name.is_empty()).then_some(name)
+}
+
+/// `stem.extension`, with the session in between when there is one
+pub fn file_name(stem: &str, extension: &str) -> String {
+    match name() {
+        Some(session) => format!("{stem}-{session}.{extension}"),
+        None => format!("{stem}.{extension}"),
+    }
+}
+
+/// Socket of the daemon of this session
+pub fn socket(xdg: &BaseDirectories) -> Result<PathBuf> {
+    Ok(xdg.place_runtime_file(file_name("passata", "sock"))?)
+}
+
+/// Socket of the daemon to talk to: the one of this session, or outside of a session (e.g.
+/// over ssh) the only daemon running
+pub fn client_socket(xdg: &BaseDirectories) -> Result<PathBuf> {
+    let socket = socket(xdg)?;
+    if name().is_some() || socket.exists() {
+        return Ok(socket);
+    }
+    let Some(dir) = socket.parent() else {
+        return Ok(socket);
+    };
+    let mut sockets = fs::read_dir(dir)?.filter_map(|entry| {
+        let name = entry.ok()?.file_name().into_string().ok()?;
+        (name.starts_with("passata-") && name.ends_with(".sock")).then(|| dir.join(name))
+    });
+    match (sockets.next(), sockets.next()) {
+        (Some(only), None) => Ok(only),
+        _ => Ok(socket),
+    }
+}
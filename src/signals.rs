@@ -0,0 +1,53 @@
+//! Control through signals, for scripts that do not want to talk to the control socket:
+//! SIGUSR1 logs the current phase with the time left, SIGUSR2 skips to the next phase.
+//! SIGTERM and SIGINT undo what would outlive the daemon before exiting.
+
+use std::process::exit;
+
+use color_eyre::Result;
+use log::{error, info};
+use smithay_client_toolkit::reexports::calloop::{
+    signals::{Signal, Signals},
+    LoopHandle,
+};
+
+use crate::Passata;
+
+/// Start handling the signals. The signals are blocked in the calling thread, so this must
+/// run before any other thread is spawned, or they would be delivered there and kill the
+/// daemon
+pub fn listen(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let signals = Signals::new(&[
+        Signal::SIGUSR1,
+        Signal::SIGUSR2,
+        Signal::SIGTERM,
+        Signal::SIGINT,
+    ])?;
+    loop_handle
+        .insert_source(signals, |event, _, state| match event.signal() {
+            Signal::SIGUSR1 => info!(event = "status"; "{}", state.status()),
+            Signal::SIGUSR2 => {
+                info!("skipping to the next phase on SIGUSR2");
+                if let Err(err) = state.skip() {
+                    error!("skipping to the next phase: {err:?}");
+                }
+            }
+            signal @ (Signal::SIGTERM | Signal::SIGINT) => {
+                info!("exiting on {signal:?}");
+                state.terminate();
+            }
+            _ => {}
+        })
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+impl Passata {
+    /// Give the countdown workspace its name back and exit
+    fn terminate(&mut self) -> ! {
+        if let Some(countdown) = self.workspace_countdown.take() {
+            countdown.restore();
+        }
+        exit(0)
+    }
+}
@@ -0,0 +1,82 @@
+//! Snoozing the break about to start: each snooze postpones it by half of the previous one,
+//! starting from the configured step, so that the snoozes of a work interval never add up to
+//! twice the step, and only as many times as the limit.
+
+use std::time::Duration;
+
+use color_eyre::{eyre::ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{events::Event, timer::Phase, Passata};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct SnoozeConfig {
+    /// How long the first snooze postpones the break, the next ones are halved each time
+    #[serde(with = "humantime_serde")]
+    pub step: Duration,
+    /// How many times the break of a work interval can be snoozed
+    pub limit: u32,
+}
+
+impl Default for SnoozeConfig {
+    fn default() -> Self {
+        Self {
+            step: Duration::from_secs(5 * 60),
+            limit: 3,
+        }
+    }
+}
+
+/// How long the snooze after `snoozes` others postpones the break
+fn snooze_step(step: Duration, snoozes: u32) -> Duration {
+    step / 2u32.saturating_pow(snoozes)
+}
+
+impl Passata {
+    /// Postpone the break ending the current work interval
+    pub fn snooze(&mut self) -> Result<()> {
+        ensure!(
+            self.phase == Phase::Work && !self.stopwatch,
+            "there is no break coming to snooze"
+        );
+        let limit = self.config.snooze.limit;
+        ensure!(
+            self.snoozes < limit,
+            "the break has already been snoozed {limit} times"
+        );
+        let by = snooze_step(self.config.snooze.step, self.snoozes);
+        // a deferred break is snoozed from now
+        self.end_deferral();
+        self.set_remaining(self.remaining() + by)?;
+        self.snoozes += 1;
+        self.emit(Event::BreakSnoozed {
+            by,
+            count: self.snoozes,
+            limit,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_secs(5 * 60);
+
+    #[test]
+    fn snoozes_are_halved() {
+        assert_eq!(snooze_step(STEP, 0), STEP);
+        assert_eq!(snooze_step(STEP, 1), Duration::from_secs(150));
+        assert_eq!(snooze_step(STEP, 2), Duration::from_secs(75));
+    }
+
+    #[test]
+    fn snoozes_never_add_up_to_twice_the_step() {
+        let total: Duration = (0..40).map(|snoozes| snooze_step(STEP, snoozes)).sum();
+        assert!(total < 2 * STEP);
+        // the divisor saturates instead of overflowing
+        assert!(snooze_step(STEP, u32::MAX) < Duration::from_secs(1));
+    }
+}
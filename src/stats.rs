@@ -0,0 +1,716 @@
+//! History of the pomodoros and breaks, appended as one line of JSON each to a file in the data
+//! directory. Once older than the retention, they are folded into daily aggregates kept in a
+//! second file. Every change takes a lock first, as several instances can share the history.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat};
+use color_eyre::{eyre::WrapErr, Result};
+use log::{info, warn};
+use nix::fcntl::{Flock, FlockArg};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
+use xdg::BaseDirectories;
+
+use crate::{streak, Passata};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    /// The work interval ended with a break
+    Completed,
+    /// The user went away for longer than idle-reset
+    Abandoned,
+    /// The user went idle during the break
+    BreakTaken,
+    /// The user kept using the computer for the whole break
+    BreakIgnored,
+    /// The break left the user refreshed, as answered in the survey; `worked` is the length of
+    /// the work interval before it
+    Refreshed,
+    /// The break left the user tired, as answered in the survey
+    Tired,
+    /// The user was idle during the work interval for `worked`, pausing it
+    Idle,
+    /// The user was idle during the work interval for `worked`, longer than idle-reset
+    IdleReset,
+}
+
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Completed => "completed",
+            Outcome::Abandoned => "abandoned",
+            Outcome::BreakTaken => "break-taken",
+            Outcome::BreakIgnored => "break-ignored",
+            Outcome::Refreshed => "refreshed",
+            Outcome::Tired => "tired",
+            Outcome::Idle => "idle",
+            Outcome::IdleReset => "idle-reset",
+        }
+    }
+
+    fn is_pomodoro(self) -> bool {
+        matches!(self, Outcome::Completed | Outcome::Abandoned)
+    }
+
+    /// Whether it ends the streak of the pomodoros completed in a row
+    pub fn ends_streak(self) -> bool {
+        matches!(
+            self,
+            Outcome::Abandoned | Outcome::BreakIgnored | Outcome::IdleReset
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Record {
+    #[serde(with = "humantime_serde")]
+    pub ended: SystemTime,
+    /// Time spent working in the pomodoro, or length of the break
+    #[serde(with = "humantime_serde")]
+    pub worked: Duration,
+    pub outcome: Outcome,
+    /// Label of the task worked on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// Share of the pomodoro spent using the keyboard or the mouse, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intensity: Option<f32>,
+    /// Times the break after the pomodoro has been snoozed
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub snoozed: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct HistoryConfig {
+    /// How long the single pomodoros and breaks are kept before being folded into the daily
+    /// aggregates; forever when not set
+    #[serde(with = "humantime_serde")]
+    pub raw_retention: Option<Duration>,
+}
+
+/// How often the history is compacted
+const COMPACTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Totals of a day whose records have been compacted
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DailyAggregate {
+    pub day: NaiveDate,
+    pub completed: u32,
+    pub abandoned: u32,
+    pub breaks_taken: u32,
+    pub breaks_ignored: u32,
+    /// Answers to the survey after the breaks
+    #[serde(default)]
+    pub refreshed: u32,
+    #[serde(default)]
+    pub tired: u32,
+    /// Time spent working in the pomodoros of the day
+    #[serde(with = "humantime_serde")]
+    pub worked: Duration,
+    /// Times the user went idle during the work intervals, without starting them over
+    #[serde(default)]
+    pub idle_periods: u32,
+    #[serde(default, with = "humantime_serde")]
+    pub idle: Duration,
+    /// Times the breaks have been snoozed
+    #[serde(default)]
+    pub snoozed: u32,
+    /// Most pomodoros completed in a row during the day
+    #[serde(default)]
+    pub best_streak: u32,
+}
+
+impl DailyAggregate {
+    pub fn add(&mut self, record: &Record) {
+        match record.outcome {
+            Outcome::Completed => self.completed += 1,
+            Outcome::Abandoned => self.abandoned += 1,
+            Outcome::BreakTaken => self.breaks_taken += 1,
+            Outcome::BreakIgnored => self.breaks_ignored += 1,
+            Outcome::Refreshed => self.refreshed += 1,
+            Outcome::Tired => self.tired += 1,
+            Outcome::Idle => {
+                self.idle_periods += 1;
+                self.idle += record.worked;
+            }
+            Outcome::IdleReset => {}
+        }
+        self.snoozed += record.snoozed;
+        if record.outcome.is_pomodoro() {
+            self.worked += record.worked;
+        }
+    }
+
+    /// Add the totals of `other`, keeping the day
+    pub fn merge(&mut self, other: &DailyAggregate) {
+        self.completed += other.completed;
+        self.abandoned += other.abandoned;
+        self.breaks_taken += other.breaks_taken;
+        self.breaks_ignored += other.breaks_ignored;
+        self.refreshed += other.refreshed;
+        self.tired += other.tired;
+        self.worked += other.worked;
+        self.idle_periods += other.idle_periods;
+        self.idle += other.idle;
+        self.snoozed += other.snoozed;
+        self.best_streak = self.best_streak.max(other.best_streak);
+    }
+}
+
+pub struct Stats {
+    path: PathBuf,
+}
+
+impl Stats {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn append(&self, record: &Record) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let _lock = lock(&self.path)?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .wrap_err_with(|| format!("writing to {:?}", self.path))
+    }
+
+    /// Fold the records older than `retention` into the daily aggregates, returning how many
+    /// have been folded
+    fn compact(&self, retention: Duration) -> Result<usize> {
+        let _lock = lock(&self.path)?;
+        let cutoff = SystemTime::now() - retention;
+        let (old, recent): (Vec<_>, Vec<_>) = read(&self.path)?
+            .into_iter()
+            .partition(|record| record.ended < cutoff);
+        if old.is_empty() {
+            return Ok(0);
+        }
+        let aggregates_path = aggregates_path(&self.path);
+        let mut aggregates = read_lines::<DailyAggregate>(&aggregates_path)?
+            .into_iter()
+            .map(|aggregate| (aggregate.day, aggregate))
+            .collect::<BTreeMap<_, _>>();
+        fold(&mut aggregates, &old);
+        // the aggregates go first, a failure in between counts the records twice instead of
+        // losing them
+        write_lines(&aggregates_path, aggregates.values())?;
+        write_lines(&self.path, &recent)?;
+        Ok(old.len())
+    }
+}
+
+/// Take the lock of the history at `path` and of its aggregates, released when dropped. The
+/// history itself is replaced when compacted, the lock is a file of its own
+fn lock(path: &Path) -> Result<Flock<File>> {
+    let lock_path = path.with_extension("lock");
+    if let Some(dir) = lock_path.parent() {
+        fs::create_dir_all(dir).wrap_err_with(|| format!("creating {dir:?}"))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .wrap_err_with(|| format!("opening {lock_path:?}"))?;
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, err)| err)
+        .wrap_err_with(|| format!("locking {lock_path:?}"))
+}
+
+/// Replace the file at `path` with one line of JSON for each item
+fn write_lines<'a, T: Serialize + 'a>(
+    path: &Path,
+    items: impl IntoIterator<Item = &'a T>,
+) -> Result<()> {
+    let mut content = String::new();
+    for item in items {
+        content.push_str(&serde_json::to_string(item)?);
+        content.push('\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    fs::write(&tmp, content).wrap_err_with(|| format!("writing {tmp:?}"))?;
+    fs::rename(&tmp, path).wrap_err_with(|| format!("replacing {path:?}"))
+}
+
+/// Compact the history now and then once a day
+pub fn schedule_compaction(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    loop_handle
+        .insert_source(Timer::immediate(), |_, _, state: &mut Passata| {
+            state.compact_history();
+            TimeoutAction::ToDuration(COMPACTION_INTERVAL)
+        })
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Where the daemon keeps the history
+pub fn history_path() -> Result<PathBuf> {
+    Ok(BaseDirectories::with_prefix("passata")?.get_data_file("history.jsonl"))
+}
+
+/// Read every record of the history at `path`
+pub fn read(path: &Path) -> Result<Vec<Record>> {
+    read_lines(path)
+}
+
+/// The daily aggregates are kept next to the history at `path`
+fn aggregates_path(path: &Path) -> PathBuf {
+    path.with_file_name("daily.jsonl")
+}
+
+/// Replace the history at `path` and its daily aggregates
+pub fn replace(path: &Path, records: &[Record], aggregates: &[DailyAggregate]) -> Result<()> {
+    let _lock = lock(path)?;
+    write_lines(&aggregates_path(path), aggregates)?;
+    write_lines(path, records)
+}
+
+/// Read the daily aggregates of the compacted records of the history at `path`
+pub fn read_aggregates(path: &Path) -> Result<Vec<DailyAggregate>> {
+    read_lines(&aggregates_path(path))
+}
+
+/// Read every record of the history at `path` that can be parsed, describing the lines that
+/// could not
+pub fn salvage(path: &Path) -> Result<(Vec<Record>, Vec<String>)> {
+    salvage_lines(path)
+}
+
+/// Read every daily aggregate next to the history at `path` that can be parsed, describing the
+/// lines that could not
+pub fn salvage_aggregates(path: &Path) -> Result<(Vec<DailyAggregate>, Vec<String>)> {
+    salvage_lines(&aggregates_path(path))
+}
+
+/// Content of `path`, empty if it does not exist yet
+fn read_content(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err).wrap_err_with(|| format!("reading {path:?}")),
+    }
+}
+
+/// Read a file with one JSON item per line, empty if it does not exist yet
+fn read_lines<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
+    read_content(path)?
+        .lines()
+        .map(|line| serde_json::from_str(line).wrap_err_with(|| format!("parsing {path:?}")))
+        .collect()
+}
+
+/// Read a file with one JSON item per line like `read_lines`, skipping the lines that cannot be
+/// parsed and describing them
+fn salvage_lines<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<(Vec<T>, Vec<String>)> {
+    let mut items = Vec::new();
+    let mut skipped = Vec::new();
+    for (number, line) in read_content(path)?.lines().enumerate() {
+        match serde_json::from_str(line) {
+            Ok(item) => items.push(item),
+            Err(err) => skipped.push(format!("line {} of {path:?}: {err}", number + 1)),
+        }
+    }
+    Ok((items, skipped))
+}
+
+/// Number of pomodoros completed since midnight, in local time
+pub fn completed_today(path: &Path) -> Result<usize> {
+    let today = Local::now().date_naive();
+    Ok(read(path)?
+        .iter()
+        .filter(|record| {
+            record.outcome == Outcome::Completed
+                && DateTime::<Local>::from(record.ended).date_naive() == today
+        })
+        .count())
+}
+
+/// Add `records` to the aggregates of their days, with the longest streak of each day
+fn fold<'a>(
+    aggregates: &mut BTreeMap<NaiveDate, DailyAggregate>,
+    records: impl IntoIterator<Item = &'a Record>,
+) {
+    // the streaks start over every day
+    let mut streaks = HashMap::new();
+    for record in records {
+        let day = DateTime::<Local>::from(record.ended).date_naive();
+        let aggregate = aggregates.entry(day).or_insert_with(|| DailyAggregate {
+            day,
+            ..Default::default()
+        });
+        aggregate.add(record);
+        let streak = streaks.entry(day).or_default();
+        *streak = streak::after(*streak, record.outcome);
+        aggregate.best_streak = aggregate.best_streak.max(*streak);
+    }
+}
+
+/// Totals of each day `in_range`, from the history at `path` and its daily aggregates
+pub fn daily(
+    path: &Path,
+    in_range: impl Fn(NaiveDate) -> bool,
+) -> Result<BTreeMap<NaiveDate, DailyAggregate>> {
+    let mut aggregates = BTreeMap::new();
+    for aggregate in read_aggregates(path)? {
+        if in_range(aggregate.day) {
+            aggregates.insert(aggregate.day, aggregate);
+        }
+    }
+    let records = read(path)?;
+    fold(
+        &mut aggregates,
+        records
+            .iter()
+            .filter(|record| in_range(DateTime::<Local>::from(record.ended).date_naive())),
+    );
+    Ok(aggregates)
+}
+
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown format {s:?}, it must be csv or json")),
+        }
+    }
+}
+
+/// A pomodoro as exported, with local timestamps
+#[derive(Serialize)]
+struct Session {
+    start: String,
+    end: String,
+    /// Seconds of work
+    duration: u64,
+    outcome: Outcome,
+    task: Option<String>,
+    intensity: Option<f32>,
+}
+
+/// Which records of the history to export
+pub struct ExportFilter {
+    /// First day included, in local time
+    pub from: Option<NaiveDate>,
+    /// Last day included, in local time
+    pub to: Option<NaiveDate>,
+    /// Include the abandoned pomodoros too
+    pub abandoned: bool,
+}
+
+/// Write the sessions of the history at `path` matching `filter` to `out`
+pub fn export(
+    path: &Path,
+    format: ExportFormat,
+    filter: &ExportFilter,
+    out: &mut impl Write,
+) -> Result<()> {
+    let sessions = read(path)?
+        .into_iter()
+        .filter(|record| record.outcome.is_pomodoro())
+        .filter(|record| filter.abandoned || record.outcome == Outcome::Completed)
+        .filter_map(|record| {
+            let end = DateTime::<Local>::from(record.ended);
+            let day = end.date_naive();
+            if filter.from.is_some_and(|from| day < from) || filter.to.is_some_and(|to| day > to) {
+                return None;
+            }
+            let start = DateTime::<Local>::from(record.ended - record.worked);
+            Some(Session {
+                start: start.to_rfc3339_opts(SecondsFormat::Secs, false),
+                end: end.to_rfc3339_opts(SecondsFormat::Secs, false),
+                duration: record.worked.as_secs(),
+                outcome: record.outcome,
+                task: record.task,
+                intensity: record.intensity,
+            })
+        })
+        .collect::<Vec<_>>();
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &sessions)?;
+            writeln!(out)?;
+        }
+        ExportFormat::Csv => {
+            writeln!(out, "start,end,duration,outcome,task,intensity")?;
+            for session in sessions {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    session.start,
+                    session.end,
+                    session.duration,
+                    session.outcome.name(),
+                    csv_field(session.task.as_deref().unwrap_or_default()),
+                    session
+                        .intensity
+                        .map(|intensity| format!("{intensity:.2}"))
+                        .unwrap_or_default()
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Quote `field` if it contains a separator, a quote or a newline
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Write a shields-style SVG badge with the pomodoros completed today to `path`
+pub fn write_badge(path: &Path, completed_today: usize) -> Result<()> {
+    let label = "today";
+    let value = format!("{completed_today} 🍅");
+    // rough width of the 11px Verdana used by shields badges
+    let text_width = |text: &str| text.chars().count() as u32 * 7 + 10;
+    let (label_width, value_width) = (text_width(label), text_width(&value));
+    let width = label_width + value_width;
+    let svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {value}">
+  <title>{label}: {value}</title>
+  <clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="#555"/>
+    <rect x="{label_width}" width="{value_width}" height="20" fill="#e05d44"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{value_x}" y="14">{value}</text>
+  </g>
+</svg>
+"##,
+        label_x = label_width / 2,
+        value_x = label_width + value_width / 2,
+    );
+    fs::write(path, svg).wrap_err_with(|| format!("writing the badge to {path:?}"))
+}
+
+impl Passata {
+    /// Record whether the break that just ended has been taken
+    pub fn record_break(&mut self, taken: bool, length: Duration) {
+        let record = Record {
+            ended: SystemTime::now(),
+            worked: length,
+            outcome: if taken {
+                Outcome::BreakTaken
+            } else {
+                Outcome::BreakIgnored
+            },
+            task: None,
+            intensity: None,
+            snoozed: 0,
+        };
+        if let Err(err) = self.stats.append(&record) {
+            warn!("recording the break in the history: {err:?}");
+            self.health.history_failures += 1;
+        }
+        self.update_streak(&record);
+    }
+
+    /// Record that the user came back after being idle for `idle_for` during the work
+    /// interval, `reset` if it has been started over for it
+    pub fn record_idle(&mut self, idle_for: Duration, reset: bool) {
+        let record = Record {
+            ended: SystemTime::now(),
+            worked: idle_for,
+            outcome: if reset {
+                Outcome::IdleReset
+            } else {
+                Outcome::Idle
+            },
+            task: self.task.clone(),
+            intensity: None,
+            snoozed: 0,
+        };
+        if let Err(err) = self.stats.append(&record) {
+            warn!("recording the idle time in the history: {err:?}");
+            self.health.history_failures += 1;
+        }
+        self.update_streak(&record);
+    }
+
+    /// Record the answer to the survey after a break, `worked` being the length of the work
+    /// interval before it
+    pub fn record_survey(&mut self, refreshed: bool, worked: Duration) {
+        let record = Record {
+            ended: SystemTime::now(),
+            worked,
+            outcome: if refreshed {
+                Outcome::Refreshed
+            } else {
+                Outcome::Tired
+            },
+            task: None,
+            intensity: None,
+            snoozed: 0,
+        };
+        if let Err(err) = self.stats.append(&record) {
+            warn!("recording the survey answer in the history: {err:?}");
+            self.health.history_failures += 1;
+        }
+    }
+
+    fn compact_history(&mut self) {
+        let Some(retention) = self.config.history.raw_retention else {
+            return;
+        };
+        match self.stats.compact(retention) {
+            Ok(0) => {}
+            Ok(compacted) => info!("compacted {compacted} records of the history"),
+            Err(err) => {
+                warn!("compacting the history: {err:?}");
+                self.health.history_failures += 1;
+            }
+        }
+    }
+
+    pub fn record_pomodoro(&mut self, outcome: Outcome, worked: Duration) {
+        let record = Record {
+            ended: SystemTime::now(),
+            worked,
+            outcome,
+            task: self.task.clone(),
+            intensity: self.intensity(worked),
+            snoozed: self.snoozes,
+        };
+        if let Err(err) = self.stats.append(&record) {
+            warn!("recording the pomodoro in the history: {err:?}");
+            self.health.history_failures += 1;
+        }
+        self.update_streak(&record);
+        self.track_time(&record);
+        if let Some(badge) = &self.config.badge {
+            // the pomodoro just recorded is counted in the daily progress right after
+            let completed =
+                self.progress.completed() + u32::from(record.outcome == Outcome::Completed);
+            if let Err(err) = write_badge(badge, completed as usize) {
+                warn!("updating the stats badge: {err:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, TimeZone};
+
+    use super::*;
+
+    fn record(day: u32, hour: u32, outcome: Outcome) -> Record {
+        let ended = Local
+            .with_ymd_and_hms(2026, 1, day, hour, 0, 0)
+            .earliest()
+            .unwrap();
+        Record {
+            ended: ended.into(),
+            worked: Duration::from_secs(25 * 60),
+            outcome,
+            task: None,
+            intensity: None,
+            snoozed: 0,
+        }
+    }
+
+    fn best_streaks(records: &[Record]) -> Vec<(u32, u32)> {
+        let mut aggregates = BTreeMap::new();
+        fold(&mut aggregates, records);
+        aggregates
+            .values()
+            .map(|aggregate| (aggregate.day.day(), aggregate.best_streak))
+            .collect()
+    }
+
+    #[test]
+    fn best_streak_of_the_day() {
+        let records = [
+            record(14, 9, Outcome::Completed),
+            record(14, 10, Outcome::BreakTaken),
+            record(14, 11, Outcome::Completed),
+            record(14, 12, Outcome::Completed),
+        ];
+        assert_eq!(best_streaks(&records), [(14, 3)]);
+    }
+
+    #[test]
+    fn abandoned_pomodoro_ends_the_streak() {
+        let records = [
+            record(14, 9, Outcome::Completed),
+            record(14, 10, Outcome::Completed),
+            record(14, 11, Outcome::Abandoned),
+            record(14, 12, Outcome::Completed),
+        ];
+        assert_eq!(best_streaks(&records), [(14, 2)]);
+        let mut aggregates = BTreeMap::new();
+        fold(&mut aggregates, &records);
+        let aggregate = &aggregates[&NaiveDate::from_ymd_opt(2026, 1, 14).unwrap()];
+        assert_eq!((aggregate.completed, aggregate.abandoned), (3, 1));
+    }
+
+    #[test]
+    fn streaks_start_over_at_midnight() {
+        let records = [
+            record(14, 21, Outcome::Completed),
+            record(14, 22, Outcome::Completed),
+            record(14, 23, Outcome::Completed),
+            record(15, 0, Outcome::Completed),
+            record(15, 1, Outcome::BreakIgnored),
+            record(15, 2, Outcome::Completed),
+        ];
+        assert_eq!(best_streaks(&records), [(14, 3), (15, 1)]);
+    }
+
+    #[test]
+    fn best_streak_kept_after_folding_more_records() {
+        let mut aggregates = BTreeMap::new();
+        fold(
+            &mut aggregates,
+            &[
+                record(14, 9, Outcome::Completed),
+                record(14, 10, Outcome::Completed),
+            ],
+        );
+        fold(&mut aggregates, &[record(14, 11, Outcome::Completed)]);
+        let best: Vec<_> = aggregates.values().map(|day| day.best_streak).collect();
+        assert_eq!(best, [2]);
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{crash, focus::Focus, goal::GoalProgress, schedule::Upcoming, timer::Phase, Passata};
+
+/// Snapshot of the timer state, shared with everything that exposes it outside of the daemon
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Status {
+    pub phase: Phase,
+    /// Name of the break from the sequence, if one is running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_type: Option<String>,
+    #[serde(with = "humantime_serde")]
+    pub length: Duration,
+    #[serde(with = "humantime_serde")]
+    pub remaining: Duration,
+    pub paused: bool,
+    /// Share of the phase done when the snapshot was taken, from 0 to 100
+    #[serde(default)]
+    pub percent: u32,
+    /// Dormant for the day off, until `passata start` or sustained activity
+    #[serde(default)]
+    pub day_off: bool,
+    pub completed_pomodoros: u32,
+    /// Pomodoros completed in a row today
+    #[serde(default)]
+    pub streak: u32,
+    /// Pomodoros completed today towards the daily goal, when one is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_goal: Option<GoalProgress>,
+    /// Label of the task being worked on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// Time left in the focus mode, skipping the breaks
+    #[serde(
+        default,
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub focus: Option<Duration>,
+    /// The work interval counts up until stopped
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stopwatch: bool,
+    /// The next phases, assuming the timer keeps running
+    #[serde(default)]
+    pub upcoming: Vec<Upcoming>,
+    #[serde(default)]
+    pub health: Health,
+    /// When this snapshot was taken
+    #[serde(skip, default = "Instant::now")]
+    pub taken: Instant,
+}
+
+/// Counters of what went wrong since the daemon started, to tell whether it is healthy
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Health {
+    #[serde(with = "humantime_serde")]
+    pub uptime: Duration,
+    /// Notifiers that failed, including the ones where the next notifier took over
+    pub notification_failures: u32,
+    /// Phases started late by the watchdog, after their timer missed the deadline
+    pub missed_deadlines: u32,
+    /// Timers rescheduled because the wall clock moved on (suspend, clock jumps)
+    pub clock_jumps: u32,
+    /// Background watchers (D-Bus, compositor IPC) that stopped following their source
+    pub watchers_stopped: u32,
+    /// Failed writes of the history and of the badge
+    pub history_failures: u32,
+    /// Times the connection to the wayland compositor dropped
+    pub wayland_disconnections: u32,
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "up {}",
+            humantime::format_duration(Duration::from_secs(self.uptime.as_secs()))
+        )?;
+        for (count, what) in [
+            (self.notification_failures, "notification failures"),
+            (self.missed_deadlines, "missed deadlines"),
+            (self.clock_jumps, "clock jumps"),
+            (self.watchers_stopped, "watchers stopped"),
+            (self.history_failures, "history failures"),
+            (self.wayland_disconnections, "wayland disconnections"),
+        ] {
+            if count > 0 {
+                write!(f, ", {count} {what}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Status {
+    /// Time left in the phase, accounting for the time passed since the snapshot was taken
+    pub fn remaining(&self) -> Duration {
+        if self.paused {
+            self.remaining
+        } else {
+            self.remaining.saturating_sub(self.taken.elapsed())
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.length.saturating_sub(self.remaining())
+    }
+
+    /// Share of the phase done, accounting for the time passed since the snapshot was taken
+    pub fn progress(&self) -> u32 {
+        percent(self.elapsed(), self.length)
+    }
+
+    /// Time to show on a clock: the time elapsed for the stopwatch, the time left otherwise
+    pub fn clock(&self) -> Duration {
+        if self.stopwatch {
+            self.elapsed()
+        } else {
+            self.remaining()
+        }
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // round to the second, the rest is noise for humans
+        let clock = Duration::from_secs(self.clock().as_secs());
+        write!(
+            f,
+            "{}: {} {}{} ({} pomodoros completed)",
+            self.break_type.as_deref().unwrap_or(self.phase.name()),
+            humantime::format_duration(clock),
+            if self.stopwatch { "elapsed" } else { "left" },
+            if self.day_off {
+                ", day off"
+            } else if self.paused {
+                ", paused"
+            } else {
+                ""
+            },
+            self.completed_pomodoros
+        )?;
+        if let Some(goal) = &self.daily_goal {
+            write!(
+                f,
+                ", {}/{} today ({}%)",
+                goal.completed, goal.goal, goal.percent
+            )?;
+        }
+        if self.streak > 1 {
+            write!(f, ", streak of {}", self.streak)?;
+        }
+        if let Some(task) = &self.task {
+            write!(f, ", task: {task}")?;
+        }
+        if let Some(focus) = self.focus {
+            write!(
+                f,
+                ", focusing for {}",
+                humantime::format_duration(Duration::from_secs(focus.as_secs()))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Share of `length` that `elapsed` is, from 0 to 100
+fn percent(elapsed: Duration, length: Duration) -> u32 {
+    if length.is_zero() {
+        return 0;
+    }
+    ((elapsed.as_secs_f64() / length.as_secs_f64() * 100.0) as u32).min(100)
+}
+
+impl Passata {
+    /// Share of the current phase done, the time spent paused or idle left out
+    pub fn phase_percent(&self) -> u32 {
+        percent(
+            self.phase_length.saturating_sub(self.remaining()),
+            self.phase_length,
+        )
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            phase: self.phase,
+            break_type: self.break_type.clone(),
+            length: self.phase_length,
+            remaining: self.remaining(),
+            paused: self.is_paused(),
+            percent: self.phase_percent(),
+            day_off: self.day_off,
+            completed_pomodoros: self.completed_pomodoros,
+            streak: self.streak.current(),
+            daily_goal: self.goal_progress(),
+            task: self.task.clone(),
+            focus: self.focus.as_ref().map(Focus::remaining),
+            stopwatch: self.stopwatch,
+            upcoming: self.upcoming(),
+            health: Health {
+                uptime: self.started.elapsed(),
+                ..self.health.clone()
+            },
+            taken: Instant::now(),
+        }
+    }
+
+    /// Push the current status to the subsystems that expose it
+    pub fn publish_status(&mut self) {
+        let status = self.status();
+        crash::remember(&status);
+        if let Some(mpris) = &self.mpris {
+            if let Err(err) = mpris.update(status.clone()) {
+                warn!("updating the mpris player: {err}");
+            }
+        }
+        if let Some(mqtt) = &self.mqtt {
+            if let Err(err) = mqtt.update(status.clone()) {
+                warn!("publishing the status to mqtt: {err}");
+            }
+        }
+        if let Some(countdown) = &self.workspace_countdown {
+            if let Err(err) = countdown.update(status.clone()) {
+                warn!("updating the workspace countdown: {err}");
+            }
+        }
+        if let Some(tray) = &self.tray {
+            if let Err(err) = tray.update(status) {
+                warn!("updating the tray icon: {err}");
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Stopwatch mode, for the focus sessions without a set length: the work interval counts up
+//! until `passata stop`, then it is recorded like any other and followed by a break
+//! proportional to it, computed as in Flowtime.
+
+use std::time::Duration;
+
+use color_eyre::{eyre::ensure, Result};
+use log::info;
+
+use crate::{timer::Phase, Passata};
+
+/// Longest stopwatch session, over like a work interval when nobody stops it
+const MAX_SESSION: Duration = Duration::from_secs(4 * 60 * 60);
+
+impl Passata {
+    /// Turn the work interval into a stopwatch session, starting it now if a break is running
+    pub fn start_stopwatch(&mut self) -> Result<()> {
+        info!("starting the stopwatch");
+        if self.phase != Phase::Work {
+            self.next_event = Phase::Work;
+            self.next_phase()?;
+            self.start_work()?;
+        }
+        // the break chosen for this work interval is kept, only its length changes
+        self.stopwatch = true;
+        let elapsed = self.phase_length.saturating_sub(self.remaining());
+        self.set_remaining(MAX_SESSION.saturating_sub(elapsed))
+    }
+
+    /// End the stopwatch session, starting its break
+    pub fn stop_stopwatch(&mut self) -> Result<()> {
+        ensure!(
+            self.stopwatch && self.phase == Phase::Work,
+            "the stopwatch is not running"
+        );
+        info!("stopping the stopwatch");
+        self.next_phase()
+    }
+}
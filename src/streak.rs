@@ -0,0 +1,103 @@
+//! Streak of the pomodoros completed in a row on the current day, ended by an abandoned
+//! pomodoro, an ignored break or an idle long enough to start the work over. The longest of
+//! each day is counted again from the history, and kept in the daily aggregates once compacted.
+
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate};
+use log::{info, warn};
+
+use crate::{
+    stats::{self, Outcome, Record},
+    Passata,
+};
+
+/// Streak after a record with `outcome`, from `current`
+pub fn after(current: u32, outcome: Outcome) -> u32 {
+    if outcome == Outcome::Completed {
+        current + 1
+    } else if outcome.ends_streak() {
+        0
+    } else {
+        current
+    }
+}
+
+pub struct Streak {
+    day: NaiveDate,
+    current: u32,
+}
+
+impl Streak {
+    /// Count the streak of today from the history at `path`
+    pub fn load(path: &Path) -> Self {
+        let day = Local::now().date_naive();
+        let current = stats::read(path).map_or_else(
+            |err| {
+                warn!("counting the streak of today: {err:?}");
+                0
+            },
+            |records| {
+                records
+                    .iter()
+                    .filter(|record| DateTime::<Local>::from(record.ended).date_naive() == day)
+                    .fold(0, |current, record| after(current, record.outcome))
+            },
+        );
+        Self { day, current }
+    }
+
+    pub fn current(&self) -> u32 {
+        if self.day == Local::now().date_naive() {
+            self.current
+        } else {
+            0
+        }
+    }
+}
+
+impl Passata {
+    /// Extend or end the streak with the record just added to the history
+    pub fn update_streak(&mut self, record: &Record) {
+        let current = self.streak.current();
+        self.streak.day = DateTime::<Local>::from(record.ended).date_naive();
+        self.streak.current = after(current, record.outcome);
+        if record.outcome.ends_streak() && current > 1 {
+            info!("streak of {current} pomodoros ended: {:?}", record.outcome);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completed_pomodoros_extend_the_streak() {
+        assert_eq!(after(0, Outcome::Completed), 1);
+        assert_eq!(after(3, Outcome::Completed), 4);
+    }
+
+    #[test]
+    fn streak_ends() {
+        for outcome in [
+            Outcome::Abandoned,
+            Outcome::BreakIgnored,
+            Outcome::IdleReset,
+        ] {
+            assert_eq!(after(3, outcome), 0, "{outcome:?}");
+        }
+    }
+
+    #[test]
+    fn streak_kept() {
+        for outcome in [
+            Outcome::BreakTaken,
+            Outcome::Refreshed,
+            Outcome::Tired,
+            Outcome::Idle,
+        ] {
+            assert_eq!(after(3, outcome), 3, "{outcome:?}");
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! One-tap survey shown after some of the breaks, asking whether they left the user
+//! refreshed. The answers are kept in the history next to the length of the work interval
+//! before the break, to suggest the length that suits the user best.
+
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::Result;
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    stats::{self, Outcome},
+    Passata,
+};
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct SurveyConfig {
+    /// Fraction of the breaks followed by the survey, from 0 (never) to 1 (always)
+    pub fraction: f64,
+}
+
+/// Answers needed for a length of the work interval before it can be suggested
+const MIN_ANSWERS: u32 = 3;
+
+/// Answers given after the work intervals of the same length
+#[derive(Default)]
+struct Answers {
+    refreshed: u32,
+    tired: u32,
+}
+
+impl Answers {
+    fn total(&self) -> u32 {
+        self.refreshed + self.tired
+    }
+
+    fn refreshed_ratio(&self) -> f64 {
+        self.refreshed as f64 / self.total() as f64
+    }
+}
+
+/// Write the answers of the history at `path`, grouped by the length of the work interval,
+/// and the length that left the user refreshed most often
+pub fn report(path: &Path, out: &mut impl Write) -> Result<()> {
+    let mut by_length = BTreeMap::<Duration, Answers>::new();
+    for record in stats::read(path)? {
+        // the lengths are rounded to the minute, extending or pausing the timer moves them
+        // by a few seconds
+        let length = Duration::from_secs((record.worked.as_secs() + 30) / 60 * 60);
+        match record.outcome {
+            Outcome::Refreshed => by_length.entry(length).or_default().refreshed += 1,
+            Outcome::Tired => by_length.entry(length).or_default().tired += 1,
+            _ => {}
+        }
+    }
+    if by_length.is_empty() {
+        writeln!(out, "No survey has been answered yet")?;
+        return Ok(());
+    }
+    writeln!(out, "Work interval  Refreshed  Answers")?;
+    for (length, answers) in &by_length {
+        writeln!(
+            out,
+            "{:<13}  {:>8.0}%  {:>7}",
+            humantime::format_duration(*length).to_string(),
+            answers.refreshed_ratio() * 100.0,
+            answers.total()
+        )?;
+    }
+    let best = by_length
+        .iter()
+        .filter(|(_, answers)| answers.total() >= MIN_ANSWERS)
+        .max_by(|(_, a), (_, b)| a.refreshed_ratio().total_cmp(&b.refreshed_ratio()));
+    match best {
+        Some((length, _)) => writeln!(
+            out,
+            "Suggestion: work intervals of {} left you refreshed most often",
+            humantime::format_duration(*length)
+        )?,
+        None => writeln!(
+            out,
+            "Answer at least {MIN_ANSWERS} surveys for a length to get a suggestion"
+        )?,
+    }
+    Ok(())
+}
+
+impl Passata {
+    /// Ask whether the break that just ended was refreshing, after a fraction of the breaks
+    pub fn ask_survey(&mut self) {
+        let fraction = self.config.survey.fraction;
+        if self.bench || fraction <= 0.0 {
+            return;
+        }
+        // a fresh clock reading is random enough to pick the breaks
+        let draw = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos() as f64
+            / 1e9;
+        if draw >= fraction {
+            return;
+        }
+        self.survey_worked = Some(self.worked_before_break);
+        let mut notification = Notification::new();
+        notification
+            .summary(&self.tr("feeling-refreshed"))
+            .body(&self.tr("how-was-the-break"))
+            .action("refreshed", "👍")
+            .action("tired", "👎");
+        self.notify(notification);
+    }
+
+    /// Record the answer to the survey shown last, if it has not been answered yet
+    pub fn answer_survey(&mut self, refreshed: bool) {
+        if let Some(worked) = self.survey_worked.take() {
+            self.record_survey(refreshed, worked);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Follow the suspends announced by logind through PrepareForSleep. Depending on the policy the
+//! time spent suspended during a work interval counts as work, is left out, or counts as the
+//! break that follows; in every case the timer is checked as soon as the system wakes up,
+//! instead of leaving the old deadline up to half a minute. A delay inhibitor holds the suspend
+//! back until the daemon has handled it.
+
+use std::{
+    sync::mpsc::{self, SyncSender},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use color_eyre::Result;
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+use zbus::{blocking::Connection, proxy, zvariant::OwnedFd};
+
+use crate::{events::Event, timer::Phase, Passata};
+
+/// What the time spent suspended during a work interval counts as
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SuspendPolicy {
+    /// Keep counting it as work, following the wall clock
+    #[default]
+    Work,
+    /// Leave it out, the work interval goes on from where it was
+    Pause,
+    /// Leave it out, and start a new work interval if the system stayed suspended for as long
+    /// as the break that follows it
+    Break,
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1",
+    gen_async = false
+)]
+trait Manager {
+    /// Take an inhibitor lock, held until the returned descriptor is closed
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    /// Sent with `start` set before suspending or hibernating, and unset after resuming
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+}
+
+/// Start following the suspends of the system
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let connection = Connection::system()?;
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg((sleeping, handled)) => {
+                state.sleep_changed(sleeping);
+                let _ = handled.send(());
+            }
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = follow_sleep(&connection, sender) {
+            warn!("stopped following the suspend: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+/// Longest time the suspend is held back waiting for the daemon, logind itself gives up after
+/// InhibitDelayMaxSec
+const HANDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn delay_sleep(manager: &ManagerProxy) -> Option<OwnedFd> {
+    manager
+        .inhibit(
+            "sleep",
+            "passata",
+            "Pausing the timer before suspending",
+            "delay",
+        )
+        .inspect_err(|err| warn!("taking the sleep delay lock: {err}"))
+        .ok()
+}
+
+fn follow_sleep(connection: &Connection, sender: Sender<(bool, SyncSender<()>)>) -> Result<()> {
+    let manager = ManagerProxy::new(connection)?;
+    let signals = manager.receive_prepare_for_sleep()?;
+    let mut lock = delay_sleep(&manager);
+    for signal in signals {
+        let start = signal.args()?.start;
+        let (handled, wait) = mpsc::sync_channel(1);
+        sender.send((start, handled))?;
+        if start {
+            let _ = wait.recv_timeout(HANDLE_TIMEOUT);
+            // closing the descriptor lets the suspend go on
+            drop(lock.take());
+        } else if lock.is_none() {
+            lock = delay_sleep(&manager);
+        }
+    }
+    Ok(())
+}
+
+impl Passata {
+    fn sleep_changed(&mut self, sleeping: bool) {
+        let policy = self.config.suspend_policy;
+        if sleeping {
+            debug!("suspending");
+            // the monotonic clock stops during the suspend, only the wall clock measures it
+            self.suspended_at = Some(SystemTime::now());
+            if self.phase == Phase::Work && policy != SuspendPolicy::Work {
+                self.pause();
+            }
+            return;
+        }
+        let suspended_for = self
+            .suspended_at
+            .take()
+            .and_then(|at| at.elapsed().ok())
+            .unwrap_or_default();
+        debug!("resumed from a suspend of {suspended_for:?}");
+        if self.phase != Phase::Work || policy == SuspendPolicy::Work {
+            if let Err(err) = self.check_timer() {
+                error!("recovering the timer: {err:?}");
+            }
+            return;
+        }
+        let next_break = self.phase_duration(self.next_event, self.next_break_type.as_deref());
+        if policy == SuspendPolicy::Break && suspended_for >= next_break {
+            self.emit(Event::BreakTakenWhileSuspended { suspended_for });
+            self.credit_break();
+        }
+        if !self.can_resume() {
+            self.publish_status();
+            return;
+        }
+        if let Err(err) = self.resume() {
+            error!("resuming the timer: {err:?}");
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Team mode: instances of passata on the same network take their breaks together, for pair
+//! programming. When a break starts, it is announced over UDP to the peers, and the peers
+//! still working start theirs right away. The peers can also all talk to a relay, `passata
+//! relay`, forwarding each message to the others, when they cannot reach each other directly.
+//! Every message is signed with the secret shared by the team and dated, the others are
+//! dropped, by the relay too.
+
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::{eyre::WrapErr, Result};
+use hmac::{Hmac, Mac};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use smithay_client_toolkit::reexports::calloop::{
+    generic::Generic,
+    timer::{TimeoutAction, Timer},
+    Interest, LoopHandle, Mode, PostAction,
+};
+
+use crate::{timer::Phase, Passata};
+
+/// How often the peers and the relay are told that this instance is around
+const HELLO_INTERVAL: Duration = Duration::from_secs(30);
+/// Silence after which the relay forgets a peer
+const RELAY_TIMEOUT: Duration = Duration::from_secs(3 * 30);
+const MAX_MESSAGE: usize = 1024;
+/// Age after which a message is dropped, so that it cannot be replayed later
+const MAX_AGE: Duration = Duration::from_secs(60);
+/// Length of the HMAC-SHA256 tag prefixing each message
+const TAG_LEN: usize = 32;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct TeamConfig {
+    /// Address to receive the messages of the peers on; team mode is off when not set
+    pub listen: Option<SocketAddr>,
+    /// Peers, or relay, the breaks are announced to
+    pub peers: Vec<SocketAddr>,
+    /// Name of the team, the messages of the other teams are ignored
+    pub name: String,
+    /// Secret shared by the team and its relay, signing the messages
+    pub secret: Option<String>,
+}
+
+impl Default for TeamConfig {
+    fn default() -> Self {
+        Self {
+            listen: None,
+            peers: Vec::new(),
+            name: "passata".to_owned(),
+            secret: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "message")]
+enum Message {
+    /// Still around, keeping this instance known to the relay
+    Hello {
+        team: String,
+    },
+    BreakStarted {
+        team: String,
+    },
+}
+
+/// Message as sent, dated to refuse the replayed ones
+#[derive(Serialize, Deserialize)]
+struct Dated {
+    /// Seconds since the epoch
+    sent: u64,
+    #[serde(flatten)]
+    message: Message,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+fn mac(secret: &str) -> Hmac<Sha256> {
+    Hmac::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length")
+}
+
+/// Date `message` and prefix it with its tag
+fn seal(secret: &str, message: Message) -> Result<Vec<u8>> {
+    let payload = serde_json::to_vec(&Dated {
+        sent: unix_now(),
+        message,
+    })?;
+    let mut mac = mac(secret);
+    mac.update(&payload);
+    let mut packet = mac.finalize().into_bytes().to_vec();
+    packet.extend_from_slice(&payload);
+    Ok(packet)
+}
+
+/// The payload of `packet` if it has been signed with `secret`, and is recent
+fn open(secret: &str, packet: &[u8]) -> Option<Dated> {
+    if packet.len() < TAG_LEN {
+        return None;
+    }
+    let (tag, payload) = packet.split_at(TAG_LEN);
+    let mut mac = mac(secret);
+    mac.update(payload);
+    mac.verify_slice(tag).ok()?;
+    let dated: Dated = serde_json::from_slice(payload).ok()?;
+    (unix_now().abs_diff(dated.sent) <= MAX_AGE.as_secs()).then_some(dated)
+}
+
+/// Listen for the messages of the peers on `address`, signed with `secret`, and greet them
+/// regularly
+pub fn listen(
+    address: SocketAddr,
+    secret: String,
+    loop_handle: &LoopHandle<'static, Passata>,
+) -> Result<UdpSocket> {
+    let socket =
+        UdpSocket::bind(address).wrap_err_with(|| format!("binding the address {address}"))?;
+    socket.set_nonblocking(true)?;
+    let sender = socket.try_clone()?;
+    loop_handle
+        .insert_source(
+            Generic::new(socket, Interest::READ, Mode::Level),
+            |_, socket, state| {
+                let mut buf = [0; MAX_MESSAGE];
+                loop {
+                    match socket.recv_from(&mut buf) {
+                        Ok((len, from)) => match open(&secret, &buf[..len]) {
+                            Some(dated) => state.team_message(dated.message, from),
+                            None => debug!("dropping an unsigned or old team message from {from}"),
+                        },
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|e| e.error)?;
+    loop_handle
+        .insert_source(Timer::immediate(), |_, _, state: &mut Passata| {
+            let team = state.config.team.name.clone();
+            state.send_team(Message::Hello { team });
+            TimeoutAction::ToDuration(HELLO_INTERVAL)
+        })
+        .map_err(|e| e.error)?;
+    Ok(sender)
+}
+
+/// Forward every message signed with `secret` received on `address` to the other peers heard
+/// from lately; the peers are only known through their signed messages, so that the relay
+/// cannot be used to reflect traffic to anyone else
+pub fn relay(address: SocketAddr, secret: &str) -> Result<()> {
+    let socket =
+        UdpSocket::bind(address).wrap_err_with(|| format!("binding the address {address}"))?;
+    println!("relaying the team messages on {address}");
+    let mut peers = HashMap::<SocketAddr, Instant>::new();
+    let mut buf = [0; MAX_MESSAGE];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf)?;
+        if open(secret, &buf[..len]).is_none() {
+            continue;
+        }
+        if peers.insert(from, Instant::now()).is_none() {
+            println!("new peer {from}");
+        }
+        peers.retain(|_, seen| seen.elapsed() < RELAY_TIMEOUT);
+        for peer in peers.keys().filter(|peer| **peer != from) {
+            if let Err(err) = socket.send_to(&buf[..len], peer) {
+                eprintln!("relaying to {peer}: {err}");
+            }
+        }
+    }
+}
+
+impl Passata {
+    fn send_team(&self, message: Message) {
+        let (Some(socket), Some(secret)) = (&self.team_socket, &self.config.team.secret) else {
+            return;
+        };
+        let Ok(payload) = seal(secret, message) else {
+            return;
+        };
+        for peer in &self.config.team.peers {
+            if let Err(err) = socket.send_to(&payload, peer) {
+                debug!("sending to the peer {peer}: {err}");
+            }
+        }
+    }
+
+    /// Tell the peers that a break started, so that they join it
+    pub fn announce_break(&self) {
+        let team = self.config.team.name.clone();
+        self.send_team(Message::BreakStarted { team });
+    }
+
+    fn team_message(&mut self, message: Message, from: SocketAddr) {
+        let Message::BreakStarted { team } = message else {
+            return;
+        };
+        if team != self.config.team.name {
+            return;
+        }
+        // already on a break, paused or pending, or holding the breaks back for a reason of its own
+        if self.phase != Phase::Work
+            || self.is_paused()
+            || self.user_paused
+            || self.day_off
+            || self.focus.is_some()
+            || self.break_deferral_reason().is_some()
+        {
+            debug!("{from} started a break, not joining it");
+            return;
+        }
+        info!("{from} started a break, joining it");
+        // the work interval is cut short like on a skip
+        if let Err(err) = self.skip() {
+            error!("starting the break of the team: {err:?}");
+        }
+    }
+}
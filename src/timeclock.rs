@@ -0,0 +1,107 @@
+//! Feed the work intervals into the existing time tracking: each one is appended to a file as
+//! it ends, as an Emacs org-clock entry or in the timeclock format read by ledger and hledger.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use color_eyre::{eyre::WrapErr, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    stats::{Outcome, Record},
+    Passata,
+};
+
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeTrackingFormat {
+    /// Check-in and check-out lines of ledger and hledger
+    #[default]
+    Timeclock,
+    /// A heading with its CLOCK line, for the clock tables of org-mode
+    Org,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct TimeTrackingConfig {
+    /// File the work intervals are appended to; none are written when not set
+    pub file: Option<PathBuf>,
+    pub format: TimeTrackingFormat,
+    /// Account of the timeclock entries, or heading of the org ones, followed by the task
+    pub account: String,
+}
+
+impl Default for TimeTrackingConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            format: TimeTrackingFormat::default(),
+            account: "passata".to_owned(),
+        }
+    }
+}
+
+/// The entry of the work interval of `record`, which ran for its time worked before it ended
+fn entry(record: &Record, format: TimeTrackingFormat, account: &str) -> String {
+    let ended = DateTime::<Local>::from(record.ended);
+    let started = DateTime::<Local>::from(record.ended - record.worked);
+    match format {
+        TimeTrackingFormat::Timeclock => {
+            let account = match &record.task {
+                Some(task) => format!("{account}:{task}"),
+                None => account.to_owned(),
+            };
+            format!(
+                "i {} {account}\no {}\n",
+                started.format("%Y/%m/%d %H:%M:%S"),
+                ended.format("%Y/%m/%d %H:%M:%S")
+            )
+        }
+        TimeTrackingFormat::Org => {
+            let heading = match &record.task {
+                Some(task) => format!("{account}: {task}"),
+                None => account.to_owned(),
+            };
+            let minutes = record.worked.as_secs() / 60;
+            format!(
+                "* {heading}\n  CLOCK: [{}]--[{}] => {:2}:{:02}\n",
+                started.format("%Y-%m-%d %a %H:%M"),
+                ended.format("%Y-%m-%d %a %H:%M"),
+                minutes / 60,
+                minutes % 60
+            )
+        }
+    }
+}
+
+fn append(path: &Path, entry: &str) -> Result<()> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(entry.as_bytes()))
+        .wrap_err_with(|| format!("writing to {path:?}"))
+}
+
+impl Passata {
+    /// Append the work interval of `record` to the time tracking file, when there is one
+    pub fn track_time(&mut self, record: &Record) {
+        let config = &self.config.time_tracking;
+        let Some(file) = &config.file else {
+            return;
+        };
+        if !matches!(record.outcome, Outcome::Completed | Outcome::Abandoned) {
+            return;
+        }
+        if let Err(err) = append(file, &entry(record, config.format, &config.account)) {
+            warn!("writing the work interval to the time tracking: {err:?}");
+            self.health.history_failures += 1;
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use color_eyre::{eyre::ContextCompat, Result};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::reexports::calloop::timer::{TimeoutAction, Timer};
+
+use crate::{events::Event, lock, prompt::BreakPrompt, workspaces::WorkspaceMode, Passata};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Work => "Work",
+            Phase::ShortBreak => "Short break",
+            Phase::LongBreak => "Long break",
+        }
+    }
+}
+
+impl Passata {
+    /// Arm the timer so that the next phase starts after `duration`, replacing the
+    /// currently armed timer if any
+    pub fn schedule(&mut self, duration: Duration) -> Result<()> {
+        if let Some(token) = self.timer_token.take() {
+            self.loop_handle.remove(token);
+        }
+        self.timer_started = Instant::now();
+        self.timer_started_wall = SystemTime::now();
+        self.timer_duration = duration;
+        let token = self
+            .loop_handle
+            .insert_source(
+                Timer::from_duration(duration),
+                |_instant, _, state: &mut Passata| {
+                    state.timer_token = None;
+                    if let Err(err) = state.timer_expired() {
+                        error!("starting the next phase: {err:?}");
+                    }
+                    TimeoutAction::Drop
+                },
+            )
+            .map_err(|e| e.error)?;
+        self.timer_token = Some(token);
+        Ok(())
+    }
+
+    /// Time left before the current phase ends
+    pub fn remaining(&self) -> Duration {
+        self.paused_remaining.unwrap_or_else(|| {
+            self.timer_duration
+                .saturating_sub(self.timer_started.elapsed())
+        })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_remaining.is_some()
+    }
+
+    /// When the current phase ends on the wall clock, unless the timer is paused
+    pub fn phase_ends_at(&self) -> Option<SystemTime> {
+        if self.is_paused() {
+            return None;
+        }
+        Some(self.timer_started_wall + self.timer_duration)
+    }
+
+    /// Why the break should not start right now, if anything is holding it back
+    pub fn break_deferral_reason(&self) -> Option<String> {
+        if self.in_quiet_workspace(WorkspaceMode::Defer) {
+            return Some(format!(
+                "workspace {:?} is focused",
+                self.focused_workspace.as_deref().unwrap_or_default()
+            ));
+        }
+        if self.session_inhibited {
+            return Some("an application inhibits the idle of the session".to_owned());
+        }
+        if self.mic_in_use {
+            return Some("the microphone is in use".to_owned());
+        }
+        if self.in_meeting {
+            return Some("a meeting window is focused".to_owned());
+        }
+        if self.in_typing_grace() {
+            return Some("the user is typing".to_owned());
+        }
+        None
+    }
+
+    pub fn timer_expired(&mut self) -> Result<()> {
+        if self.phase == Phase::Work {
+            if self.skip_break_for_focus()? {
+                return Ok(());
+            }
+            self.start_typing_grace();
+            if let Some(reason) = self.break_deferral_reason() {
+                if !self.break_deferred {
+                    self.break_deferred = true;
+                    self.emit(Event::BreakDeferred { reason });
+                    self.limit_deferral();
+                }
+                return Ok(());
+            }
+        }
+        self.next_phase()
+    }
+
+    /// Start the deferred break anyway once it has been held back for max-deferral
+    fn limit_deferral(&mut self) {
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(self.config.max_deferral),
+            |_, _, state: &mut Passata| {
+                state.deferral_token = None;
+                info!("the break has been deferred for too long, starting it");
+                if let Err(err) = state.next_phase() {
+                    error!("starting the deferred break: {err:?}");
+                }
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(token) => self.deferral_token = Some(token),
+            Err(err) => warn!("the deferral of the break will not be limited: {err}"),
+        }
+    }
+
+    /// The break is not deferred anymore
+    pub fn end_deferral(&mut self) {
+        self.break_deferred = false;
+        if let Some(token) = self.deferral_token.take() {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// Start the deferred break, once nothing is holding it back anymore
+    pub fn check_deferred_break(&mut self) -> Result<()> {
+        if self.break_deferred && self.break_deferral_reason().is_none() {
+            self.end_deferral();
+            self.next_phase()?;
+        }
+        Ok(())
+    }
+
+    /// Start the phase stored in `next_event` and compute the one after it
+    pub fn next_phase(&mut self) -> Result<()> {
+        self.advance_phase(false)
+    }
+
+    /// Skip to the next phase on request of the user: a work interval cut short is recorded as
+    /// abandoned, and a pending one is started instead of going to a break with no work done
+    pub fn skip(&mut self) -> Result<()> {
+        if self.phase == Phase::Work && self.user_paused && self.remaining() >= self.phase_length {
+            return self.start_work();
+        }
+        self.advance_phase(true)
+    }
+
+    fn advance_phase(&mut self, skipped: bool) -> Result<()> {
+        self.dismiss_break_prompt();
+        self.stop_break_command();
+        if self.phase == Phase::Work {
+            let worked = self.phase_length.saturating_sub(self.remaining());
+            self.worked_before_break = worked;
+            if skipped && !self.remaining().is_zero() {
+                self.emit(Event::PomodoroAbandoned { worked });
+            } else {
+                self.emit(Event::PomodoroCompleted { worked });
+                self.completed_pomodoros += 1;
+            }
+        } else {
+            // without idle detection there is no telling whether the break has been taken
+            if self.config.idle_pause.is_some() || self.config.end_break_on_activity {
+                // still away from the input at the end of the break
+                let away = self.break_away_since.is_some_and(|away_since| {
+                    away_since.elapsed() >= self.config.end_break_absence
+                });
+                self.emit(Event::BreakEnded {
+                    taken: self.break_idled || away,
+                    length: self.phase_length.saturating_sub(self.remaining()),
+                });
+            }
+            self.ask_survey();
+        }
+        self.paused_remaining = None;
+        self.user_paused = false;
+        self.end_deferral();
+        self.end_typing_grace();
+        self.phase = self.next_event;
+        self.break_idled = self.idle;
+        // only the absence during the break counts
+        self.break_away_since = self.input_idle.then(Instant::now);
+        self.break_type = match self.phase {
+            Phase::Work => None,
+            Phase::ShortBreak | Phase::LongBreak => self.next_break_type.take(),
+        };
+        self.pick_activity();
+        self.break_quiet = self.phase != Phase::Work && self.break_is_quiet();
+        if self.phase == Phase::Work {
+            self.start_work_interval()?;
+        } else {
+            self.start_break()?;
+        }
+        self.publish_status();
+        Ok(())
+    }
+
+    /// Reset what is kept for each work interval and choose the break after the one starting now
+    fn reset_work_interval(&mut self) {
+        self.stopwatch = false;
+        self.snoozes = 0;
+        self.end_deferral();
+        self.end_typing_grace();
+        self.select_time_range();
+        self.advance_cycle();
+        self.reset_intensity();
+    }
+
+    /// Start a new work interval, aligned to the wall clock, waiting for the user when the work
+    /// does not start on its own
+    pub fn start_work_interval(&mut self) -> Result<()> {
+        self.reset_work_interval();
+        self.phase_length = self.work_interval();
+        let duration = self.align_phase_length(self.phase_length);
+        self.schedule(duration)?;
+        self.stop_escalation();
+        self.hide_overlay();
+        self.restore_outputs();
+        if !self.config.auto_start_work || self.config.break_prompt == BreakPrompt::Dialog {
+            // the work time only counts once the user is back
+            if let Some(token) = self.timer_token.take() {
+                self.loop_handle.remove(token);
+            }
+            self.paused_remaining = Some(duration);
+            self.user_paused = true;
+            self.emit(Event::WorkPending);
+        } else if self.off_work_app || self.inhibited || self.battery_held() {
+            // held back from the start, as it would have been during the work interval
+            self.pause();
+        }
+        // emitted once armed, announcing when the next break is due
+        self.emit(Event::WorkStarted);
+        Ok(())
+    }
+
+    /// Start the break stored in `phase`, with the named break `break_type` if any
+    fn start_break(&mut self) -> Result<()> {
+        self.next_event = Phase::Work;
+        let duration = if let Some(name) = self.break_type.clone() {
+            let duration = self.phase_duration(self.phase, Some(&name));
+            self.emit(Event::NamedBreakStarted { name });
+            duration
+        } else if self.phase == Phase::LongBreak {
+            self.emit(Event::LongBreakStarted);
+            self.long_break().context("no long break is configured")?
+        } else {
+            let counter = self
+                .short_breaks_before_long_break()
+                .map(|total| (self.current_short_breaks.min(total), total + 1));
+            self.emit(Event::ShortBreakStarted { counter });
+            self.short_break_after(self.worked_before_break)
+        };
+        // a stopwatch session is followed by a break proportional to it, whichever break is due
+        let duration = if self.stopwatch {
+            self.short_break_after(self.worked_before_break)
+        } else {
+            duration
+        };
+        // the breaks skipped in a row are paid off with a longer one
+        self.phase_length = duration + self.break_debt_extension();
+        self.schedule(self.phase_length)?;
+        if !self.break_quiet {
+            if self.config.dimming.enabled {
+                self.dim_outputs();
+            }
+            if self.phase == Phase::LongBreak
+                && self.config.lock_on_long_break
+                && !self.bench
+                && !self.enforcement_exempt()
+            {
+                lock::lock_session();
+            }
+            if !self.bench {
+                self.start_break_command();
+            }
+            self.start_escalation();
+        }
+        self.announce_break();
+        Ok(())
+    }
+
+    /// Change the time left in the current phase, re-arming the timer if it is running
+    pub fn set_remaining(&mut self, remaining: Duration) -> Result<()> {
+        let elapsed = self.phase_length.saturating_sub(self.remaining());
+        self.phase_length = elapsed + remaining;
+        if self.is_paused() {
+            self.paused_remaining = Some(remaining);
+        } else {
+            self.schedule(remaining)?;
+        }
+        self.emit(Event::RemainingChanged { remaining });
+        self.publish_status();
+        Ok(())
+    }
+
+    /// Stop the timer, keeping the time left in the current phase
+    pub fn pause(&mut self) {
+        if self.is_paused() {
+            return;
+        }
+        self.paused_remaining = Some(self.remaining());
+        if let Some(token) = self.timer_token.take() {
+            self.loop_handle.remove(token);
+        }
+        self.emit(Event::Paused);
+        self.publish_status();
+    }
+
+    /// Pause or resume the timer on request of the user; a manual pause is not lifted when
+    /// coming back from idle
+    pub fn toggle_pause(&mut self) {
+        if self.is_paused() {
+            self.user_paused = false;
+            if let Err(err) = self.resume() {
+                error!("resuming the timer: {err:?}");
+            }
+        } else {
+            self.user_paused = true;
+            self.pause();
+        }
+    }
+
+    /// Start the work interval held after the break, when auto-start-work is disabled
+    pub fn start_work(&mut self) -> Result<()> {
+        if self.phase != Phase::Work || !self.user_paused {
+            return Ok(());
+        }
+        self.user_paused = false;
+        self.resume()
+    }
+
+    /// Throw away the work done in the current interval and start it over, staying paused if
+    /// the timer is
+    pub fn reset_work(&mut self) -> Result<()> {
+        if self.phase != Phase::Work {
+            return Ok(());
+        }
+        let worked = self.phase_length.saturating_sub(self.remaining());
+        self.emit(Event::PomodoroAbandoned { worked });
+        self.reset_intensity();
+        self.end_deferral();
+        self.end_typing_grace();
+        self.stopwatch = false;
+        self.snoozes = 0;
+        self.phase_length = self.work_interval();
+        if self.is_paused() {
+            self.paused_remaining = Some(self.phase_length);
+        } else {
+            let remaining = self.align_phase_length(self.phase_length);
+            self.schedule(remaining)?;
+        }
+        self.publish_status();
+        Ok(())
+    }
+
+    /// The break after this work interval has been taken some other way: record the work done
+    /// so far, then start the work interval over, followed by the break after that one
+    pub fn credit_break(&mut self) {
+        if self.phase == Phase::Work {
+            let worked = self.phase_length.saturating_sub(self.remaining());
+            if !worked.is_zero() {
+                self.emit(Event::PomodoroAbandoned { worked });
+            }
+        }
+        self.settle_break(true);
+        self.reset_work_interval();
+        self.phase_length = self.work_interval();
+        if self.is_paused() {
+            self.paused_remaining = Some(self.phase_length);
+        }
+    }
+
+    /// What holds the work time back, if anything
+    pub fn resume_hold(&self) -> Option<&'static str> {
+        if self.user_paused {
+            Some("the timer was paused by the user")
+        } else if self.idle {
+            Some("the user is idle")
+        } else if self.inhibited {
+            Some("an idle inhibitor is active")
+        } else if self.locked_since.is_some() {
+            Some("the screen is locked")
+        } else if self.off_work_app {
+            Some("the focused application is not a work app")
+        } else if self.battery_held() {
+            Some("running on battery")
+        } else {
+            None
+        }
+    }
+
+    /// Whether the timer can be resumed, nothing holding the work time back
+    pub fn can_resume(&self) -> bool {
+        self.resume_hold().is_none()
+    }
+
+    /// Restart the timer from where it was paused
+    pub fn resume(&mut self) -> Result<()> {
+        let Some(time_left) = self.paused_remaining.take() else {
+            return Ok(());
+        };
+        self.end_day_off();
+        self.dismiss_break_prompt();
+        self.schedule(time_left)?;
+        self.emit(Event::Resumed { time_left });
+        self.publish_status();
+        Ok(())
+    }
+}
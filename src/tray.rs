@@ -0,0 +1,280 @@
+//! StatusNotifierItem tray icon showing the minutes left in the current phase, drawn into the
+//! icon itself so that the countdown can be read without hovering it.
+
+use std::{process, time::Duration};
+
+use color_eyre::Result;
+use log::warn;
+use smithay_client_toolkit::reexports::calloop::timer::{TimeoutAction, Timer};
+use zbus::{
+    block_on,
+    blocking::{connection, Connection},
+    interface, proxy, SignalContext,
+};
+
+use crate::{i18n::Language, status::Status, timer::Phase, Passata};
+
+const OBJECT_PATH: &str = "/StatusNotifierItem";
+const ICON_SIZE: usize = 32;
+/// Each pixel of the font is drawn as a square of this size
+const FONT_SCALE: usize = 4;
+const FONT_WIDTH: usize = 3;
+const FONT_HEIGHT: usize = 5;
+/// 3x5 bitmaps of the digits, one row for each byte
+const DIGITS: [[u8; FONT_HEIGHT]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// ARGB32 pixmap, in network byte order as the spec requires
+type Pixmap = (i32, i32, Vec<u8>);
+
+#[proxy(
+    interface = "org.kde.StatusNotifierWatcher",
+    default_service = "org.kde.StatusNotifierWatcher",
+    default_path = "/StatusNotifierWatcher",
+    gen_async = false
+)]
+trait StatusNotifierWatcher {
+    fn register_status_notifier_item(&self, service: &str) -> zbus::Result<()>;
+}
+
+pub struct Tray {
+    connection: Connection,
+}
+
+impl Tray {
+    pub fn new(status: Status, language: Language) -> zbus::Result<Self> {
+        let name = format!("org.kde.StatusNotifierItem-{}-1", process::id());
+        let connection = connection::Builder::session()?
+            .name(name.as_str())?
+            .serve_at(OBJECT_PATH, Item::new(status, language))?
+            .build()?;
+        StatusNotifierWatcherProxy::new(&connection)?.register_status_notifier_item(&name)?;
+        Ok(Self { connection })
+    }
+
+    pub fn update(&self, status: Status) -> zbus::Result<()> {
+        let iface_ref = self
+            .connection
+            .object_server()
+            .interface::<_, Item>(OBJECT_PATH)?;
+        let mut item = iface_ref.get_mut();
+        let icon = render(&status);
+        let icon_changed = icon != item.icon;
+        item.status = status;
+        item.icon = icon;
+        if icon_changed {
+            block_on(Item::new_icon(iface_ref.signal_context()))?;
+        }
+        block_on(Item::new_tool_tip(iface_ref.signal_context()))
+    }
+}
+
+/// Time until the number of minutes shown in the icon changes
+fn until_next_minute(status: &Status) -> Duration {
+    if status.paused {
+        return Duration::from_secs(60);
+    }
+    match status.remaining().as_millis() % 60_000 {
+        0 => Duration::from_secs(60),
+        millis => Duration::from_millis(millis as u64),
+    }
+}
+
+/// Draw the minutes left, rounded up, over a square colored after the phase
+fn render(status: &Status) -> Pixmap {
+    let background: [u8; 4] = if status.paused {
+        [0xff, 0x6c, 0x70, 0x86]
+    } else {
+        match status.phase {
+            Phase::Work => [0xff, 0xe6, 0x45, 0x53],
+            Phase::ShortBreak | Phase::LongBreak => [0xff, 0x40, 0xa0, 0x2b],
+        }
+    };
+    let foreground = [0xff; 4];
+    let minutes = status.remaining().as_secs().div_ceil(60).min(99);
+    let digits = if minutes < 10 {
+        vec![minutes as usize]
+    } else {
+        vec![minutes as usize / 10, minutes as usize % 10]
+    };
+
+    let mut pixels = background.repeat(ICON_SIZE * ICON_SIZE);
+    let digit_width = FONT_WIDTH * FONT_SCALE;
+    let gap = FONT_SCALE;
+    let text_width = digits.len() * digit_width + (digits.len() - 1) * gap;
+    let left = (ICON_SIZE - text_width) / 2;
+    let top = (ICON_SIZE - FONT_HEIGHT * FONT_SCALE) / 2;
+    for (i, digit) in digits.into_iter().enumerate() {
+        let digit_left = left + i * (digit_width + gap);
+        for (row, bits) in DIGITS[digit].iter().enumerate() {
+            for col in 0..FONT_WIDTH {
+                if bits & (1 << (FONT_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for y in 0..FONT_SCALE {
+                    for x in 0..FONT_SCALE {
+                        let px = digit_left + col * FONT_SCALE + x;
+                        let py = top + row * FONT_SCALE + y;
+                        let offset = (py * ICON_SIZE + px) * 4;
+                        pixels[offset..offset + 4].copy_from_slice(&foreground);
+                    }
+                }
+            }
+        }
+    }
+    (ICON_SIZE as i32, ICON_SIZE as i32, pixels)
+}
+
+impl Passata {
+    /// Keep the countdown up to date, waking up only when the minutes shown change
+    pub fn tick_tray(&mut self) -> Result<()> {
+        let first = until_next_minute(&self.status());
+        self.loop_handle
+            .insert_source(Timer::from_duration(first), |_, _, state: &mut Passata| {
+                let status = state.status();
+                if let Some(tray) = &state.tray {
+                    if let Err(err) = tray.update(status.clone()) {
+                        warn!("updating the tray icon: {err}");
+                    }
+                }
+                TimeoutAction::ToDuration(until_next_minute(&status))
+            })
+            .map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+/// Text of the tooltip, one fact per line
+fn describe(status: &Status, language: Language) -> String {
+    let phase = match (&status.break_type, status.phase) {
+        (Some(name), _) => name.clone(),
+        (None, Phase::Work) => language.format("work", &[]),
+        (None, Phase::ShortBreak) => language.format("short-break", &[]),
+        (None, Phase::LongBreak) => language.format("long-break", &[]),
+    };
+    // the minutes rounded up, like the icon
+    let clock = Duration::from_secs(status.clock().as_secs().div_ceil(60) * 60);
+    let mut lines = vec![language.format(
+        if status.stopwatch {
+            "tray-elapsed"
+        } else {
+            "tray-left"
+        },
+        &[
+            ("phase", phase.into()),
+            ("time", humantime::format_duration(clock).to_string().into()),
+        ],
+    )];
+    if status.day_off {
+        lines.push(language.format("tray-day-off", &[]));
+    } else if status.paused {
+        lines.push(language.format("tray-paused", &[]));
+    }
+    if let Some(goal) = &status.daily_goal {
+        lines.push(language.format(
+            "today-progress",
+            &[
+                ("completed", goal.completed.into()),
+                ("goal", goal.goal.into()),
+            ],
+        ));
+    }
+    if status.streak > 1 {
+        lines.push(language.format("streak", &[("streak", status.streak.into())]));
+    }
+    if let Some(task) = &status.task {
+        lines.push(language.format("task", &[("task", task.as_str().into())]));
+    }
+    if let Some(focus) = status.focus {
+        let focus = Duration::from_secs(focus.as_secs() / 60 * 60);
+        lines.push(language.format(
+            "tray-focusing",
+            &[("time", humantime::format_duration(focus).to_string().into())],
+        ));
+    }
+    lines.join("\n")
+}
+
+struct Item {
+    status: Status,
+    icon: Pixmap,
+    language: Language,
+}
+
+impl Item {
+    fn new(status: Status, language: Language) -> Self {
+        let icon = render(&status);
+        Self {
+            status,
+            icon,
+            language,
+        }
+    }
+}
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+    fn activate(&self, _x: i32, _y: i32) {}
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {}
+
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, _delta: i32, _orientation: &str) {}
+
+    #[zbus(signal)]
+    async fn new_icon(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_tool_tip(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> &str {
+        "passata"
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> &str {
+        "passata"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn icon_pixmap(&self) -> Vec<Pixmap> {
+        vec![self.icon.clone()]
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn tool_tip(&self) -> (String, Vec<Pixmap>, String, String) {
+        (
+            String::new(),
+            Vec::new(),
+            "passata".to_owned(),
+            describe(&self.status, self.language),
+        )
+    }
+}
@@ -0,0 +1,153 @@
+//! Terminal dashboard talking to the daemon over the control socket.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Gauge, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    ipc::{self, Request, Response},
+    stats,
+    status::Status,
+    timer::Phase,
+};
+
+/// How often the countdown is redrawn
+const TICK: Duration = Duration::from_millis(250);
+/// How often the status is asked again to the daemon, the countdown runs locally in between
+const REFRESH: Duration = Duration::from_secs(5);
+const ADJUST_STEP: Duration = Duration::from_secs(60);
+
+pub fn run(socket: &Path) -> Result<()> {
+    let history = stats::history_path()?;
+    let mut dashboard = Dashboard {
+        socket,
+        history,
+        status: None,
+        completed_today: 0,
+        error: None,
+        refreshed: Instant::now(),
+    };
+    let mut terminal = ratatui::init();
+    let res = dashboard.run(&mut terminal);
+    ratatui::restore();
+    res
+}
+
+struct Dashboard<'a> {
+    socket: &'a Path,
+    history: PathBuf,
+    status: Option<Status>,
+    completed_today: usize,
+    /// Error of the last request, shown until the next one succeeds
+    error: Option<String>,
+    refreshed: Instant,
+}
+
+impl Dashboard<'_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        self.send(&Request::Status);
+        loop {
+            if self.refreshed.elapsed() >= REFRESH {
+                self.send(&Request::Status);
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(TICK)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char(' ') | KeyCode::Char('p') => self.send(&Request::TogglePause),
+                KeyCode::Char('s') => self.send(&Request::Skip),
+                KeyCode::Char('+') => self.send(&Request::Extend {
+                    duration: ADJUST_STEP,
+                }),
+                KeyCode::Char('-') => self.send(&Request::Shorten {
+                    duration: ADJUST_STEP,
+                }),
+                _ => {}
+            }
+        }
+    }
+
+    /// Send a request answered with the status and keep the result
+    fn send(&mut self, request: &Request) {
+        self.refreshed = Instant::now();
+        self.error = match ipc::send(self.socket, request) {
+            Ok(Response::Status(status)) => {
+                self.status = Some(status);
+                None
+            }
+            Ok(Response::Error { message }) => Some(message),
+            Ok(_) => Some("unexpected response from the daemon".to_owned()),
+            Err(err) => Some(format!("{err}")),
+        };
+        self.completed_today = stats::completed_today(&self.history).unwrap_or_default();
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [gauge_area, info_area, keys_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let mut info = Vec::new();
+        if let Some(status) = &self.status {
+            let remaining = Duration::from_secs(status.remaining().as_secs());
+            let ratio = if status.length.is_zero() {
+                0.0
+            } else {
+                status.elapsed().as_secs_f64() / status.length.as_secs_f64()
+            };
+            let color = match status.phase {
+                Phase::Work => Color::Red,
+                Phase::ShortBreak | Phase::LongBreak => Color::Green,
+            };
+            let title = if status.paused {
+                format!(" {} (paused) ", status.phase.name())
+            } else {
+                format!(" {} ", status.phase.name())
+            };
+            frame.render_widget(
+                Gauge::default()
+                    .block(Block::bordered().title(title))
+                    .gauge_style(Style::default().fg(color))
+                    .ratio(ratio.clamp(0.0, 1.0))
+                    .label(format!("{} left", humantime::format_duration(remaining))),
+                gauge_area,
+            );
+            info.push(Line::from(format!(
+                "{} pomodoros completed today, {} since the daemon started",
+                self.completed_today, status.completed_pomodoros
+            )));
+        } else {
+            frame.render_widget(Block::bordered().title(" passata "), gauge_area);
+        }
+        if let Some(error) = &self.error {
+            info.push(Line::from(error.as_str()).red());
+        }
+        frame.render_widget(Paragraph::new(info), info_area);
+        frame.render_widget(
+            Paragraph::new("space pause/resume · s skip · +/- one minute more/less · q quit").dim(),
+            keys_area,
+        );
+    }
+}
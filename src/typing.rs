@@ -0,0 +1,61 @@
+//! Hold the break back while the user is typing, until the first micro-pause of the input or
+//! for grace-typing at most, so that it does not cut a sentence in half.
+
+use std::time::Duration;
+
+use log::{debug, error, warn};
+use smithay_client_toolkit::reexports::calloop::timer::{TimeoutAction, Timer};
+
+use crate::Passata;
+
+/// Inactivity of the input that counts as a micro-pause in the typing
+pub const MICRO_PAUSE: Duration = Duration::from_secs(2);
+
+impl Passata {
+    /// The input went quiet for MICRO_PAUSE, or resumed
+    pub fn typing_changed(&mut self, idled: bool) {
+        self.typing = !idled;
+        if idled && self.typing_grace_token.is_some() {
+            debug!("micro-pause in the typing");
+            if let Err(err) = self.check_deferred_break() {
+                error!("starting the deferred break: {err:?}");
+            }
+        }
+    }
+
+    /// Give the user grace-typing to reach a micro-pause, when the break is due while typing
+    pub fn start_typing_grace(&mut self) {
+        let Some(grace) = self.config.grace_typing else {
+            return;
+        };
+        if !self.typing || self.break_deferred || self.typing_grace_token.is_some() {
+            return;
+        }
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(grace),
+            |_, _, state: &mut Passata| {
+                state.typing_grace_token = None;
+                if let Err(err) = state.check_deferred_break() {
+                    error!("starting the deferred break: {err:?}");
+                }
+                TimeoutAction::Drop
+            },
+        );
+        match res {
+            Ok(token) => self.typing_grace_token = Some(token),
+            Err(err) => warn!("arming the typing grace: {}", err.error),
+        }
+    }
+
+    /// Close the grace window, the break is not due anymore
+    pub fn end_typing_grace(&mut self) {
+        if let Some(token) = self.typing_grace_token.take() {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// The user is typing within the grace window
+    pub fn in_typing_grace(&self) -> bool {
+        self.typing && self.typing_grace_token.is_some()
+    }
+}
@@ -0,0 +1,61 @@
+//! Live countdown on a single line of the terminal, redrawn in place every second until
+//! interrupted: a lighter alternative to the TUI, e.g. for a tmux pane.
+
+use std::{
+    io::{self, Write},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+
+use crate::{
+    ipc::{self, Request, Response},
+    status::Status,
+};
+
+const TICK: Duration = Duration::from_secs(1);
+/// How often the status is asked again to the daemon
+const REFRESH: Duration = Duration::from_secs(5);
+
+pub fn run(socket: &Path) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    let mut status = None;
+    let mut refreshed: Option<Instant> = None;
+    loop {
+        if !refreshed.is_some_and(|refreshed| refreshed.elapsed() < REFRESH) {
+            status = match ipc::send(socket, &Request::Status) {
+                Ok(Response::Status(status)) => Some(status),
+                _ => None,
+            };
+            refreshed = Some(Instant::now());
+        }
+        // back to the start of the line and clear it, the new line can be shorter
+        write!(stdout, "\r\x1b[2K{}", line(status.as_ref()))?;
+        stdout.flush()?;
+        thread::sleep(TICK);
+    }
+}
+
+fn line(status: Option<&Status>) -> String {
+    let Some(status) = status else {
+        return "passata is not running".to_owned();
+    };
+    let clock = status.clock().as_secs();
+    let mut line = format!(
+        "{} {:02}:{:02}",
+        status.break_type.as_deref().unwrap_or(status.phase.name()),
+        clock / 60,
+        clock % 60
+    );
+    if status.day_off {
+        line.push_str(" (day off)");
+    } else if status.paused {
+        line.push_str(" (paused)");
+    }
+    if let Some(goal) = &status.daily_goal {
+        line.push_str(&format!(" · {}/{} today", goal.completed, goal.goal));
+    }
+    line
+}
@@ -0,0 +1,65 @@
+//! Periodic cross-check of the phase timer against the monotonic and the wall clocks, recovering
+//! when its deadline has been missed (event loop stall) or the wall clock moved on without it
+//! (suspend, clock jump).
+
+use std::time::Duration;
+
+use color_eyre::Result;
+use log::{error, warn};
+use smithay_client_toolkit::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
+
+use crate::Passata;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Drift tolerated before recovering, to not fight the normal scheduling delays
+const MARGIN: Duration = Duration::from_secs(10);
+
+pub fn start(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    loop_handle
+        .insert_source(
+            Timer::from_duration(CHECK_INTERVAL),
+            |_, _, state: &mut Passata| {
+                if let Err(err) = state.check_timer() {
+                    error!("recovering the timer: {err:?}");
+                }
+                TimeoutAction::ToDuration(CHECK_INTERVAL)
+            },
+        )
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+impl Passata {
+    pub fn check_timer(&mut self) -> Result<()> {
+        if self.timer_token.is_none() || self.is_paused() {
+            return Ok(());
+        }
+        let elapsed = self.timer_started.elapsed();
+        // a wall clock going backwards is not an issue, the monotonic one is trusted
+        let wall_elapsed = self.timer_started_wall.elapsed().unwrap_or_default();
+        if elapsed > self.timer_duration + MARGIN {
+            warn!(
+                "the timer missed its deadline by {:?}, starting the next phase",
+                elapsed - self.timer_duration
+            );
+            self.health.missed_deadlines += 1;
+            if let Some(token) = self.timer_token.take() {
+                self.loop_handle.remove(token);
+            }
+            self.timer_expired()?;
+        } else if wall_elapsed > elapsed + MARGIN {
+            let remaining = self.timer_duration.saturating_sub(wall_elapsed);
+            warn!(
+                "the wall clock moved {:?} ahead of the timer, rescheduling it with {remaining:?} left",
+                wall_elapsed - elapsed
+            );
+            self.health.clock_jumps += 1;
+            self.schedule(remaining)?;
+            self.publish_status();
+        }
+        Ok(())
+    }
+}
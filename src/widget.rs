@@ -0,0 +1,253 @@
+//! Small widget in a corner of the screen, showing the progress of the current phase as a ring.
+//! It is drawn on the globals of the overlay, above the windows, and lets the pointer through.
+
+use std::{f32::consts::PI, time::Duration};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use smithay_client_toolkit::{
+    compositor::Region,
+    reexports::calloop::{
+        timer::{TimeoutAction, Timer},
+        RegistrationToken,
+    },
+    shell::{
+        wlr_layer::{Anchor, KeyboardInteractivity, Layer, LayerSurface},
+        WaylandSurface,
+    },
+};
+
+use crate::{
+    overlay::{blend, Color},
+    timer::Phase,
+    Passata,
+};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    fn anchor(self) -> Anchor {
+        match self {
+            Corner::TopLeft => Anchor::TOP | Anchor::LEFT,
+            Corner::TopRight => Anchor::TOP | Anchor::RIGHT,
+            Corner::BottomLeft => Anchor::BOTTOM | Anchor::LEFT,
+            Corner::BottomRight => Anchor::BOTTOM | Anchor::RIGHT,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct WidgetConfig {
+    /// Show the widget when the daemon starts, `passata widget` shows or hides it anyway
+    pub enabled: bool,
+    pub corner: Corner,
+    /// Width and height of the widget in pixels
+    pub size: u32,
+    /// Distance from the edges of the screen in pixels
+    pub margin: i32,
+    /// Width of the ring in pixels
+    pub thickness: u32,
+    /// Name of the output to show the widget on, e.g. "DP-1"; the compositor chooses when not
+    /// set
+    pub output: Option<String>,
+    pub work_color: Color,
+    pub break_color: Color,
+    pub paused_color: Color,
+    /// Color of the part of the ring still to go
+    pub track_color: Color,
+}
+
+impl Default for WidgetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            corner: Corner::BottomRight,
+            size: 48,
+            margin: 16,
+            thickness: 6,
+            output: None,
+            work_color: Color([0x88, 0xc0, 0xd0, 0xff]),
+            break_color: Color([0xa3, 0xbe, 0x8c, 0xff]),
+            paused_color: Color([0xeb, 0xcb, 0x8b, 0xff]),
+            track_color: Color([0x4c, 0x56, 0x6a, 0x80]),
+        }
+    }
+}
+
+pub struct Widget {
+    layer: LayerSurface,
+    /// Size given by the last configure
+    size: Option<(u32, u32)>,
+    /// Redraws the ring every second
+    tick_token: RegistrationToken,
+}
+
+/// Blend a ring filling the canvas, the first `progress` of it clockwise from the top in
+/// `color` and the rest in `track`
+fn draw_ring(
+    canvas: &mut [u8],
+    width: u32,
+    height: u32,
+    thickness: f32,
+    progress: f32,
+    color: Color,
+    track: Color,
+) {
+    let (color, track) = (color.to_argb8888(), track.to_argb8888());
+    let (center_x, center_y) = (width as f32 / 2.0, height as f32 / 2.0);
+    let outer = center_x.min(center_y) - 0.5;
+    let inner = outer - thickness;
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - center_x, y as f32 + 0.5 - center_y);
+            let distance = dx.hypot(dy);
+            let coverage = (outer - distance + 0.5)
+                .min(distance - inner + 0.5)
+                .clamp(0.0, 1.0);
+            if coverage == 0.0 {
+                continue;
+            }
+            // clockwise from the top, from 0 to 1
+            let angle = (dx.atan2(-dy) / (2.0 * PI)).rem_euclid(1.0);
+            let pixel = if angle < progress { color } else { track };
+            let i = (y * width + x) as usize * 4;
+            blend(&mut canvas[i..i + 4], pixel, coverage);
+        }
+    }
+}
+
+impl Passata {
+    pub fn show_widget(&mut self) {
+        if self.widget.is_some() {
+            return;
+        }
+        let config = &self.config.widget;
+        let (Some(overlay), Some(wayland)) = (&self.overlay, &self.wayland) else {
+            warn!("the widget is not available in this session");
+            return;
+        };
+        let output = config.output.as_ref().and_then(|name| {
+            let output = wayland.output_state.outputs().find(|output| {
+                wayland
+                    .output_state
+                    .info(output)
+                    .and_then(|info| info.name)
+                    .is_some_and(|output_name| &output_name == name)
+            });
+            if output.is_none() {
+                warn!("no output called {name}, the compositor chooses where the widget goes");
+            }
+            output
+        });
+        let layer = overlay.create_layer(
+            &wayland.qh,
+            Layer::Overlay,
+            "passata-widget",
+            output.as_ref(),
+        );
+        layer.set_anchor(config.corner.anchor());
+        layer.set_size(config.size, config.size);
+        layer.set_margin(config.margin, config.margin, config.margin, config.margin);
+        layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+        // an empty input region lets the clicks through to the windows below
+        match Region::new(overlay.compositor()) {
+            Ok(region) => layer.set_input_region(Some(region.wl_region())),
+            Err(err) => warn!("the widget will catch the clicks: {err}"),
+        }
+        layer.commit();
+        let res = self.loop_handle.insert_source(
+            Timer::from_duration(Duration::from_secs(1)),
+            |_, _, state: &mut Passata| {
+                state.redraw_widget();
+                TimeoutAction::ToDuration(Duration::from_secs(1))
+            },
+        );
+        let tick_token = match res {
+            Ok(token) => token,
+            Err(err) => {
+                warn!("the widget will not be updated: {}", err.error);
+                return;
+            }
+        };
+        debug!("showing the widget");
+        self.widget = Some(Widget {
+            layer,
+            size: None,
+            tick_token,
+        });
+    }
+
+    pub fn hide_widget(&mut self) {
+        if let Some(widget) = self.widget.take() {
+            debug!("hiding the widget");
+            self.loop_handle.remove(widget.tick_token);
+        }
+    }
+
+    pub fn toggle_widget(&mut self) {
+        if self.widget.is_some() {
+            self.hide_widget();
+        } else {
+            self.show_widget();
+        }
+    }
+
+    fn redraw_widget(&mut self) {
+        let config = &self.config.widget;
+        let color = if self.is_paused() {
+            config.paused_color
+        } else if self.phase == Phase::Work {
+            config.work_color
+        } else {
+            config.break_color
+        };
+        let progress = self.phase_percent() as f32 / 100.0;
+        let (thickness, track) = (config.thickness as f32, config.track_color);
+        let (Some(widget), Some(overlay)) = (&self.widget, &mut self.overlay) else {
+            return;
+        };
+        let Some((width, height)) = widget.size else {
+            return;
+        };
+        let res = overlay.paint(&widget.layer, width, height, |canvas| {
+            canvas.fill(0);
+            draw_ring(canvas, width, height, thickness, progress, color, track);
+        });
+        if let Err(err) = res {
+            warn!("drawing the widget: {err:?}");
+        }
+    }
+
+    /// Draw the widget at the size given by the compositor; false if `layer` is not the widget
+    pub fn widget_configured(&mut self, layer: &LayerSurface, (width, height): (u32, u32)) -> bool {
+        let Some(widget) = self.widget.as_mut().filter(|widget| &widget.layer == layer) else {
+            return false;
+        };
+        if width > 0 && height > 0 {
+            widget.size = Some((width, height));
+            self.redraw_widget();
+        }
+        true
+    }
+
+    /// Forget the widget closed by the compositor; false if `layer` is not the widget
+    pub fn widget_closed(&mut self, layer: &LayerSurface) -> bool {
+        if !self
+            .widget
+            .as_ref()
+            .is_some_and(|widget| &widget.layer == layer)
+        {
+            return false;
+        }
+        self.hide_widget();
+        true
+    }
+}
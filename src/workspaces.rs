@@ -0,0 +1,343 @@
+//! Track the focused workspace through the compositor IPC (sway or Hyprland), so that breaks can
+//! be deferred or delivered quietly on the workspaces marked in the config, and show the time
+//! left in the name of a workspace, in the bar the user already has.
+
+use std::{
+    env,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use color_eyre::{
+    eyre::{bail, ensure, ContextCompat},
+    Result,
+};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+
+use crate::{escalation::EscalationAction, status::Status, timer::Phase, Passata};
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct WorkspacesConfig {
+    /// Names of the workspaces where breaks should not interrupt
+    pub quiet: Vec<String>,
+    pub mode: WorkspaceMode,
+    /// Workspace whose name shows the minutes left, by name on sway and by id on Hyprland
+    pub countdown: Option<String>,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorkspaceMode {
+    /// Wait until a work workspace is focused before starting the break
+    #[default]
+    Defer,
+    /// Start the break without escalating until a work workspace is focused
+    Quiet,
+}
+
+const I3_MAGIC: &[u8] = b"i3-ipc";
+const I3_RUN_COMMAND: u32 = 0;
+const I3_GET_WORKSPACES: u32 = 1;
+const I3_SUBSCRIBE: u32 = 2;
+const I3_WORKSPACE_EVENT: u32 = 0x8000_0000;
+
+/// How often the name of the countdown workspace is refreshed, besides the status changes
+const COUNTDOWN_REFRESH: Duration = Duration::from_secs(30);
+
+enum Compositor {
+    Sway(PathBuf),
+    Hyprland(PathBuf),
+}
+
+impl Compositor {
+    fn detect() -> Option<Self> {
+        if let Some(socket) = env::var_os("SWAYSOCK") {
+            return Some(Self::Sway(socket.into()));
+        }
+        let signature = env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+        let dir = env::var_os("XDG_RUNTIME_DIR")
+            .map(|runtime_dir| Path::new(&runtime_dir).join("hypr").join(&signature))
+            .filter(|dir| dir.exists())
+            // Hyprland used to keep its sockets in /tmp
+            .unwrap_or_else(|| Path::new("/tmp/hypr").join(&signature));
+        Some(Self::Hyprland(dir))
+    }
+}
+
+/// Start following the focused workspace, if the compositor is supported
+pub fn watch(loop_handle: &LoopHandle<'static, Passata>) -> Result<()> {
+    let compositor =
+        Compositor::detect().context("workspaces are only supported on sway and Hyprland")?;
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg(workspace) => state.workspace_focused(workspace),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        let res = match compositor {
+            Compositor::Sway(socket) => follow_sway(&socket, sender),
+            Compositor::Hyprland(dir) => follow_hyprland(&dir, sender),
+        };
+        if let Err(err) = res {
+            warn!("stopped following the focused workspace: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+fn i3_send(stream: &mut UnixStream, kind: u32, payload: &[u8]) -> Result<()> {
+    let mut message = I3_MAGIC.to_vec();
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream.write_all(&message)?;
+    Ok(())
+}
+
+fn i3_read(stream: &mut UnixStream) -> Result<(u32, Value)> {
+    let mut header = [0; 14];
+    stream.read_exact(&mut header)?;
+    if &header[..6] != I3_MAGIC {
+        bail!("invalid message from the sway ipc");
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into()?);
+    let kind = u32::from_ne_bytes(header[10..14].try_into()?);
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload)?;
+    Ok((kind, serde_json::from_slice(&payload)?))
+}
+
+fn follow_sway(socket: &Path, sender: Sender<String>) -> Result<()> {
+    let mut stream = UnixStream::connect(socket)?;
+    i3_send(&mut stream, I3_GET_WORKSPACES, b"")?;
+    let (_, workspaces) = i3_read(&mut stream)?;
+    if let Some(name) = workspaces
+        .as_array()
+        .and_then(|workspaces| workspaces.iter().find(|w| w["focused"] == true))
+        .and_then(|workspace| workspace["name"].as_str())
+    {
+        sender.send(name.to_owned())?;
+    }
+    i3_send(&mut stream, I3_SUBSCRIBE, br#"["workspace"]"#)?;
+    let (_, reply) = i3_read(&mut stream)?;
+    if reply["success"] != true {
+        bail!("could not subscribe to the sway workspace events");
+    }
+    loop {
+        let (kind, event) = i3_read(&mut stream)?;
+        if kind != I3_WORKSPACE_EVENT || event["change"] != "focus" {
+            continue;
+        }
+        if let Some(name) = event["current"]["name"].as_str() {
+            sender.send(name.to_owned())?;
+        }
+    }
+}
+
+fn follow_hyprland(dir: &Path, sender: Sender<String>) -> Result<()> {
+    let mut stream = UnixStream::connect(dir.join(".socket.sock"))?;
+    stream.write_all(b"j/activeworkspace")?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    let active: Value = serde_json::from_str(&reply)?;
+    if let Some(name) = active["name"].as_str() {
+        sender.send(name.to_owned())?;
+    }
+    let events = BufReader::new(UnixStream::connect(dir.join(".socket2.sock"))?);
+    for line in events.lines() {
+        let line = line?;
+        let Some((event, data)) = line.split_once(">>") else {
+            continue;
+        };
+        let workspace = match event {
+            "workspace" => data,
+            // focusedmon>>MONITOR,WORKSPACE
+            "focusedmon" => match data.split_once(',') {
+                Some((_, workspace)) => workspace,
+                None => continue,
+            },
+            _ => continue,
+        };
+        sender.send(workspace.to_owned())?;
+    }
+    Ok(())
+}
+
+/// Shows the minutes left in the name of a workspace, renamed from a thread
+pub struct WorkspaceCountdown {
+    sender: mpsc::Sender<Status>,
+    thread: JoinHandle<()>,
+}
+
+impl WorkspaceCountdown {
+    /// Start renaming `workspace` with the time left, beginning with `status`
+    pub fn new(workspace: String, status: Status) -> Result<Self> {
+        let compositor = Compositor::detect()
+            .context("the workspace countdown is only supported on sway and Hyprland")?;
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            // the name currently given to the workspace, still showing a countdown if a previous
+            // daemon could not give it its name back
+            let prefix = format!("{workspace}: ");
+            let mut current = compositor
+                .workspace_names()
+                .inspect_err(|err| debug!("listing the workspaces: {err:?}"))
+                .ok()
+                .and_then(|names| names.into_iter().find(|name| name.starts_with(&prefix)))
+                .unwrap_or_else(|| workspace.clone());
+            let mut latest = status;
+            loop {
+                let name = countdown_name(&workspace, &latest);
+                if name != current {
+                    match compositor.rename(&workspace, &current, &name) {
+                        Ok(()) => current = name,
+                        Err(err) => debug!("renaming the workspace {workspace:?}: {err:?}"),
+                    }
+                }
+                match receiver.recv_timeout(COUNTDOWN_REFRESH) {
+                    Ok(status) => latest = status,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // the daemon is gone, give the workspace its name back
+                    Err(RecvTimeoutError::Disconnected) => {
+                        let _ = compositor.rename(&workspace, &current, &workspace);
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Self { sender, thread })
+    }
+
+    pub fn update(&self, status: Status) -> Result<()> {
+        self.sender.send(status)?;
+        Ok(())
+    }
+
+    /// Give the workspace its name back, waiting for it to be renamed
+    pub fn restore(self) {
+        let Self { sender, thread } = self;
+        drop(sender);
+        let _ = thread.join();
+    }
+}
+
+/// Name of `workspace` showing the minutes left in `status`, e.g. "1: break 4m"
+fn countdown_name(workspace: &str, status: &Status) -> String {
+    let minutes = status.clock().as_secs().div_ceil(60);
+    let phase = if status.phase == Phase::Work {
+        ""
+    } else {
+        "break "
+    };
+    let paused = if status.paused { " (paused)" } else { "" };
+    format!("{workspace}: {phase}{minutes}m{paused}")
+}
+
+impl Compositor {
+    /// Names of every workspace
+    fn workspace_names(&self) -> Result<Vec<String>> {
+        let workspaces = match self {
+            Compositor::Sway(socket) => {
+                let mut stream = UnixStream::connect(socket)?;
+                i3_send(&mut stream, I3_GET_WORKSPACES, b"")?;
+                i3_read(&mut stream)?.1
+            }
+            Compositor::Hyprland(dir) => {
+                let mut stream = UnixStream::connect(dir.join(".socket.sock"))?;
+                stream.write_all(b"j/workspaces")?;
+                let mut reply = String::new();
+                stream.read_to_string(&mut reply)?;
+                serde_json::from_str(&reply)?
+            }
+        };
+        Ok(workspaces
+            .as_array()
+            .context("the workspaces are not a list")?
+            .iter()
+            .filter_map(|workspace| Some(workspace["name"].as_str()?.to_owned()))
+            .collect())
+    }
+
+    /// Rename the workspace configured as `workspace` and now called `current` to `name`
+    fn rename(&self, workspace: &str, current: &str, name: &str) -> Result<()> {
+        match self {
+            Compositor::Sway(socket) => {
+                let mut stream = UnixStream::connect(socket)?;
+                let quote = |name: &str| format!("\"{}\"", name.replace('"', "\\\""));
+                // the workspace is created again with its own name once emptied and left
+                let mut olds = vec![current, workspace];
+                olds.dedup();
+                let command = olds
+                    .iter()
+                    .map(|old| format!("rename workspace {} to {}", quote(old), quote(name)))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                i3_send(&mut stream, I3_RUN_COMMAND, command.as_bytes())?;
+                let (_, reply) = i3_read(&mut stream)?;
+                let renamed = reply
+                    .as_array()
+                    .is_some_and(|results| results.iter().any(|result| result["success"] == true));
+                ensure!(renamed, "sway refused to rename it: {reply}");
+            }
+            Compositor::Hyprland(dir) => {
+                let mut stream = UnixStream::connect(dir.join(".socket.sock"))?;
+                write!(stream, "dispatch renameworkspace {workspace} {name}")?;
+                let mut reply = String::new();
+                stream.read_to_string(&mut reply)?;
+                ensure!(reply == "ok", "Hyprland refused to rename it: {reply}");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Passata {
+    /// Whether the break starting now is delivered quietly, in the quiet hours or on a quiet
+    /// workspace
+    pub fn break_is_quiet(&self) -> bool {
+        self.in_quiet_hours() || self.in_quiet_workspace(WorkspaceMode::Quiet)
+    }
+
+    /// Whether the focused workspace is one of the quiet ones with the given mode
+    pub fn in_quiet_workspace(&self, mode: WorkspaceMode) -> bool {
+        let config = &self.config.workspaces;
+        config.mode == mode
+            && self
+                .focused_workspace
+                .as_ref()
+                .is_some_and(|workspace| config.quiet.contains(workspace))
+    }
+
+    fn workspace_focused(&mut self, mut workspace: String) {
+        debug!("workspace {workspace:?} focused");
+        // the countdown workspace is known by its own name
+        if let Some(countdown) = &self.config.workspaces.countdown {
+            if workspace.starts_with(&format!("{countdown}: ")) {
+                workspace.clone_from(countdown);
+            }
+        }
+        self.focused_workspace = Some(workspace);
+        if let Err(err) = self.check_deferred_break() {
+            warn!("starting the deferred break: {err:?}");
+        }
+        if self.break_quiet && self.phase != Phase::Work && !self.break_is_quiet() {
+            info!("back on a work workspace, escalating the break");
+            self.break_quiet = false;
+            self.run_escalation_action(EscalationAction::Notification);
+            self.start_escalation();
+        }
+    }
+}
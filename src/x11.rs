@@ -0,0 +1,109 @@
+//! Idle time of the X11 sessions, where there is no Wayland compositor to send the idle
+//! notifications. The time since the last input comes from the MIT-SCREEN-SAVER extension,
+//! polled every second; the polling only needs a source of the idle time, so that other
+//! display servers without notifications can be added the same way.
+
+use std::{thread, time::Duration};
+
+use color_eyre::{
+    eyre::{ensure, WrapErr},
+    Result,
+};
+use log::warn;
+use smithay_client_toolkit::reexports::calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+use x11rb::{
+    connection::{Connection as _, RequestConnection as _},
+    protocol::{
+        screensaver::{self, ConnectionExt as _},
+        xproto::Window,
+    },
+    rust_connection::RustConnection,
+};
+
+use crate::{idle::IdleLevel, Passata};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Source of the time since the last input of the user
+trait IdleTime: Send + 'static {
+    fn idle_time(&mut self) -> Result<Duration>;
+}
+
+/// Connection to the X server, asking the MIT-SCREEN-SAVER extension for the idle time
+struct ScreenSaver {
+    connection: RustConnection,
+    /// Root window of the default screen
+    root: Window,
+}
+
+impl ScreenSaver {
+    /// Connect to the display in $DISPLAY
+    fn connect() -> Result<Self> {
+        let (connection, screen) = x11rb::connect(None).wrap_err("connecting to the X server")?;
+        ensure!(
+            connection
+                .extension_information(screensaver::X11_EXTENSION_NAME)?
+                .is_some(),
+            "the X server has no MIT-SCREEN-SAVER extension"
+        );
+        let root = connection.setup().roots[screen].root;
+        Ok(Self { connection, root })
+    }
+}
+
+impl IdleTime for ScreenSaver {
+    fn idle_time(&mut self) -> Result<Duration> {
+        let info = self.connection.screensaver_query_info(self.root)?.reply()?;
+        Ok(Duration::from_millis(info.ms_since_user_input.into()))
+    }
+}
+
+/// Start following the idle time of the X11 session, sending the changes of each of the idle
+/// `timeouts` like the Wayland idle notifications
+pub fn watch_idle(
+    loop_handle: &LoopHandle<'static, Passata>,
+    timeouts: Vec<(Duration, IdleLevel)>,
+) -> Result<()> {
+    watch(loop_handle, ScreenSaver::connect()?, timeouts)
+}
+
+fn watch(
+    loop_handle: &LoopHandle<'static, Passata>,
+    mut source: impl IdleTime,
+    timeouts: Vec<(Duration, IdleLevel)>,
+) -> Result<()> {
+    let (sender, channel) = channel::channel();
+    loop_handle
+        .insert_source(channel, |event, _, state| match event {
+            channel::Event::Msg((level, idled)) => state.idle_changed(&level, idled),
+            channel::Event::Closed => state.health.watchers_stopped += 1,
+        })
+        .map_err(|e| e.error)?;
+    thread::spawn(move || {
+        if let Err(err) = follow_idle(&mut source, &timeouts, sender) {
+            warn!("stopped following the X11 idle time: {err:?}");
+        }
+    });
+    Ok(())
+}
+
+fn follow_idle(
+    source: &mut impl IdleTime,
+    timeouts: &[(Duration, IdleLevel)],
+    sender: Sender<(IdleLevel, bool)>,
+) -> Result<()> {
+    let mut idled = vec![false; timeouts.len()];
+    loop {
+        let idle_for = source.idle_time()?;
+        for ((timeout, level), idled) in timeouts.iter().zip(&mut idled) {
+            if (idle_for >= *timeout) != *idled {
+                *idled = !*idled;
+                sender.send((*level, *idled))?;
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}